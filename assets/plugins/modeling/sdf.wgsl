@@ -4,6 +4,12 @@
 struct SdfMaterial {
     color: vec4<f32>,
     time: f32,
+    emission: f32,
+    roughness: f32,
+    // 0 = cube, 1 = sphere, 2 = torus, 3 = plane (see `Primitive::shader_id`)
+    primitive: u32,
+    // Inverse of the object's transform; marching happens in object space
+    local_from_world: mat4x4<f32>,
 };
 
 @group(2) @binding(0)
@@ -20,17 +26,25 @@ fn sdBox(p: vec3<f32>, b: vec3<f32>) -> f32 {
     return length(max(q, vec3<f32>(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0);
 }
 
+fn sdTorus(p: vec3<f32>, t: vec2<f32>) -> f32 {
+    let q = vec2<f32>(length(p.xz) - t.x, p.y);
+    return length(q) - t.y;
+}
+
 // --- Scene ---
 
+// Object-space distance. The bounding meshes spawned on the Rust side leave
+// a margin around these shapes for the animated ripple.
 fn map(p: vec3<f32>) -> f32 {
-    let sphere = sdSphere(p, 1.0);
-    let offset = vec3<f32>(sin(material.time) * 1.5, 0.0, 0.0);
-    let box = sdBox(p - offset, vec3<f32>(0.75));
-    
-    // Smooth Union
-    let k = 0.5;
-    let h = max(k - abs(sphere - box), 0.0) / k;
-    return min(sphere, box) - h * h * k * (1.0 / 4.0);
+    var d: f32;
+    switch material.primitive {
+        case 1u: { d = sdSphere(p, 1.0); }
+        case 2u: { d = sdTorus(p, vec2<f32>(1.0, 0.35)); }
+        case 3u: { d = sdBox(p, vec3<f32>(1.25, 0.02, 1.25)); }
+        default: { d = sdBox(p, vec3<f32>(1.0)); }
+    }
+    let ripple = sin(p.x * 6.0 + material.time) * sin(p.y * 6.0 + material.time) * sin(p.z * 6.0 + material.time);
+    return d + ripple * 0.02;
 }
 
 // --- Normal calculation ---
@@ -51,18 +65,20 @@ fn fragment(
     @location(1) world_normal: vec3<f32>,
     @location(2) uv: vec2<f32>,
 ) -> @location(0) vec4<f32> {
-    let view_pos = view.world_position;
-    let ray_origin = view_pos;
-    let ray_dir = normalize(world_position.xyz - view_pos);
+    let ray_dir = normalize(world_position.xyz - view.world_position);
+
+    // March in object space, starting where the ray enters the bounding mesh
+    let ray_origin = (material.local_from_world * vec4<f32>(world_position.xyz, 1.0)).xyz;
+    let local_dir = normalize((material.local_from_world * vec4<f32>(ray_dir, 0.0)).xyz);
 
     var t = 0.0;
-    let t_max = 20.0;
+    let t_max = 6.0;
     var hit = false;
     var p = ray_origin;
 
     // Raymarching loop
-    for (var i = 0; i < 128; i++) {
-        p = ray_origin + ray_dir * t;
+    for (var i = 0; i < 96; i++) {
+        p = ray_origin + local_dir * t;
         let d = map(p);
         if (d < 0.001) {
             hit = true;
@@ -73,10 +89,19 @@ fn fragment(
     }
 
     if (hit) {
-        let normal = calcNormal(p);
+        // Object-space normals go back to world space through the transposed inverse
+        let m = material.local_from_world;
+        let normal = normalize(transpose(mat3x3<f32>(m[0].xyz, m[1].xyz, m[2].xyz)) * calcNormal(p));
         let light_dir = normalize(vec3<f32>(5.0, 5.0, 5.0));
         let diff = max(dot(normal, light_dir), 0.1);
-        let color = material.color.rgb * diff;
+
+        // Blinn-Phong specular, sharper and brighter the smoother the surface
+        let view_dir = -ray_dir;
+        let half_dir = normalize(light_dir + view_dir);
+        let shininess = mix(128.0, 2.0, material.roughness);
+        let spec = pow(max(dot(normal, half_dir), 0.0), shininess) * (1.0 - material.roughness);
+
+        let color = material.color.rgb * (diff + material.emission) + vec3<f32>(spec);
         return vec4<f32>(color, 1.0);
     } else {
         discard;
@@ -1,7 +1,38 @@
 use std::fs;
 use std::path::Path;
+use std::process::Command;
+
+/// 导出构建信息 (git 提交、Bevy/egui 版本) 供 About 窗口使用
+fn emit_build_info() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=VERBIUM_GIT_HASH={}", git_hash);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+
+    let manifest: toml::Value = fs::read_to_string("Cargo.toml")
+        .ok()
+        .and_then(|c| toml::from_str(&c).ok())
+        .unwrap_or(toml::Value::Table(Default::default()));
+    let dep_version = |name: &str| -> String {
+        match manifest.get("dependencies").and_then(|d| d.get(name)) {
+            Some(toml::Value::String(v)) => v.clone(),
+            Some(toml::Value::Table(t)) => t.get("version").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
+            _ => "unknown".to_string(),
+        }
+    };
+    println!("cargo:rustc-env=VERBIUM_BEVY_VERSION={}", dep_version("bevy"));
+    println!("cargo:rustc-env=VERBIUM_EGUI_VERSION={}", dep_version("egui"));
+    println!("cargo:rerun-if-changed=Cargo.toml");
+}
 
 fn main() {
+    emit_build_info();
+
     let plugins_dir = "src/plugins";
     let dest_path = Path::new("src/plugins/generated.rs");
 
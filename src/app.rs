@@ -1,8 +1,12 @@
 use bevy::prelude::*;
+use bevy::window::{FileDragAndDrop, WindowCloseRequested, WindowTheme, WindowThemeChanged};
 use bevy_egui::EguiContexts;
-use egui_dock::{DockArea, DockState, Style, TabViewer};
-use crate::{Tab, Plugin, AppCommand, NotificationLevel};
+use egui_dock::{DockArea, DockState, Node, NodeIndex, Split, Style, TabViewer};
+use crate::{Tab, TabAction, TabInstance, Plugin, AppCommand, NotificationAction, NotificationLevel};
 use crate::plugins;
+use crate::config;
+use crate::keymap::{self, Keymap};
+use crate::palette::{CommandPalette, PaletteAction, PaletteEntry};
 
 // ----------------------------------------------------------------------------
 // Bevy Resources
@@ -11,11 +15,190 @@ use crate::plugins;
 #[derive(Resource)]
 pub struct PluginRegistry {
     pub instances: Vec<Box<dyn Plugin>>,
+    /// 在设置窗口中被停用的插件名，随会话保存
+    pub disabled: std::collections::HashSet<String>,
+}
+
+impl PluginRegistry {
+    /// 核心插件提供基础菜单，不能停用
+    pub fn can_disable(name: &str) -> bool {
+        name != "core"
+    }
+
+    /// 已启用的插件 (按依赖顺序)。停用的插件不参与菜单、更新与打开文件，但已打开的标签页不受影响
+    pub fn enabled(&self) -> impl Iterator<Item = &Box<dyn Plugin>> {
+        let disabled = &self.disabled;
+        self.instances.iter().filter(move |p| !disabled.contains(p.name()))
+    }
+
+    pub fn enabled_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Plugin>> {
+        let disabled = &self.disabled;
+        self.instances.iter_mut().filter(move |p| !disabled.contains(p.name()))
+    }
 }
 
 #[derive(Resource)]
 pub struct VerbiumDockState(pub DockState<Tab>);
 
+impl VerbiumDockState {
+    /// 以标签页 id 描述当前布局 (分割结构、各叶子中的标签页及其顺序)，用于持久化
+    /// 拖拽排序或跨叶子移动标签页都会移动 `Tab` 本身，因此 id 始终稳定
+    pub fn layout_snapshot(&self) -> DockState<u64> {
+        self.0.map_tabs(|tab| tab.id)
+    }
+
+    /// 按快照恢复布局，快照中找不到对应实例的标签页会被丢弃
+    pub fn from_snapshot(snapshot: &DockState<u64>, mut tabs: std::collections::HashMap<u64, Tab>) -> Self {
+        Self(snapshot.filter_map_tabs(|id| tabs.remove(id)))
+    }
+
+    /// 把所有标签页 (包括浮动窗口中的) 排成网格，每格一个标签页，原焦点标签页保持焦点。
+    /// 标签页实例被移动而不是克隆，因此共享后台资源的标签页 (如终端的 PTY) 不会被复制
+    pub fn tile_all(&mut self) {
+        let ids: Vec<u64> = self.0.iter_all_tabs().map(|(_, tab)| tab.id).collect();
+        if ids.len() <= 1 {
+            return;
+        }
+        let focused = self.0.find_active_focused().map(|(_, tab)| tab.id);
+
+        let mut tabs = std::collections::HashMap::new();
+        self.0.retain_tabs(|tab| {
+            let instance = std::mem::replace(&mut tab.instance, Box::new(DetachedTab));
+            tabs.insert(tab.id, Tab { instance, id: tab.id });
+            false
+        });
+        *self = Self::from_snapshot(&grid_layout(&ids), tabs);
+
+        if let Some(id) = focused {
+            self.focus_tab(id);
+        }
+    }
+
+    /// 激活并聚焦指定 id 的标签页，找不到时返回 false
+    pub fn focus_tab(&mut self, id: u64) -> bool {
+        let Some(location) = self.0.find_tab_from(|tab| tab.id == id) else { return false };
+        self.0.set_active_tab(location);
+        self.0.set_focused_node_and_surface((location.0, location.1));
+        true
+    }
+
+    /// 把标签页从所在节点移出，放到该节点 `direction` 一侧新分出的等分节点中并聚焦。
+    /// 所在节点只有这一个标签页时没有可分的内容，返回 false
+    pub fn split_tab(&mut self, id: u64, direction: Split) -> bool {
+        let Some((surface, node, index)) = self.0.find_tab_from(|tab| tab.id == id) else { return false };
+        if self.0[surface][node].tabs().is_none_or(|tabs| tabs.len() <= 1) {
+            return false;
+        }
+        let Some(tab) = self.0.remove_tab((surface, node, index)) else { return false };
+        let [_, new] = self.0.split((surface, node), direction, 0.5, Node::leaf(tab));
+        self.0.set_focused_node_and_surface((surface, new));
+        true
+    }
+}
+
+/// 焦点标签页能执行的操作，即焦点标签页具备所需能力的操作；没有焦点标签页时只有不需要能力的操作可用
+pub(crate) fn enabled_tab_actions<'a>(focused: Option<&dyn TabInstance>, actions: &'a [TabAction]) -> Vec<&'a TabAction> {
+    let capabilities = focused.map(|tab| tab.capabilities()).unwrap_or_default();
+    actions.iter().filter(|action| capabilities.contains(action.requires)).collect()
+}
+
+/// 按列优先顺序把标签页排成接近正方形的网格，各列等宽、列内各行等高
+fn grid_layout(ids: &[u64]) -> DockState<u64> {
+    let columns = (ids.len() as f32).sqrt().ceil() as usize;
+    let columns: Vec<&[u64]> = ids.chunks(ids.len().div_ceil(columns)).collect();
+
+    let mut dock = DockState::new(vec![columns[0][0]]);
+    let tree = dock.main_surface_mut();
+    // split 的比例是原节点保留的份额，依次切出剩余的列/行即可等分
+    let mut leaves = vec![NodeIndex::root()];
+    for (i, column) in columns.iter().enumerate().skip(1) {
+        let [left, right] = tree.split_right(leaves[i - 1], 1.0 / (columns.len() - i + 1) as f32, vec![column[0]]);
+        leaves[i - 1] = left;
+        leaves.push(right);
+    }
+    for (leaf, column) in leaves.into_iter().zip(&columns) {
+        let mut current = leaf;
+        for (j, id) in column.iter().enumerate().skip(1) {
+            let [_, below] = tree.split_below(current, 1.0 / (column.len() - j + 1) as f32, vec![*id]);
+            current = below;
+        }
+    }
+    dock
+}
+
+// ----------------------------------------------------------------------------
+// 会话持久化
+// ----------------------------------------------------------------------------
+
+/// 把布局、可恢复的标签页与工作区写入会话文件
+/// 没有描述的标签页 (例如运行命令的终端) 不被保存，它们也会从保存的布局中移除
+fn save_session(dock: &VerbiumDockState, workspace: &Workspace, registry: &PluginRegistry) -> Result<(), String> {
+    let mut tabs = toml::Table::new();
+    for (_, tab) in dock.0.iter_all_tabs() {
+        if let Some(descriptor) = tab.instance.descriptor() {
+            let mut entry = toml::Table::new();
+            entry.insert("plugin".to_string(), descriptor.plugin.into());
+            entry.insert("state".to_string(), descriptor.state.into());
+            tabs.insert(tab.id.to_string(), entry.into());
+        }
+    }
+    let mut layout = dock.layout_snapshot();
+    layout.retain_tabs(|id| tabs.contains_key(&id.to_string()));
+
+    let mut session = toml::Table::new();
+    if let Some(root) = &workspace.root {
+        session.insert("workspace".to_string(), root.to_string_lossy().into_owned().into());
+    }
+    session.insert("layout".to_string(), toml::Value::try_from(&layout).map_err(|e| e.to_string())?);
+    session.insert("tabs".to_string(), tabs.into());
+    let mut disabled: Vec<&String> = registry.disabled.iter().collect();
+    disabled.sort();
+    session.insert("disabled_plugins".to_string(), toml::Value::try_from(disabled).map_err(|e| e.to_string())?);
+    let content = toml::to_string(&session).map_err(|e| e.to_string())?;
+    std::fs::write(config::SESSION_FILE, content).map_err(|e| format!("{}: {}", config::SESSION_FILE, e))
+}
+
+/// 按会话文件重建布局：由保存时的插件重建各标签页，重建失败的标签页被丢弃
+fn restore_session(session: &toml::Table, ctx: &egui::Context, registry: &mut PluginRegistry) -> Result<VerbiumDockState, String> {
+    let Some(layout) = session.get("layout") else {
+        return Ok(VerbiumDockState(DockState::new(Vec::new())));
+    };
+    let mut layout: DockState<u64> = layout.clone().try_into().map_err(|e: toml::de::Error| e.to_string())?;
+
+    let mut tabs = std::collections::HashMap::new();
+    for (id, entry) in session.get("tabs").and_then(toml::Value::as_table).into_iter().flatten() {
+        let plugin = entry.get("plugin").and_then(toml::Value::as_str);
+        let state = entry.get("state").and_then(toml::Value::as_table);
+        let (Ok(id), Some(plugin), Some(state)) = (id.parse::<u64>(), plugin, state) else {
+            continue;
+        };
+        let instance = registry.instances.iter_mut()
+            .find(|p| p.name() == plugin)
+            .and_then(|p| p.restore_tab(ctx, state));
+        if let Some(instance) = instance {
+            tabs.insert(id, Tab::with_id(instance, id));
+        }
+    }
+    layout.retain_tabs(|id| tabs.contains_key(id));
+    Ok(VerbiumDockState::from_snapshot(&layout, tabs))
+}
+
+/// 平铺时临时占据被移走的标签页的位置，随即被丢弃
+#[derive(Debug)]
+struct DetachedTab;
+
+impl TabInstance for DetachedTab {
+    fn title(&self) -> egui::WidgetText {
+        egui::WidgetText::default()
+    }
+
+    fn ui(&mut self, _ui: &mut egui::Ui, _control: &mut Vec<AppCommand>) {}
+
+    fn box_clone(&self) -> Box<dyn TabInstance> {
+        Box::new(DetachedTab)
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct CommandQueue {
     pub queue: Vec<AppCommand>,
@@ -24,23 +207,210 @@ pub struct CommandQueue {
 #[derive(Resource, Default)]
 pub struct NotificationState {
     pub notifications: Vec<NotificationInstance>,
+    /// 上一帧鼠标悬停的通知 (`notifications` 中的下标)，它的倒计时暂停
+    hovered: Option<usize>,
 }
 
 pub struct NotificationInstance {
     pub message: String,
     pub level: NotificationLevel,
+    /// 剩余显示时间，错误通知为无穷大 (直到被关闭)
     pub remaining_time: f32,
+    pub actions: Vec<NotificationAction>,
+}
+
+impl NotificationInstance {
+    /// 最后这段时间内淡出
+    pub const FADE_OUT_SECS: f32 = 0.4;
+    /// 非错误通知的显示时间
+    pub const DURATION_SECS: f32 = 4.0;
+
+    /// 错误通知一直显示到被关闭，其余通知 `DURATION_SECS` 后消失
+    pub fn new(message: impl Into<String>, level: NotificationLevel) -> Self {
+        let remaining_time = match level {
+            NotificationLevel::Error => f32::INFINITY,
+            _ => Self::DURATION_SECS,
+        };
+        Self { message: message.into(), level, remaining_time, actions: Vec::new() }
+    }
+
+    /// 关闭通知：进入淡出阶段，已在淡出的通知保持原有进度
+    pub fn dismiss(&mut self) {
+        self.remaining_time = self.remaining_time.min(Self::FADE_OUT_SECS);
+    }
+}
+
+impl NotificationState {
+    /// 同时显示的通知数量上限，更早的通知合并为一条 "+N more"
+    pub const MAX_VISIBLE: usize = 5;
+
+    pub fn clear_all(&mut self) {
+        self.notifications.iter_mut().for_each(NotificationInstance::dismiss);
+    }
 }
 
 #[derive(Resource, Default)]
 pub struct ShowSettings(pub bool);
 
+/// 已请求退出 (关闭窗口或 `AppCommand::Quit`)，等待确认未保存的标签页
+#[derive(Resource, Default)]
+pub struct QuitRequest(pub bool);
+
+/// 界面主题，保存在全局设置的 `[core] theme` 中
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AppTheme {
+    #[default]
+    Dark,
+    Light,
+    /// 跟随系统，系统主题未知时使用深色
+    System,
+}
+
+impl AppTheme {
+    pub const ALL: [AppTheme; 3] = [AppTheme::Dark, AppTheme::Light, AppTheme::System];
+
+    pub fn from_config(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "dark" => Some(AppTheme::Dark),
+            "light" => Some(AppTheme::Light),
+            "system" => Some(AppTheme::System),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AppTheme::Dark => "dark",
+            AppTheme::Light => "light",
+            AppTheme::System => "system",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AppTheme::Dark => "Dark",
+            AppTheme::Light => "Light",
+            AppTheme::System => "Follow System",
+        }
+    }
+
+    fn resolve(self, system: Option<egui::Theme>) -> egui::Theme {
+        match self {
+            AppTheme::Dark => egui::Theme::Dark,
+            AppTheme::Light => egui::Theme::Light,
+            AppTheme::System => system.unwrap_or(egui::Theme::Dark),
+        }
+    }
+}
+
+/// 当前打开的工作区
+#[derive(Resource, Default)]
+pub struct Workspace {
+    pub root: Option<std::path::PathBuf>,
+}
+
+/// 正拖放到窗口上方的文件，用于绘制提示层
+#[derive(Resource, Default)]
+pub struct DropHover {
+    pub paths: Vec<std::path::PathBuf>,
+}
+
+/// 全局设置，以及各插件加载配置后由 `Plugin::save_config` 导出的基准。
+/// 与基准不同的键就是用户在设置窗口中修改过的，由 `persist` 写回全局设置文件
+#[derive(Resource, Default)]
+pub struct Settings {
+    /// 全局设置文件的内容
+    pub global: toml::Table,
+    baseline: std::collections::HashMap<String, toml::Table>,
+    /// 各插件被工作区设置覆盖的键，对它们的修改只在本次运行中生效
+    workspace_keys: std::collections::HashMap<String, std::collections::BTreeSet<String>>,
+}
+
+impl Settings {
+    /// 把各插件相对基准修改过的设置写入全局设置文件，返回写入失败的错误信息
+    pub fn persist(&mut self, registry: &PluginRegistry) -> Vec<String> {
+        let mut errors = Vec::new();
+        for plugin in &registry.instances {
+            let Some(current) = plugin.save_config() else { continue };
+            let name = plugin.name();
+            let baseline = self.baseline.entry(name.to_string()).or_default();
+            let overridden = self.workspace_keys.get(name);
+            let changed: toml::Table = current
+                .into_iter()
+                .filter(|(key, value)| baseline.get(key) != Some(value) && !overridden.is_some_and(|keys| keys.contains(key)))
+                .collect();
+            if changed.is_empty() {
+                continue;
+            }
+            match config::set_global_values(name, &changed) {
+                Ok(()) => {
+                    if let Some(section) = self.global.entry(name).or_insert(toml::Value::Table(toml::Table::new())).as_table_mut() {
+                        section.extend(changed.clone());
+                    }
+                    baseline.extend(changed);
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        errors
+    }
+}
+
+/// 按 工作区 > 全局 > 默认值 的优先级重新加载各插件的配置，返回读取或写入失败的错误信息。
+/// 重新加载前先保存用户修改过的设置，以免被覆盖
+fn apply_plugin_configs(registry: &mut PluginRegistry, settings: &mut Settings, workspace: Option<&std::path::Path>) -> Vec<String> {
+    let mut errors = settings.persist(registry);
+    let mut load = |path: &std::path::Path| config::load_table(path).unwrap_or_else(|e| {
+        errors.push(e);
+        toml::Table::new()
+    });
+    settings.global = load(&config::global_settings_path());
+    let local = workspace
+        .map(|root| load(&root.join(config::WORKSPACE_SETTINGS_FILE)))
+        .unwrap_or_default();
+    for plugin in &mut registry.instances {
+        let plugin_config = config::resolve(&settings.global, &local, plugin.name());
+        plugin.load_config(&plugin_config);
+        let name = plugin.name().to_string();
+        settings.baseline.insert(name.clone(), plugin.save_config().unwrap_or_default());
+        settings.workspace_keys.insert(name, plugin_config.workspace_keys);
+    }
+    errors
+}
+
+/// 最近执行过的可重复命令，最新的在前
+#[derive(Resource, Default)]
+pub struct CommandHistory {
+    pub entries: std::collections::VecDeque<(String, AppCommand)>,
+}
+
+impl CommandHistory {
+    const MAX_ENTRIES: usize = 20;
+
+    pub fn record(&mut self, cmd: &AppCommand) {
+        let Some(label) = cmd.repeat_label() else { return };
+        self.entries.retain(|(l, _)| *l != label);
+        self.entries.push_front((label, cmd.clone()));
+        self.entries.truncate(Self::MAX_ENTRIES);
+    }
+
+    /// 依赖的文件已不存在的命令不再提供重复执行
+    pub fn applicable(&self) -> impl Iterator<Item = &(String, AppCommand)> {
+        self.entries.iter().filter(|(_, cmd)| match cmd {
+            AppCommand::OpenFile(path) | AppCommand::RevealInShell(path) => path.exists(),
+            _ => true,
+        })
+    }
+}
+
 // ----------------------------------------------------------------------------
 // TabViewer 实现 (保持不变)
 // ----------------------------------------------------------------------------
 
 struct VerbiumTabViewer<'a> {
     command_queue: &'a mut Vec<AppCommand>,
+    /// 有未保存修改、等待用户确认关闭的标签页
+    close_request: &'a mut Option<u64>,
 }
 
 impl<'a> TabViewer for VerbiumTabViewer<'a> {
@@ -64,7 +434,13 @@ impl<'a> TabViewer for VerbiumTabViewer<'a> {
         true
     }
 
-    fn on_close(&mut self, _tab: &mut Self::Tab) -> egui_dock::tab_viewer::OnCloseResponse {
+    fn on_close(&mut self, tab: &mut Self::Tab) -> egui_dock::tab_viewer::OnCloseResponse {
+        if tab.instance.is_dirty() {
+            // 先保留标签页，由确认框决定是否关闭
+            *self.close_request = Some(tab.id);
+            return egui_dock::tab_viewer::OnCloseResponse::Ignore;
+        }
+        tab.instance.on_close();
         egui_dock::tab_viewer::OnCloseResponse::Close
     }
 
@@ -79,6 +455,82 @@ impl<'a> TabViewer for VerbiumTabViewer<'a> {
     }
 }
 
+/// 依次显示状态栏的各项，项之间以分隔线隔开
+fn status_items_ui(ui: &mut egui::Ui, items: &[String]) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            ui.separator();
+        }
+        ui.label(egui::RichText::new(item).small());
+    }
+}
+
+/// 关闭确认框的选择
+enum CloseChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+/// "Save / Don't Save / Cancel" 确认框；标签页已不存在时直接放弃请求
+fn close_dirty_tab_ui(
+    ctx: &egui::Context,
+    dock_state: &mut VerbiumDockState,
+    queue: &mut Vec<AppCommand>,
+    close_request: &mut Option<u64>,
+    id: u64,
+) {
+    let Some((_, tab)) = dock_state.0.iter_all_tabs().find(|(_, tab)| tab.id == id) else {
+        *close_request = None;
+        return;
+    };
+    let name = tab.instance.title().text().trim_end_matches('*').trim().to_string();
+
+    let mut choice = None;
+    let modal = egui::Modal::new(egui::Id::new("confirm_close_tab")).show(ctx, |ui| {
+        ui.set_width(360.0);
+        ui.heading("Unsaved Changes");
+        ui.label(format!("Do you want to save the changes you made to {}?", name));
+        ui.weak("Your changes will be lost if you don't save them.");
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                choice = Some(CloseChoice::Save);
+            }
+            if ui.button("Don't Save").clicked() {
+                choice = Some(CloseChoice::Discard);
+            }
+            if ui.button("Cancel").clicked() {
+                choice = Some(CloseChoice::Cancel);
+            }
+        });
+    });
+    // Esc 或点击框外视为取消
+    let choice = choice.or_else(|| modal.should_close().then_some(CloseChoice::Cancel));
+
+    let close = match choice {
+        None => return,
+        Some(CloseChoice::Cancel) => false,
+        Some(CloseChoice::Discard) => true,
+        Some(CloseChoice::Save) => dock_state.0.iter_all_tabs_mut()
+            .find(|(_, tab)| tab.id == id)
+            .is_some_and(|(_, tab)| {
+                tab.instance.save(queue);
+                !tab.instance.is_dirty()
+            }),
+    };
+    if close {
+        dock_state.0.retain_tabs(|tab| {
+            let keep = tab.id != id;
+            if !keep {
+                tab.instance.on_close();
+            }
+            keep
+        });
+    }
+    *close_request = None;
+}
+
 // ----------------------------------------------------------------------------
 // Bevy Systems
 // ----------------------------------------------------------------------------
@@ -88,19 +540,238 @@ pub fn setup_camera(mut commands: Commands) {
 }
 
 pub fn setup_verbium(mut commands: Commands) {
-    let plugins = plugins::all_plugins();
-    commands.insert_resource(PluginRegistry { instances: plugins });
+    let (plugins, plugin_errors) = plugins::all_plugins();
+    // 停用的插件随会话保存，需要在任何插件钩子被调用之前读取
+    let session = config::load_table(std::path::Path::new(config::SESSION_FILE)).unwrap_or_default();
+    let disabled = session.get("disabled_plugins")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str().map(str::to_string))
+        .filter(|name| PluginRegistry::can_disable(name))
+        .collect();
+    let mut registry = PluginRegistry { instances: plugins, disabled };
+    // 上次的工作区也在这里直接恢复：插件配置只按它加载一次，且不产生通知与命令历史
+    let workspace = Workspace {
+        root: session.get("workspace")
+            .and_then(toml::Value::as_str)
+            .map(std::path::PathBuf::from)
+            .filter(|root| root.is_dir()),
+    };
+    let mut settings = Settings::default();
+    for error in apply_plugin_configs(&mut registry, &mut settings, workspace.root.as_deref()) {
+        warn!("Failed to load settings: {}", error);
+    }
+    if let Some(root) = &workspace.root {
+        for plugin in registry.enabled_mut() {
+            plugin.on_workspace_changed(Some(root));
+        }
+    }
+    let mut keymap = Keymap { bindings: keymap::core_bindings() };
+    for plugin in &registry.instances {
+        keymap.bindings.extend(plugin.keybindings());
+    }
+    commands.insert_resource(keymap);
+    commands.insert_resource(registry);
     commands.insert_resource(VerbiumDockState(DockState::new(Vec::new())));
-    commands.insert_resource(CommandQueue::default());
+    let queue = plugin_errors.into_iter()
+        .map(|message| AppCommand::notify(message, NotificationLevel::Error))
+        .collect();
+    commands.insert_resource(CommandQueue { queue });
     commands.insert_resource(NotificationState::default());
     commands.insert_resource(ShowSettings(false));
+    commands.insert_resource(QuitRequest::default());
+    let theme = settings.global.get("core")
+        .and_then(|core| core.get("theme")?.as_str().and_then(AppTheme::from_config))
+        .unwrap_or_default();
+    commands.insert_resource(theme);
+    commands.insert_resource(settings);
+    commands.insert_resource(CommandHistory::default());
+    commands.insert_resource(workspace);
+    commands.insert_resource(DropHover::default());
+}
+
+/// 首次拿到 egui 上下文时恢复上次退出时的会话 (终端等标签页重建时需要上下文)
+pub fn restore_session_system(
+    mut contexts: EguiContexts,
+    mut registry: ResMut<PluginRegistry>,
+    mut dock_state: ResMut<VerbiumDockState>,
+    mut notification_state: ResMut<NotificationState>,
+    mut is_restored: Local<bool>,
+) {
+    if *is_restored {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    *is_restored = true;
+
+    // 工作区已在 setup_verbium 中恢复，这里只重建布局与标签页
+    let result = config::load_table(std::path::Path::new(config::SESSION_FILE))
+        .and_then(|session| restore_session(&session, ctx, &mut registry));
+    match result {
+        Ok(restored) => *dock_state = restored,
+        Err(error) => notification_state.notifications.push(NotificationInstance::new(format!("Failed to restore session: {}", error), NotificationLevel::Error)),
+    }
+}
+
+/// 退出时保存会话
+pub fn save_session_system(
+    mut exits: MessageReader<AppExit>,
+    dock_state: Res<VerbiumDockState>,
+    workspace: Res<Workspace>,
+    registry: Res<PluginRegistry>,
+) {
+    if exits.is_empty() {
+        return;
+    }
+    exits.clear();
+    if let Err(error) = save_session(&dock_state, &workspace, &registry) {
+        warn!("Failed to save session: {}", error);
+    }
+}
+
+/// 处理退出请求：没有未保存的标签页时直接退出，否则列出它们并询问 "Save All / Don't Save / Cancel"。
+/// 窗口的关闭按钮不会直接关闭窗口 (`close_when_requested` 为 false)，而是经过这里
+pub fn quit_confirmation_system(
+    mut contexts: EguiContexts,
+    mut close_requests: MessageReader<WindowCloseRequested>,
+    mut quit: ResMut<QuitRequest>,
+    mut dock_state: ResMut<VerbiumDockState>,
+    mut command_queue: ResMut<CommandQueue>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if !close_requests.is_empty() {
+        close_requests.clear();
+        quit.0 = true;
+    }
+    if !quit.0 {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    let dirty: Vec<String> = dock_state.0.iter_all_tabs()
+        .filter(|(_, tab)| tab.instance.is_dirty())
+        .map(|(_, tab)| tab.instance.title().text().trim_end_matches('*').trim().to_string())
+        .collect();
+    if dirty.is_empty() {
+        quit.0 = false;
+        exit.write(AppExit::Success);
+        return;
+    }
+
+    let mut choice = None;
+    let modal = egui::Modal::new(egui::Id::new("confirm_quit")).show(ctx, |ui| {
+        ui.set_width(360.0);
+        ui.heading("Unsaved Changes");
+        ui.label(if dirty.len() == 1 {
+            "1 file has unsaved changes:".to_string()
+        } else {
+            format!("{} files have unsaved changes:", dirty.len())
+        });
+        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+            for name in &dirty {
+                ui.label(format!("• {}", name));
+            }
+        });
+        ui.weak("Your changes will be lost if you don't save them.");
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            if ui.button("Save All").clicked() {
+                choice = Some(CloseChoice::Save);
+            }
+            if ui.button("Don't Save").clicked() {
+                choice = Some(CloseChoice::Discard);
+            }
+            if ui.button("Cancel").clicked() {
+                choice = Some(CloseChoice::Cancel);
+            }
+        });
+    });
+    // Esc 或点击框外视为取消
+    let Some(choice) = choice.or_else(|| modal.should_close().then_some(CloseChoice::Cancel)) else {
+        return;
+    };
+    quit.0 = false;
+    let exit_now = match choice {
+        CloseChoice::Cancel => false,
+        CloseChoice::Discard => true,
+        CloseChoice::Save => {
+            for (_, tab) in dock_state.0.iter_all_tabs_mut() {
+                if tab.instance.is_dirty() {
+                    tab.instance.save(&mut command_queue.queue);
+                }
+            }
+            // 保存失败或取消了另存为的标签页仍为 dirty，此时放弃退出，由通知告知原因
+            dock_state.0.iter_all_tabs().all(|(_, tab)| !tab.instance.is_dirty())
+        }
+    };
+    if exit_now {
+        exit.write(AppExit::Success);
+    }
+}
+
+/// 设置窗口关闭时及退出时，把插件设置中修改过的项写入全局设置文件
+pub fn persist_settings_system(
+    mut exits: MessageReader<AppExit>,
+    show_settings: Res<ShowSettings>,
+    registry: Res<PluginRegistry>,
+    mut settings: ResMut<Settings>,
+    mut notification_state: ResMut<NotificationState>,
+    mut was_open: Local<bool>,
+) {
+    let closed = *was_open && !show_settings.0;
+    *was_open = show_settings.0;
+    let exiting = !exits.is_empty();
+    exits.clear();
+    if !closed && !exiting {
+        return;
+    }
+    for error in settings.persist(&registry) {
+        warn!("Failed to save settings: {}", error);
+        notification_state.notifications.push(NotificationInstance::new(format!("Failed to save settings: {}", error), NotificationLevel::Error));
+    }
+}
+
+/// 处理拖放到窗口的文件：文件在编辑器中打开，第一个文件夹设为工作区并在资源管理器中打开
+pub fn file_drop_system(
+    mut events: MessageReader<FileDragAndDrop>,
+    mut hover: ResMut<DropHover>,
+    mut command_queue: ResMut<CommandQueue>,
+) {
+    let mut dropped = Vec::new();
+    for event in events.read() {
+        match event {
+            FileDragAndDrop::HoveredFile { path_buf, .. } => {
+                if !hover.paths.contains(path_buf) {
+                    hover.paths.push(path_buf.clone());
+                }
+            }
+            FileDragAndDrop::DroppedFile { path_buf, .. } => dropped.push(path_buf.clone()),
+            FileDragAndDrop::HoveredFileCanceled { .. } => hover.paths.clear(),
+        }
+    }
+    if dropped.is_empty() {
+        return;
+    }
+    hover.paths.clear();
+
+    if let Some(dir) = dropped.iter().find(|p| p.is_dir()) {
+        command_queue.queue.push(AppCommand::OpenWorkspace(dir.clone()));
+        command_queue.queue.push(AppCommand::OpenFile(dir.clone()));
+    }
+    for path in dropped.into_iter().filter(|p| !p.is_dir()) {
+        command_queue.queue.push(AppCommand::OpenFile(path));
+    }
 }
 
 pub fn update_plugins_system(
     mut registry: ResMut<PluginRegistry>,
     mut command_queue: ResMut<CommandQueue>,
 ) {
-    for plugin in &mut registry.instances {
+    for plugin in registry.enabled_mut() {
         plugin.update(&mut command_queue.queue);
     }
 }
@@ -111,6 +782,11 @@ pub fn process_commands_system(
     mut registry: ResMut<PluginRegistry>,
     mut notification_state: ResMut<NotificationState>,
     mut show_settings: ResMut<ShowSettings>,
+    mut history: ResMut<CommandHistory>,
+    mut workspace: ResMut<Workspace>,
+    mut settings: ResMut<Settings>,
+    mut keymap: ResMut<Keymap>,
+    mut quit: ResMut<QuitRequest>,
     mut contexts: EguiContexts,
 ) {
     let Ok(ctx) = contexts.ctx_mut() else {
@@ -123,29 +799,40 @@ pub fn process_commands_system(
             AppCommand::OpenTab(tab) => {
                 dock_state.0.main_surface_mut().push_to_focused_leaf(tab.clone());
             }
-            AppCommand::TileAll => {
-                let mut all_tabs = Vec::new();
-                dock_state.0.retain_tabs(|tab| {
-                    all_tabs.push(tab.clone());
-                    true
-                });
-                if !all_tabs.is_empty() {
-                    dock_state.0 = DockState::new(all_tabs);
-                }
-            }
+            AppCommand::TileAll => dock_state.tile_all(),
             AppCommand::ResetLayout => {
                 dock_state.0 = DockState::new(Vec::new());
             }
-            AppCommand::CloseTab(title) => {
+            AppCommand::CloseTab(id) => {
                 dock_state.0.retain_tabs(|tab| {
-                    tab.instance.title().text() != title
+                    let keep = tab.id != *id;
+                    if !keep {
+                        tab.instance.on_close();
+                    }
+                    keep
                 });
             }
+            AppCommand::SplitTab { id, direction } => {
+                dock_state.split_tab(*id, *direction);
+            }
+            AppCommand::FocusTab(id) => {
+                dock_state.focus_tab(*id);
+            }
             AppCommand::OpenFile(path) => {
-                for plugin in &mut registry.instances {
-                    if let Some(instance) = plugin.try_open_file(path) {
-                        dock_state.0.main_surface_mut().push_to_focused_leaf(Tab::new(instance));
-                        break;
+                // 已经打开的文件只聚焦其标签页，避免同一文件的两个编辑器各自修改
+                let open = dock_state.0.iter_all_tabs()
+                    .find(|(_, tab)| tab.instance.matches_path(path))
+                    .map(|(_, tab)| tab.id);
+                if let Some(id) = open {
+                    dock_state.focus_tab(id);
+                } else {
+                    let instance = registry.enabled_mut().find_map(|plugin| plugin.try_open_file(path));
+                    match instance {
+                        Some(instance) => dock_state.0.main_surface_mut().push_to_focused_leaf(Tab::new(instance)),
+                        None => notification_state.notifications.push(NotificationInstance::new(
+                            format!("No plugin can open {}", path.display()),
+                            NotificationLevel::Warning,
+                        )),
                     }
                 }
             }
@@ -178,17 +865,67 @@ pub fn process_commands_system(
             AppCommand::CopyToClipboard(text) => {
                 ctx.copy_text(text.clone());
             }
-            AppCommand::Notify { message, level } => {
-                notification_state.notifications.push(NotificationInstance {
-                    message: message.clone(),
-                    level: level.clone(),
-                    remaining_time: 4.0,
-                });
+            AppCommand::Notify { message, level, actions } => {
+                let mut notification = NotificationInstance::new(message.clone(), level.clone());
+                notification.actions = actions.clone();
+                notification_state.notifications.push(notification);
             }
             AppCommand::ToggleSettings => {
                 show_settings.0 = !show_settings.0;
             }
+            AppCommand::OpenUrl(url) => {
+                ctx.open_url(egui::OpenUrl::new_tab(url));
+            }
+            AppCommand::Run { command, cwd } => {
+                let mut handled = false;
+                for plugin in registry.enabled_mut() {
+                    if let Some(instance) = plugin.try_run_command(ctx, command, cwd.as_deref()) {
+                        dock_state.0.main_surface_mut().push_to_focused_leaf(Tab::new(instance));
+                        handled = true;
+                        break;
+                    }
+                }
+                if !handled {
+                    notification_state.notifications.push(NotificationInstance::new("No plugin is available to run commands".to_string(), NotificationLevel::Warning));
+                }
+            }
+            AppCommand::OpenWorkspace(path) => {
+                workspace.root = Some(path.clone());
+                let errors = apply_plugin_configs(&mut registry, &mut settings, workspace.root.as_deref());
+                for plugin in registry.enabled_mut() {
+                    plugin.on_workspace_changed(Some(path));
+                }
+                let has_settings = path.join(config::WORKSPACE_SETTINGS_FILE).exists();
+                let message = format!(
+                    "Workspace: {}{}",
+                    path.file_name().unwrap_or_default().to_string_lossy(),
+                    if has_settings { " (workspace settings applied)" } else { "" },
+                );
+                notification_state.notifications.push(NotificationInstance::new(message, NotificationLevel::Info));
+                for error in errors {
+                    notification_state.notifications.push(NotificationInstance::new(format!("Failed to load settings: {}", error), NotificationLevel::Error));
+                }
+            }
+            AppCommand::CloseWorkspace => {
+                if workspace.root.take().is_some() {
+                    let errors = apply_plugin_configs(&mut registry, &mut settings, None);
+                    for plugin in registry.enabled_mut() {
+                        plugin.on_workspace_changed(None);
+                    }
+                    for error in errors {
+                        notification_state.notifications.push(NotificationInstance::new(format!("Failed to load settings: {}", error), NotificationLevel::Error));
+                    }
+                }
+            }
+            AppCommand::Quit => quit.0 = true,
+            AppCommand::Rebind { action, shortcut } => {
+                let conflicts = keymap.rebind(action, *shortcut);
+                if !conflicts.is_empty() {
+                    notification_state.notifications.push(NotificationInstance::new(format!("{} is also bound to: {}", ctx.format_shortcut(shortcut), conflicts.join(", ")), NotificationLevel::Warning));
+                }
+            }
         }
+        history.record(cmd);
         i += 1;
     }
     command_queue.queue.clear();
@@ -201,42 +938,167 @@ pub fn ui_system(
     mut command_queue: ResMut<CommandQueue>,
     mut notification_state: ResMut<NotificationState>,
     mut show_settings: ResMut<ShowSettings>,
+    history: Res<CommandHistory>,
+    workspace: Res<Workspace>,
+    drop_hover: Res<DropHover>,
+    keymap: Res<Keymap>,
     time: Res<Time>,
+    mut focused_tab: Local<Option<u64>>,
+    mut palette: Local<CommandPalette>,
+    mut close_request: Local<Option<u64>>,
+    mut theme: ResMut<AppTheme>,
+    (mut window_theme_changes, mut system_theme): (MessageReader<WindowThemeChanged>, Local<Option<egui::Theme>>),
 ) {
     let Ok(ctx) = contexts.ctx_mut() else {
         return;
     };
+    if keymap.is_changed() {
+        keymap.publish(ctx);
+    }
+
+    // 主题：跟随系统时优先使用窗口报告的系统主题变化。
+    // 代码编辑器的语法高亮与终端的默认配色都按当前 visuals 的深浅选择
+    for change in window_theme_changes.read() {
+        *system_theme = Some(match change.theme {
+            WindowTheme::Light => egui::Theme::Light,
+            WindowTheme::Dark => egui::Theme::Dark,
+        });
+    }
+    let resolved = theme.resolve(system_theme.or(ctx.system_theme()));
+    if ctx.theme() != resolved {
+        ctx.set_theme(resolved);
+    }
     let dt = time.delta_secs();
 
-    // 0. 更新通知时间
+    // 0. 更新通知时间：鼠标悬停的通知暂停倒计时，已关闭 (正在淡出) 的除外
+    let hovered = notification_state.hovered.take();
+    let mut index = 0;
     notification_state.notifications.retain_mut(|n| {
-        n.remaining_time -= dt;
+        let paused = hovered == Some(index) && n.remaining_time > NotificationInstance::FADE_OUT_SECS;
+        index += 1;
+        if !paused {
+            n.remaining_time -= dt;
+        }
         n.remaining_time > 0.0
     });
 
+    // 清除所有通知 (默认 Ctrl + Alt + N)
+    if keymap.consume(ctx, "core.clear_notifications") {
+        notification_state.clear_all();
+    }
+
+    // 重复上一个操作 (默认 Ctrl + Shift + R)
+    if keymap.consume(ctx, "core.repeat_last_action") {
+        if let Some((_, cmd)) = history.applicable().next() {
+            command_queue.queue.push(cmd.clone());
+        }
+    }
+
+    // 插件声明的标签页操作按焦点标签页的能力启用
+    let tab_actions: Vec<TabAction> = registry.enabled().flat_map(|p| p.tab_actions()).collect();
+    let enabled_actions = enabled_tab_actions(dock_state.0.find_active_focused().map(|(_, tab)| tab.instance.as_ref()), &tab_actions);
+    let is_enabled = |action: &TabAction| enabled_actions.iter().any(|a| a.action == action.action);
+    let mut triggered_action = None;
+
+    // 命令面板 (默认 Ctrl + Shift + P)：插件命令与焦点标签页当前可用的操作
+    if keymap.consume(ctx, "core.command_palette") {
+        if palette.open {
+            palette.open = false;
+        } else {
+            palette.show();
+        }
+    }
+    if palette.open {
+        let shortcut_text = |action: &str| keymap.get(action).map(|s| ctx.format_shortcut(&s)).unwrap_or_default();
+        let mut entries: Vec<PaletteEntry> = registry.enabled()
+            .flat_map(|p| p.commands())
+            .map(|c| PaletteEntry {
+                label: format!("{}: {}", c.category, c.label),
+                shortcut: String::new(),
+                action: PaletteAction::Command(c.command),
+            })
+            .collect();
+        entries.extend(enabled_actions.iter().map(|a| PaletteEntry {
+            label: format!("File: {}", a.label),
+            shortcut: shortcut_text(&a.action),
+            action: PaletteAction::TabAction(a.action.clone()),
+        }));
+        match palette.ui(ctx, &entries) {
+            Some(PaletteAction::Command(cmd)) => command_queue.queue.push(cmd),
+            Some(PaletteAction::TabAction(action)) => triggered_action = Some(action),
+            None => {}
+        }
+    }
+
     // 1. 顶部栏渲染
     egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
         egui::MenuBar::new().ui(ui, |ui| {
             ui.menu_button("File", |ui| {
-                for plugin in &mut registry.instances {
+                ui.menu_button("Recent Actions", |ui| {
+                    let mut any = false;
+                    for (label, cmd) in history.applicable() {
+                        any = true;
+                        if ui.button(label).clicked() {
+                            command_queue.queue.push(cmd.clone());
+                            ui.close();
+                        }
+                    }
+                    if !any {
+                        ui.weak("No recent actions");
+                    }
+                });
+                if let Some(root) = &workspace.root {
+                    let name = root.file_name().unwrap_or_default().to_string_lossy();
+                    if ui.button(format!("Close Workspace ({})", name)).clicked() {
+                        command_queue.queue.push(AppCommand::CloseWorkspace);
+                        ui.close();
+                    }
+                }
+                if !tab_actions.is_empty() {
+                    ui.separator();
+                }
+                for action in &tab_actions {
+                    let shortcut = keymap.get(&action.action).map(|s| ui.ctx().format_shortcut(&s)).unwrap_or_default();
+                    let button = egui::Button::new(&action.label).shortcut_text(shortcut);
+                    if ui.add_enabled(is_enabled(action), button).clicked() {
+                        triggered_action = Some(action.action.clone());
+                        ui.close();
+                    }
+                }
+                ui.separator();
+                for plugin in registry.enabled_mut() {
                     plugin.on_file_menu(ui, &mut command_queue.queue);
                 }
             });
 
             ui.menu_button("Tab", |ui| {
-                for plugin in &mut registry.instances {
+                for plugin in registry.enabled_mut() {
                     plugin.on_tab_menu(ui, &mut command_queue.queue);
                 }
             });
 
-            for plugin in &mut registry.instances {
+            for plugin in registry.enabled_mut() {
                 plugin.on_menu_bar(ui, &mut command_queue.queue);
             }
+
+            ui.menu_button("Help", |ui| {
+                if ui.button("Keyboard Shortcuts").clicked() {
+                    let tab = Tab::new(Box::new(keymap::ShortcutsTab::default()));
+                    command_queue.queue.push(AppCommand::OpenTab(tab));
+                    ui.close();
+                }
+            });
         });
     });
 
+    if let Some(action) = triggered_action {
+        if let Some((_, tab)) = dock_state.0.find_active_focused() {
+            tab.instance.on_action(&action, &mut command_queue.queue);
+        }
+    }
+
     // 2. 全局 UI
-    for plugin in &mut registry.instances {
+    for plugin in registry.enabled_mut() {
         plugin.on_global_ui(ctx, &mut command_queue.queue);
     }
 
@@ -245,12 +1107,51 @@ pub fn ui_system(
         egui::Window::new("Settings")
             .open(&mut show_settings.0)
             .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Theme");
+                    for choice in AppTheme::ALL {
+                        if ui.selectable_value(&mut *theme, choice, choice.label()).changed() {
+                            if let Err(message) = config::set_global_value("core", "theme", choice.as_str()) {
+                                command_queue.queue.push(AppCommand::notify(message, NotificationLevel::Error));
+                            }
+                        }
+                    }
+                });
+                ui.separator();
+                if let Some(root) = &workspace.root {
+                    ui.label(format!(
+                        "Workspace overrides: {}",
+                        root.join(config::WORKSPACE_SETTINGS_FILE).display()
+                    ));
+                    ui.separator();
+                }
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    for plugin in &mut registry.instances {
+                    let PluginRegistry { instances, disabled } = &mut *registry;
+                    for plugin in instances {
                         let plugin_name = plugin.name().to_string();
+                        let mut enabled = !disabled.contains(&plugin_name);
+                        let header = if enabled {
+                            egui::RichText::new(&plugin_name)
+                        } else {
+                            egui::RichText::new(format!("{} (disabled)", plugin_name)).weak()
+                        };
                         ui.push_id(&plugin_name, |ui| {
-                            ui.collapsing(&plugin_name, |ui| {
-                                plugin.on_settings_ui(ui);
+                            ui.collapsing(header, |ui| {
+                                let toggle = egui::Checkbox::new(&mut enabled, "Enabled");
+                                if ui.add_enabled(PluginRegistry::can_disable(&plugin_name), toggle)
+                                    .on_hover_text("Disabled plugins keep their open tabs but add no menus and open no files")
+                                    .changed()
+                                {
+                                    if enabled {
+                                        disabled.remove(&plugin_name);
+                                    } else {
+                                        disabled.insert(plugin_name.clone());
+                                    }
+                                }
+                                if enabled {
+                                    ui.separator();
+                                    plugin.on_settings_ui(ui);
+                                }
                             });
                         });
                     }
@@ -258,10 +1159,26 @@ pub fn ui_system(
             });
     }
 
-    // 4. 中心 Dock 区域
+    // 4. 底部状态栏：焦点标签页提供的内容，须在中心区域之前布局
+    let status = dock_state.0.find_active_focused()
+        .and_then(|(_, tab)| tab.instance.status())
+        .unwrap_or_default();
+    egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            status_items_ui(ui, &status.left);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                // 从右向左布局，倒序后仍按声明的顺序显示
+                let right: Vec<String> = status.right.iter().rev().cloned().collect();
+                status_items_ui(ui, &right);
+            });
+        });
+    });
+
+    // 5. 中心 Dock 区域
     egui::CentralPanel::default().show(ctx, |ui| {
         let mut viewer = VerbiumTabViewer {
             command_queue: &mut command_queue.queue,
+            close_request: &mut *close_request,
         };
         let style = Style::from_egui(ui.style().as_ref());
 
@@ -273,20 +1190,78 @@ pub fn ui_system(
             .show_inside(ui, &mut viewer);
     });
 
-    // 5. 渲染通知
+    // 关闭有未保存修改的标签页前确认
+    if let Some(id) = *close_request {
+        close_dirty_tab_ui(ctx, &mut dock_state, &mut command_queue.queue, &mut *close_request, id);
+    }
+
+    // 拖放提示层
+    if !drop_hover.paths.is_empty() {
+        let folders = drop_hover.paths.iter().filter(|p| p.is_dir()).count();
+        let files = drop_hover.paths.len() - folders;
+        let text = match (folders, files) {
+            (0, 1) => "Open file".to_string(),
+            (0, n) => format!("Open {} files", n),
+            (_, 0) => "Open folder".to_string(),
+            (_, 1) => "Open folder and 1 file".to_string(),
+            (_, n) => format!("Open folder and {} files", n),
+        };
+        let screen = ctx.content_rect();
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("file_drop_overlay")));
+        painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+        painter.text(screen.center(), egui::Align2::CENTER_CENTER, text, egui::FontId::proportional(24.0), egui::Color32::WHITE);
+    }
+
+    // 焦点标签页变化时通知新旧标签页
+    let active = dock_state.0.find_active_focused().map(|(_, tab)| tab.id);
+    if active != *focused_tab {
+        for (_, tab) in dock_state.0.iter_all_tabs_mut() {
+            if Some(tab.id) == *focused_tab {
+                tab.instance.on_blur();
+            } else if Some(tab.id) == active {
+                tab.instance.on_focus();
+            }
+        }
+        *focused_tab = active;
+    }
+
+    // 6. 渲染通知：最新的在最上方，超出上限的较早通知合并为底部的 "+N more"
+    const SPACING: f32 = 5.0;
     let mut offset = egui::vec2(-10.0, -10.0);
-    for (i, n) in notification_state.notifications.iter().enumerate() {
+    let mut clear_all = false;
+    let hidden = notification_state.notifications.len().saturating_sub(NotificationState::MAX_VISIBLE);
+    if hidden > 0 {
+        let area = egui::Area::new(egui::Id::new("notification_more"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, offset)
+            .show(ctx, |ui| {
+                let messages: Vec<&str> = notification_state.notifications[..hidden].iter().map(|n| n.message.as_str()).collect();
+                egui::Frame::window(ui.style())
+                    .fill(egui::Color32::from_rgba_premultiplied(30, 30, 30, 230))
+                    .corner_radius(4.0)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new(format!("+{} more", hidden)).weak())
+                            .on_hover_text(messages.join("\n"));
+                    });
+            });
+        offset.y -= area.response.rect.height() + SPACING;
+    }
+    let mut hovered = None;
+    for (i, n) in notification_state.notifications.iter_mut().enumerate().skip(hidden) {
         let color = match n.level {
             NotificationLevel::Info => egui::Color32::from_rgb(100, 150, 255),
             NotificationLevel::Success => egui::Color32::from_rgb(100, 200, 100),
             NotificationLevel::Warning => egui::Color32::from_rgb(255, 200, 100),
             NotificationLevel::Error => egui::Color32::from_rgb(255, 100, 100),
         };
+        let opacity = (n.remaining_time / NotificationInstance::FADE_OUT_SECS).clamp(0.0, 1.0);
+        let mut clicked = false;
 
         let area_id = egui::Id::new("notification").with(i);
-        egui::Area::new(area_id)
+        let area = egui::Area::new(area_id)
             .anchor(egui::Align2::RIGHT_BOTTOM, offset)
             .show(ctx, |ui| {
+                ui.set_opacity(opacity);
+                ui.set_max_width(400.0);
                 egui::Frame::window(ui.style())
                     .fill(egui::Color32::from_rgba_premultiplied(30, 30, 30, 230))
                     .stroke(egui::Stroke::new(1.0, color))
@@ -300,11 +1275,49 @@ pub fn ui_system(
                                 NotificationLevel::Error => "❌",
                             };
                             ui.label(egui::RichText::new(icon).color(color).strong());
-                            ui.label(&n.message);
+                            ui.add(egui::Label::new(&n.message).wrap());
+                            if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                                n.dismiss();
+                            }
                         });
+                        if !n.actions.is_empty() {
+                            ui.horizontal(|ui| {
+                                for action in &n.actions {
+                                    if ui.button(&action.label).clicked() {
+                                        command_queue.queue.push(action.command.clone());
+                                        clicked = true;
+                                    }
+                                }
+                            });
+                        }
                     });
             });
-        offset.y -= 45.0;
+        if clicked {
+            n.dismiss();
+        }
+        if area.response.contains_pointer() {
+            hovered = Some(i);
+        }
+        // 按实际高度堆叠，多行消息不会互相重叠
+        offset.y -= area.response.rect.height() + SPACING;
+    }
+    notification_state.hovered = hovered;
+
+    if notification_state.notifications.len() > 1 {
+        egui::Area::new(egui::Id::new("notification_clear_all"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, offset)
+            .show(ctx, |ui| {
+                let mut button = ui.button("Clear All");
+                if let Some(shortcut) = keymap.get("core.clear_notifications") {
+                    button = button.on_hover_text(ctx.format_shortcut(&shortcut));
+                }
+                if button.clicked() {
+                    clear_all = true;
+                }
+            });
+    }
+    if clear_all {
+        notification_state.clear_all();
     }
 }
 
@@ -354,4 +1367,122 @@ pub fn setup_fonts_system(mut contexts: EguiContexts, mut is_setup: Local<bool>)
 
     ctx.set_fonts(fonts);
     *is_setup = true;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui_dock::SurfaceIndex;
+
+    /// 记录所属标签页 id 的空标签页，用于检查实例是否跟随标签页移动
+    #[derive(Debug)]
+    struct TestTab(u64);
+
+    impl TabInstance for TestTab {
+        fn title(&self) -> egui::WidgetText {
+            self.0.to_string().into()
+        }
+
+        fn ui(&mut self, _ui: &mut egui::Ui, _control: &mut Vec<AppCommand>) {}
+
+        fn box_clone(&self) -> Box<dyn TabInstance> {
+            Box::new(TestTab(self.0))
+        }
+    }
+
+    fn test_tab() -> Tab {
+        let mut tab = Tab::new(Box::new(TestTab(0)));
+        tab.instance = Box::new(TestTab(tab.id));
+        tab
+    }
+
+    /// 各叶子 (按遍历顺序) 中的标签页 id
+    fn arrangement<T>(dock: &DockState<T>, id: impl Fn(&T) -> u64) -> Vec<((SurfaceIndex, NodeIndex), Vec<u64>)> {
+        let mut leaves: Vec<((SurfaceIndex, NodeIndex), Vec<u64>)> = Vec::new();
+        for (location, tab) in dock.iter_all_tabs() {
+            match leaves.last_mut() {
+                Some((last, ids)) if *last == location => ids.push(id(tab)),
+                _ => leaves.push((location, vec![id(tab)])),
+            }
+        }
+        leaves
+    }
+
+    fn move_tab(dock: &mut DockState<Tab>, id: u64) -> Tab {
+        let location = dock.find_tab_from(|tab| tab.id == id).unwrap();
+        dock.remove_tab(location).unwrap()
+    }
+
+    #[test]
+    fn layout_snapshot_round_trips_through_toml() {
+        let (a, b, c) = (test_tab(), test_tab(), test_tab());
+        let (a_id, c_id) = (a.id, c.id);
+        let mut dock = DockState::new(vec![a, b, c]);
+        // c 移到右侧新分出的叶子，a 再移到 c 之后
+        let tab = move_tab(&mut dock, c_id);
+        let [_, right] = dock.main_surface_mut().split_right(NodeIndex::root(), 0.5, vec![tab]);
+        dock.set_focused_node_and_surface((SurfaceIndex::main(), right));
+        let tab = move_tab(&mut dock, a_id);
+        dock.push_to_focused_leaf(tab);
+        let state = VerbiumDockState(dock);
+        let before = arrangement(&state.0, |tab| tab.id);
+        assert_eq!(before.iter().map(|(_, ids)| ids.len()).collect::<Vec<_>>(), vec![1, 2]);
+
+        let value = toml::Value::try_from(state.layout_snapshot()).unwrap();
+        let text = toml::to_string(&toml::Table::from_iter([("layout".to_string(), value)])).unwrap();
+        let parsed: toml::Table = text.parse().unwrap();
+        let snapshot: DockState<u64> = parsed["layout"].clone().try_into().unwrap();
+        assert_eq!(arrangement(&snapshot, |id| *id), before);
+
+        let tabs = state.0.iter_all_tabs().map(|(_, tab)| (tab.id, tab.clone())).collect();
+        let restored = VerbiumDockState::from_snapshot(&snapshot, tabs);
+        assert_eq!(arrangement(&restored.0, |tab| tab.id), before);
+    }
+
+    fn focused_id(state: &mut VerbiumDockState) -> Option<u64> {
+        state.0.find_active_focused().map(|(_, tab)| tab.id)
+    }
+
+    #[test]
+    fn tile_all_keeps_every_tab_and_the_focus() {
+        let tabs: Vec<Tab> = (0..5).map(|_| test_tab()).collect();
+        let ids: std::collections::BTreeSet<u64> = tabs.iter().map(|tab| tab.id).collect();
+        let mut tabs = tabs.into_iter();
+        let mut dock = DockState::new(vec![tabs.next().unwrap(), tabs.next().unwrap()]);
+        dock.main_surface_mut().split_below(NodeIndex::root(), 0.5, vec![tabs.next().unwrap()]);
+        let window = dock.add_window(vec![tabs.next().unwrap(), tabs.next().unwrap()]);
+        dock.set_focused_node_and_surface((window, NodeIndex::root()));
+        let mut state = VerbiumDockState(dock);
+        let focused = focused_id(&mut state);
+        assert!(focused.is_some());
+
+        state.tile_all();
+
+        let tiled: Vec<(u64, String)> = state.0.iter_all_tabs()
+            .map(|(_, tab)| (tab.id, format!("{:?}", tab.instance)))
+            .collect();
+        assert_eq!(tiled.iter().map(|(id, _)| *id).collect::<std::collections::BTreeSet<_>>(), ids);
+        // 每个实例都是原来的那个，没有 DetachedTab 占位留下
+        for (id, instance) in &tiled {
+            assert_eq!(*instance, format!("{:?}", TestTab(*id)));
+        }
+        // 每格一个标签页，全部位于主区域
+        let leaves = arrangement(&state.0, |tab| tab.id);
+        assert_eq!(leaves.len(), ids.len());
+        assert!(leaves.iter().all(|((surface, _), ids)| *surface == SurfaceIndex::main() && ids.len() == 1));
+        assert_eq!(focused_id(&mut state), focused);
+    }
+
+    #[test]
+    fn tile_all_with_one_tab_is_a_no_op() {
+        let tab = test_tab();
+        let id = tab.id;
+        let mut state = VerbiumDockState(DockState::new(vec![tab]));
+        let before = arrangement(&state.0, |tab| tab.id);
+
+        state.tile_all();
+
+        assert_eq!(arrangement(&state.0, |tab| tab.id), before);
+        assert_eq!(before, vec![((SurfaceIndex::main(), NodeIndex::root()), vec![id])]);
+        assert_eq!(format!("{:?}", state.0.iter_all_tabs().next().unwrap().1.instance), format!("{:?}", TestTab(id)));
+    }
+}
@@ -0,0 +1,139 @@
+//! 插件配置：默认值 < 全局设置 < 工作区设置
+//!
+//! 配置文件为 TOML，每个插件一个以插件名命名的表，例如：
+//!
+//! ```toml
+//! [code_editor]
+//! word_wrap = true
+//!
+//! [code_editor.run_commands]
+//! rs = "cargo run --release"
+//! ```
+//!
+//! 合并按插件表的顶层键进行：工作区中出现的键整体覆盖全局中的同名键。
+//!
+//! 全局设置位于平台配置目录 (例如 Linux 的 `~/.config/verbium/settings.toml`)；
+//! 工作目录中存在 `verbium_settings.toml` 时改用它 (便携模式)。
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// 便携模式的全局设置文件 (相对于工作目录)，存在时优先于平台配置目录中的设置
+pub const GLOBAL_SETTINGS_FILE: &str = "verbium_settings.toml";
+/// 工作区设置文件 (相对于工作区根目录)
+pub const WORKSPACE_SETTINGS_FILE: &str = ".verbium/settings.toml";
+/// 退出时保存的会话 (布局、打开的标签页与工作区，相对于工作目录)
+pub const SESSION_FILE: &str = "verbium_session.toml";
+
+/// 合并后交给插件的配置
+#[derive(Debug, Clone, Default)]
+pub struct PluginConfig {
+    pub values: toml::Table,
+    /// 由工作区设置提供的键
+    pub workspace_keys: BTreeSet<String>,
+}
+
+impl PluginConfig {
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.values.get(key).and_then(toml::Value::as_bool)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.values.get(key).and_then(toml::Value::as_str)
+    }
+
+    pub fn get_integer(&self, key: &str) -> Option<i64> {
+        self.values.get(key).and_then(toml::Value::as_integer)
+    }
+
+    pub fn get_table(&self, key: &str) -> Option<&toml::Table> {
+        self.values.get(key).and_then(toml::Value::as_table)
+    }
+
+    /// 设置项的显示文本，被工作区覆盖的项带有标记
+    pub fn label(&self, text: &str, key: &str) -> egui::WidgetText {
+        if self.workspace_keys.contains(key) {
+            egui::RichText::new(format!("{} (workspace)", text))
+                .color(egui::Color32::from_rgb(120, 180, 255))
+                .into()
+        } else {
+            text.into()
+        }
+    }
+}
+
+/// 读取配置文件，文件不存在时返回空表
+pub fn load_table(path: &Path) -> Result<toml::Table, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => content
+            .parse::<toml::Table>()
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(toml::Table::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// 全局设置文件的位置：便携模式的文件存在时使用它，否则使用平台配置目录 (没有时退回便携模式的文件)
+pub fn global_settings_path() -> PathBuf {
+    let portable = Path::new(GLOBAL_SETTINGS_FILE);
+    if portable.exists() {
+        return portable.to_path_buf();
+    }
+    dirs::config_dir()
+        .map(|dir| dir.join("verbium").join("settings.toml"))
+        .unwrap_or_else(|| portable.to_path_buf())
+}
+
+/// 修改全局设置文件中 `[plugin]` 表的一个键，保留文件中的其余内容与注释
+pub fn set_global_value(plugin: &str, key: &str, value: impl Into<toml_edit::Value>) -> Result<(), String> {
+    edit_global(plugin, |table| {
+        table[key] = toml_edit::value(value);
+        Ok(())
+    })
+}
+
+/// 把 `values` 中的键写入全局设置文件的 `[plugin]` 表，其余内容与注释保持不变。
+/// 表类型的值写成 `[plugin.key]` 形式的子表
+pub fn set_global_values(plugin: &str, values: &toml::Table) -> Result<(), String> {
+    edit_global(plugin, |table| {
+        for (key, value) in values {
+            // toml::Value 的文本形式是合法的 TOML 值 (表为内联表)，借此转换为 toml_edit 的值
+            let value = value.to_string().parse::<toml_edit::Value>().map_err(|e| format!("{}.{}: {}", plugin, key, e))?;
+            table[key.as_str()] = match value {
+                toml_edit::Value::InlineTable(inline) => toml_edit::Item::Table(inline.into_table()),
+                value => toml_edit::Item::Value(value),
+            };
+        }
+        Ok(())
+    })
+}
+
+/// 读取全局设置文件，修改其中的 `[plugin]` 表后写回；文件或目录不存在时创建
+fn edit_global(plugin: &str, f: impl FnOnce(&mut toml_edit::Table) -> Result<(), String>) -> Result<(), String> {
+    let path = global_settings_path();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let mut doc = content.parse::<toml_edit::DocumentMut>().map_err(|e| format!("{}: {}", path.display(), e))?;
+    let table = doc.entry(plugin)
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or_else(|| format!("{}: [{}] is not a table", path.display(), plugin))?;
+    f(table)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&path, doc.to_string()).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// 合并指定插件的全局与工作区配置
+pub fn resolve(global: &toml::Table, workspace: &toml::Table, plugin: &str) -> PluginConfig {
+    let section = |table: &toml::Table| table.get(plugin).and_then(toml::Value::as_table).cloned().unwrap_or_default();
+    let mut values = section(global);
+    let overrides = section(workspace);
+    let workspace_keys = overrides.keys().cloned().collect();
+    values.extend(overrides);
+    PluginConfig { values, workspace_keys }
+}
@@ -0,0 +1,206 @@
+//! 单实例支持：再次启动 `verbium <路径>` 时，将路径转发给已在运行的实例并直接退出
+//!
+//! 运行中的实例监听回环地址上由系统分配的端口，并把端口与随机令牌写入当前用户的
+//! 运行时目录 (无则为配置目录) 下的 `verbium/instance.lock`。其他用户读不到该文件，
+//! 也就无法向本实例发送路径。协议为纯文本：客户端发送 `MAGIC` 行、令牌行及每行一个
+//! 绝对路径，服务端校验令牌并全部接收后回复 `OK`。
+
+use bevy::prelude::*;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::app::CommandQueue;
+use crate::AppCommand;
+
+const MAGIC: &str = "VERBIUM-OPEN 1";
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 命令行参数
+pub struct LaunchArgs {
+    /// `--new-window`：总是启动新进程，不转发给已运行的实例
+    pub new_window: bool,
+    /// 启动时要打开的文件 (已转换为绝对路径)
+    pub paths: Vec<PathBuf>,
+}
+
+impl LaunchArgs {
+    pub fn parse() -> Self {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let mut args = LaunchArgs { new_window: false, paths: Vec::new() };
+        for arg in std::env::args_os().skip(1) {
+            if arg == "--new-window" {
+                args.new_window = true;
+            } else {
+                let path = cwd.join(arg);
+                args.paths.push(std::fs::canonicalize(&path).unwrap_or(path));
+            }
+        }
+        args
+    }
+}
+
+/// 记录运行中实例端口与令牌的锁文件路径，按用户区分
+fn lock_file_path() -> Option<PathBuf> {
+    let dir = dirs::runtime_dir().or_else(dirs::config_dir)?;
+    Some(dir.join("verbium").join("instance.lock"))
+}
+
+/// 读取锁文件中的端口与令牌
+fn read_lock_file() -> Option<(u16, String)> {
+    let content = std::fs::read_to_string(lock_file_path()?).ok()?;
+    let (port, token) = content.trim().split_once(' ')?;
+    Some((port.parse().ok()?, token.to_string()))
+}
+
+/// 写入锁文件；Unix 上仅当前用户可读写
+fn write_lock_file(port: u16, token: &str) -> std::io::Result<()> {
+    let path = lock_file_path().ok_or_else(|| std::io::Error::other("no runtime or config directory"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // 旧文件的权限不受 mode 影响，这里再设置一次
+        if path.exists() {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(&path)?.write_all(format!("{} {}\n", port, token).as_bytes())
+}
+
+/// 生成 128 位随机令牌。`RandomState` 的密钥取自系统随机源，不必为此引入额外依赖
+fn random_token() -> String {
+    let mut token = String::new();
+    for _ in 0..2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+        token.push_str(&format!("{:016x}", hasher.finish()));
+    }
+    token
+}
+
+/// 尝试把路径转发给已运行的实例。
+/// 只有收到对方确认才返回 `true`；没有锁文件、实例已退出、端口被其他程序占用等情况均返回 `false`，
+/// 此时调用方应正常启动。
+pub fn forward_to_running_instance(paths: &[PathBuf]) -> bool {
+    let Some((port, token)) = read_lock_file() else { return false };
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let Ok(mut stream) = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(REPLY_TIMEOUT));
+
+    let mut message = format!("{}\n{}\n", MAGIC, token);
+    for path in paths {
+        message.push_str(&path.to_string_lossy());
+        message.push('\n');
+    }
+    if stream.write_all(message.as_bytes()).is_err() || stream.shutdown(std::net::Shutdown::Write).is_err() {
+        return false;
+    }
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).is_ok() && reply.trim() == "OK"
+}
+
+/// 待打开的路径 (启动参数与其他进程转发的路径)，由 `receive_paths_system` 转为
+/// `OpenFile` 命令 (目录则转为 `OpenWorkspace`)
+#[derive(Resource)]
+pub struct IncomingPaths {
+    receiver: Mutex<Receiver<PathBuf>>,
+    /// 本实例写入的令牌，退出时据此判断锁文件是否仍属于自己
+    token: Option<String>,
+}
+
+impl Drop for IncomingPaths {
+    fn drop(&mut self) {
+        let Some(token) = &self.token else { return };
+        // 锁文件可能已被之后启动的实例覆盖，只删除自己写入的
+        if read_lock_file().is_some_and(|(_, current)| &current == token) {
+            if let Some(path) = lock_file_path() {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// 创建路径通道；`listen` 为真时在后台线程监听其他实例的转发请求
+pub fn start(initial_paths: Vec<PathBuf>, listen: bool) -> IncomingPaths {
+    let (sender, receiver) = channel();
+    for path in initial_paths {
+        let _ = sender.send(path);
+    }
+
+    let token = if listen { listen_for_clients(sender) } else { None };
+    IncomingPaths { receiver: Mutex::new(receiver), token }
+}
+
+/// 绑定端口、写入锁文件并启动监听线程，返回写入的令牌。任一步失败都放弃单实例功能
+fn listen_for_clients(sender: Sender<PathBuf>) -> Option<String> {
+    let listener = match TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Single-instance listener unavailable: {}", e);
+            return None;
+        }
+    };
+    let port = listener.local_addr().ok()?.port();
+    let token = random_token();
+    if let Err(e) = write_lock_file(port, &token) {
+        warn!("Failed to write single-instance lock file: {}", e);
+        return None;
+    }
+
+    let expected: Arc<str> = Arc::from(token.as_str());
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // 每个客户端单独一个线程，慢客户端不会阻塞其他请求
+            let sender = sender.clone();
+            let expected = expected.clone();
+            std::thread::spawn(move || handle_client(stream, &expected, &sender));
+        }
+    });
+    Some(token)
+}
+
+fn handle_client(stream: TcpStream, token: &str, sender: &Sender<PathBuf>) {
+    let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+    let Ok(mut writer) = stream.try_clone() else { return };
+    let mut lines = BufReader::new(stream).lines();
+
+    if !matches!(lines.next(), Some(Ok(ref line)) if line == MAGIC) {
+        return;
+    }
+    if !matches!(lines.next(), Some(Ok(ref line)) if line == token) {
+        return;
+    }
+    for line in lines.map_while(Result::ok) {
+        if !line.is_empty() {
+            let _ = sender.send(PathBuf::from(line));
+        }
+    }
+    let _ = writer.write_all(b"OK\n");
+}
+
+pub fn receive_paths_system(incoming: Res<IncomingPaths>, mut queue: ResMut<CommandQueue>) {
+    let Ok(receiver) = incoming.receiver.lock() else { return };
+    for path in receiver.try_iter() {
+        if path.is_dir() {
+            queue.queue.push(AppCommand::OpenWorkspace(path));
+        } else {
+            queue.queue.push(AppCommand::OpenFile(path));
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! 快捷键表：核心与各插件声明的操作及其快捷键
+//!
+//! `Keymap` 资源是唯一的数据来源，由 `ui_system` 发布到 egui 上下文中，
+//! 插件与标签页通过 `consume` 按操作名查询，因此重新绑定后立即生效 (仅限本次运行)。
+
+use bevy::prelude::*;
+use egui::{Id, Key, KeyboardShortcut, Modifiers, Ui, WidgetText};
+use std::sync::Arc;
+use crate::{AppCommand, TabInstance};
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    /// 操作标识，以插件名为前缀，例如 `code_editor.save`
+    pub action: String,
+    /// 在快捷键列表中的分组
+    pub category: String,
+    pub label: String,
+    pub shortcut: KeyboardShortcut,
+}
+
+impl Binding {
+    pub fn new(action: &str, category: &str, label: &str, modifiers: Modifiers, key: Key) -> Self {
+        Self {
+            action: action.to_string(),
+            category: category.to_string(),
+            label: label.to_string(),
+            shortcut: KeyboardShortcut::new(modifiers, key),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Keymap {
+    pub bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    pub fn get(&self, action: &str) -> Option<KeyboardShortcut> {
+        self.bindings.iter().find(|b| b.action == action).map(|b| b.shortcut)
+    }
+
+    /// 修改绑定，返回与新快捷键冲突的其他操作
+    pub fn rebind(&mut self, action: &str, shortcut: KeyboardShortcut) -> Vec<String> {
+        let conflicts = self.bindings.iter()
+            .filter(|b| b.action != action && b.shortcut == shortcut)
+            .map(|b| b.label.clone())
+            .collect();
+        if let Some(binding) = self.bindings.iter_mut().find(|b| b.action == action) {
+            binding.shortcut = shortcut;
+        }
+        conflicts
+    }
+
+    /// 若本帧按下了 `action` 绑定的快捷键，消耗该按键并返回 true
+    pub fn consume(&self, ctx: &egui::Context, action: &str) -> bool {
+        let Some(shortcut) = self.get(action) else { return false };
+        ctx.input_mut(|i| i.consume_shortcut(&shortcut))
+    }
+
+    /// 发布到 egui 上下文，供插件与标签页读取
+    pub fn publish(&self, ctx: &egui::Context) {
+        ctx.data_mut(|d| d.insert_temp(keymap_id(), Arc::new(self.clone())));
+    }
+}
+
+fn keymap_id() -> Id {
+    Id::new("verbium_keymap")
+}
+
+/// 当前发布的快捷键表
+pub fn current(ctx: &egui::Context) -> Arc<Keymap> {
+    ctx.data(|d| d.get_temp::<Arc<Keymap>>(keymap_id())).unwrap_or_default()
+}
+
+/// 插件中处理快捷键的入口：`if keymap::consume(ui.ctx(), "my_plugin.action") { ... }`
+pub fn consume(ctx: &egui::Context, action: &str) -> bool {
+    current(ctx).consume(ctx, action)
+}
+
+/// 核心 (非插件) 操作的默认快捷键
+pub fn core_bindings() -> Vec<Binding> {
+    vec![
+        Binding::new("core.repeat_last_action", "General", "Repeat Last Action", Modifiers::COMMAND | Modifiers::SHIFT, Key::R),
+        Binding::new("core.clear_notifications", "General", "Clear All Notifications", Modifiers::COMMAND | Modifiers::ALT, Key::N),
+        Binding::new("core.command_palette", "General", "Command Palette", Modifiers::COMMAND | Modifiers::SHIFT, Key::P),
+    ]
+}
+
+// ----------------------------------------------------------------------------
+// 快捷键参考标签页
+// ----------------------------------------------------------------------------
+
+/// 只读的快捷键列表，点击快捷键可重新绑定
+#[derive(Debug, Clone, Default)]
+pub struct ShortcutsTab {
+    filter: String,
+    /// 正在等待新快捷键的操作
+    capturing: Option<String>,
+}
+
+impl ShortcutsTab {
+    /// 读取本帧按下的第一个按键；Esc 取消
+    fn capture_ui(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        let Some(action) = self.capturing.clone() else { return };
+        let pressed = ui.input_mut(|i| {
+            let pressed = i.events.iter().find_map(|e| match e {
+                egui::Event::Key { key, pressed: true, modifiers, .. } => Some(KeyboardShortcut::new(*modifiers, *key)),
+                _ => None,
+            });
+            // 捕获期间的按键不再传给其他控件
+            if pressed.is_some() {
+                i.events.clear();
+            }
+            pressed
+        });
+        match pressed {
+            Some(shortcut) if shortcut == KeyboardShortcut::new(Modifiers::NONE, Key::Escape) => self.capturing = None,
+            Some(shortcut) => {
+                control.push(AppCommand::Rebind { action, shortcut });
+                self.capturing = None;
+            }
+            None => {}
+        }
+    }
+}
+
+impl TabInstance for ShortcutsTab {
+    fn title(&self) -> WidgetText {
+        "Keyboard Shortcuts".into()
+    }
+
+    fn ui(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        self.capture_ui(ui, control);
+        let keymap = current(ui.ctx());
+
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("Filter by action or key").desired_width(240.0));
+        });
+        ui.label(egui::RichText::new("Click a shortcut to rebind it. Changes last for this session.").weak());
+        ui.separator();
+
+        let filter = self.filter.to_lowercase();
+        let ctx = ui.ctx().clone();
+        let matches = |b: &Binding| {
+            filter.is_empty()
+                || b.label.to_lowercase().contains(&filter)
+                || b.category.to_lowercase().contains(&filter)
+                || b.action.to_lowercase().contains(&filter)
+                || ctx.format_shortcut(&b.shortcut).to_lowercase().contains(&filter)
+        };
+
+        // 分组按首次出现的顺序排列
+        let mut categories: Vec<&str> = Vec::new();
+        for binding in &keymap.bindings {
+            if !categories.contains(&binding.category.as_str()) {
+                categories.push(&binding.category);
+            }
+        }
+
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            let mut any = false;
+            for category in categories {
+                let rows: Vec<&Binding> = keymap.bindings.iter().filter(|b| b.category == category && matches(b)).collect();
+                if rows.is_empty() {
+                    continue;
+                }
+                any = true;
+                ui.heading(category);
+                egui::Grid::new(("shortcuts_grid", category)).num_columns(2).striped(true).min_col_width(200.0).show(ui, |ui| {
+                    for binding in rows {
+                        ui.label(&binding.label).on_hover_text(&binding.action);
+                        let text = if self.capturing.as_deref() == Some(binding.action.as_str()) {
+                            "Press a key… (Esc to cancel)".to_string()
+                        } else {
+                            ui.ctx().format_shortcut(&binding.shortcut)
+                        };
+                        if ui.button(egui::RichText::new(text).monospace()).clicked() {
+                            self.capturing = Some(binding.action.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.add_space(8.0);
+            }
+            if !any {
+                ui.weak("No matching shortcuts");
+            }
+        });
+    }
+
+    fn on_blur(&mut self) {
+        self.capturing = None;
+    }
+
+    fn box_clone(&self) -> Box<dyn TabInstance> {
+        Box::new(self.clone())
+    }
+}
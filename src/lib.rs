@@ -4,6 +4,10 @@ use std::sync::atomic::{AtomicU64, Ordering};
 
 pub mod plugins;
 pub mod app;
+pub mod config;
+pub mod ipc;
+pub mod keymap;
+pub mod palette;
 
 static NEXT_TAB_ID: AtomicU64 = AtomicU64::new(1);
 
@@ -17,10 +21,133 @@ pub trait TabInstance: Debug + Send + Sync {
     fn ui(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>);
     /// 标签页右键菜单钩子
     fn on_context_menu(&mut self, _ui: &mut Ui, _control: &mut Vec<AppCommand>) {}
+    /// 成为焦点标签页时调用 (在下一次 `ui` 之前)
+    fn on_focus(&mut self) {}
+    /// 不再是焦点标签页时调用
+    fn on_blur(&mut self) {}
+    /// 标签页被关闭时调用，用于立即释放子进程等资源 (标签页的克隆可能仍然存在，不能只依赖 Drop)
+    fn on_close(&mut self) {}
+    /// 有未保存的修改；为 true 时关闭前会询问是否保存
+    fn is_dirty(&self) -> bool {
+        false
+    }
+    /// 关闭确认框中选择 "Save" 时调用；之后仍为 dirty 则取消关闭
+    fn save(&mut self, _control: &mut Vec<AppCommand>) {}
+    /// 当前支持的操作，宿主据此启用或禁用针对焦点标签页的菜单项
+    fn capabilities(&self) -> TabCapabilities {
+        TabCapabilities::NONE
+    }
+    /// 执行插件通过 `Plugin::tab_actions` 声明的操作 (仅在标签页为焦点且具备所需能力时调用)
+    fn on_action(&mut self, _action: &str, _control: &mut Vec<AppCommand>) {}
+    /// 焦点标签页在底部状态栏显示的内容；返回 None 时状态栏留空
+    fn status(&self) -> Option<TabStatus> {
+        None
+    }
+    /// 退出时保存会话所用的描述，下次启动时交给 `Plugin::restore_tab` 重建；返回 None 的标签页不会被恢复
+    fn descriptor(&self) -> Option<TabDescriptor> {
+        None
+    }
+    /// 是否正在显示 `path`；为 true 时 `AppCommand::OpenFile` 聚焦该标签页，而不是再打开一份
+    fn matches_path(&self, _path: &std::path::Path) -> bool {
+        false
+    }
     /// 用于克隆 Trait 对象
     fn box_clone(&self) -> Box<dyn TabInstance>;
 }
 
+/// 两个路径是否指向同一个文件；写法不同 (相对路径、`..`、符号链接) 时比较规范化后的路径
+pub fn same_path(a: &std::path::Path, b: &std::path::Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+/// 标签页提供给状态栏的内容，每一项之间以分隔线隔开
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TabStatus {
+    /// 靠左显示，例如光标位置
+    pub left: Vec<String>,
+    /// 靠右显示，例如语言与换行符
+    pub right: Vec<String>,
+}
+
+/// 标签页能力的集合，可用 `|` 组合
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TabCapabilities(u32);
+
+impl TabCapabilities {
+    pub const NONE: Self = Self(0);
+    /// 可以保存 (例如未处于只读模式的编辑器)
+    pub const SAVEABLE: Self = Self(1);
+    /// 可以运行 (例如配置了运行命令的代码文件)
+    pub const RUNNABLE: Self = Self(1 << 1);
+    /// 存在非空的选区
+    pub const HAS_SELECTION: Self = Self(1 << 2);
+
+    /// 是否具备 `other` 中的全部能力
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for TabCapabilities {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for TabCapabilities {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// 插件贡献到 "File" 菜单、作用于焦点标签页的操作
+#[derive(Debug, Clone)]
+pub struct TabAction {
+    /// 操作标识，传给 `TabInstance::on_action`；与快捷键操作同名时菜单中会显示其快捷键
+    pub action: String,
+    pub label: String,
+    /// 焦点标签页必须具备的能力，否则菜单项被禁用
+    pub requires: TabCapabilities,
+}
+
+impl TabAction {
+    pub fn new(action: &str, label: &str, requires: TabCapabilities) -> Self {
+        Self { action: action.to_string(), label: label.to_string(), requires }
+    }
+}
+
+/// 插件贡献到命令面板的命令
+#[derive(Clone)]
+pub struct Command {
+    /// 在面板中的分组，显示为 `分组: 名称`
+    pub category: String,
+    pub label: String,
+    /// 选中后推入命令队列
+    pub command: AppCommand,
+}
+
+impl Command {
+    pub fn new(category: &str, label: &str, command: AppCommand) -> Self {
+        Self { category: category.to_string(), label: label.to_string(), command }
+    }
+}
+
+/// 可持久化的标签页描述
+#[derive(Debug, Clone)]
+pub struct TabDescriptor {
+    /// 负责重建该标签页的插件 (`Plugin::name`)
+    pub plugin: String,
+    /// 插件自行定义的状态，例如编辑器的文件路径
+    pub state: toml::Table,
+}
+
+impl TabDescriptor {
+    pub fn new(plugin: &str, state: toml::Table) -> Self {
+        Self { plugin: plugin.to_string(), state }
+    }
+}
+
 /// 包装器，用于在 egui_dock 中持有动态生成的 Tab
 pub struct Tab {
     pub instance: Box<dyn TabInstance>,
@@ -34,6 +161,13 @@ impl Tab {
             id: NEXT_TAB_ID.fetch_add(1, Ordering::SeqCst),
         }
     }
+
+    /// 使用已知 id 创建标签页 (例如从持久化的布局恢复)
+    /// 会推进 id 计数器，确保之后新建的标签页不会与其冲突
+    pub fn with_id(instance: Box<dyn TabInstance>, id: u64) -> Self {
+        NEXT_TAB_ID.fetch_max(id + 1, Ordering::SeqCst);
+        Self { instance, id }
+    }
 }
 
 impl Clone for Tab {
@@ -58,6 +192,7 @@ impl Debug for Tab {
 // 命令系统
 // ----------------------------------------------------------------------------
 
+#[derive(Clone)]
 pub enum AppCommand {
     /// 打开一个新的标签页
     OpenTab(Tab),
@@ -65,18 +200,77 @@ pub enum AppCommand {
     TileAll,
     /// 重置为初始布局
     ResetLayout,
-    /// 关闭指定标题的标签页（简单示例）
-    CloseTab(String),
+    /// 关闭指定 id (`Tab::id`) 的标签页
+    CloseTab(u64),
+    /// 把指定 id 的标签页移到其所在节点旁新分出的一侧并聚焦它；所在节点只有这一个标签页时不做任何事
+    SplitTab { id: u64, direction: egui_dock::Split },
+    /// 激活并聚焦指定 id 的标签页 (包括浮动窗口中的)
+    FocusTab(u64),
     /// 请求打开指定路径的文件
     OpenFile(std::path::PathBuf),
     /// 在系统文件管理器中定位
     RevealInShell(std::path::PathBuf),
     /// 将字符串拷贝到系统剪贴板
     CopyToClipboard(String),
-    /// 发送全局通知
-    Notify { message: String, level: NotificationLevel },
+    /// 发送全局通知，可附带操作按钮；错误通知一直显示到被关闭
+    Notify { message: String, level: NotificationLevel, actions: Vec<NotificationAction> },
     /// 切换设置窗口
     ToggleSettings,
+    /// 在新的终端标签页中运行命令，并在其中显示输出
+    Run { command: String, cwd: Option<std::path::PathBuf> },
+    /// 使用系统浏览器打开链接
+    OpenUrl(String),
+    /// 打开工作区：加载其 `.verbium/settings.toml` 并覆盖全局设置
+    OpenWorkspace(std::path::PathBuf),
+    /// 关闭当前工作区，恢复全局设置
+    CloseWorkspace,
+    /// 修改操作绑定的快捷键 (仅限本次运行)
+    Rebind { action: String, shortcut: egui::KeyboardShortcut },
+    /// 退出程序；有未保存的标签页时先请用户确认
+    Quit,
+}
+
+impl AppCommand {
+    /// 不带操作按钮的通知
+    pub fn notify(message: impl Into<String>, level: NotificationLevel) -> Self {
+        AppCommand::Notify { message: message.into(), level, actions: Vec::new() }
+    }
+
+    /// 可重复执行的命令返回用于展示的描述；内部、一次性或依赖已失效对象的命令返回 None
+    pub fn repeat_label(&self) -> Option<String> {
+        match self {
+            AppCommand::TileAll => Some("Tile All".to_string()),
+            AppCommand::ResetLayout => Some("Reset Layout".to_string()),
+            AppCommand::OpenFile(path) => Some(format!("Open {}", path.display())),
+            AppCommand::RevealInShell(path) => Some(format!("Reveal {}", path.display())),
+            AppCommand::ToggleSettings => Some("Toggle Settings".to_string()),
+            AppCommand::Run { command, .. } => Some(format!("Run: {}", command)),
+            AppCommand::OpenUrl(url) => Some(format!("Open {}", url)),
+            AppCommand::OpenWorkspace(path) => Some(format!("Open Workspace {}", path.display())),
+            AppCommand::OpenTab(_)
+            | AppCommand::CloseWorkspace
+            | AppCommand::Rebind { .. }
+            | AppCommand::CloseTab(_)
+            | AppCommand::SplitTab { .. }
+            | AppCommand::FocusTab(_)
+            | AppCommand::Quit
+            | AppCommand::CopyToClipboard(_)
+            | AppCommand::Notify { .. } => None,
+        }
+    }
+}
+
+/// 通知上的按钮，点击后执行 `command` 并关闭通知
+#[derive(Clone)]
+pub struct NotificationAction {
+    pub label: String,
+    pub command: AppCommand,
+}
+
+impl NotificationAction {
+    pub fn new(label: &str, command: AppCommand) -> Self {
+        Self { label: label.to_string(), command }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -106,6 +300,46 @@ pub trait Plugin: Send + Sync {
         None
     }
 
+    /// 尝试承载一条运行命令 (由 `AppCommand::Run` 触发)
+    /// 如果插件能够运行该命令，返回一个显示输出的 Tab 实例；否则返回 None
+    fn try_run_command(&mut self, _ctx: &Context, _command: &str, _cwd: Option<&std::path::Path>) -> Option<Box<dyn TabInstance>> {
+        None
+    }
+
+    /// 按 `TabInstance::descriptor` 保存的状态重建标签页 (启动恢复会话时调用)
+    /// 状态已失效 (例如文件已被删除) 时返回 None，该标签页会被跳过
+    fn restore_tab(&mut self, _ctx: &Context, _state: &toml::Table) -> Option<Box<dyn TabInstance>> {
+        None
+    }
+
+    /// 加载配置 (启动时及打开/关闭工作区时调用)
+    /// 插件应先恢复默认设置，再应用 `config` 中出现的键
+    fn load_config(&mut self, _config: &config::PluginConfig) {}
+
+    /// 以配置表的形式导出当前设置，键与 `load_config` 读取的一致。
+    /// 宿主在加载配置后记下一份，设置窗口关闭及退出时把与之不同的键写入全局设置文件 (被工作区覆盖的键除外)
+    fn save_config(&self) -> Option<toml::Table> {
+        None
+    }
+
+    /// 打开 (`Some`) 或关闭 (`None`) 工作区时调用，在 `load_config` 之后
+    fn on_workspace_changed(&mut self, _root: Option<&std::path::Path>) {}
+
+    /// 声明插件的快捷键操作及默认绑定，处理时使用 `keymap::consume` 按操作名查询
+    fn keybindings(&self) -> Vec<keymap::Binding> {
+        Vec::new()
+    }
+
+    /// 声明作用于焦点标签页的操作，由宿主显示在 "File" 菜单中并按焦点标签页的能力启用
+    fn tab_actions(&self) -> Vec<TabAction> {
+        Vec::new()
+    }
+
+    /// 声明命令面板 (默认 Ctrl + Shift + P) 中的命令，仅在面板打开时查询
+    fn commands(&self) -> Vec<Command> {
+        Vec::new()
+    }
+
     /// 注入到设置窗口的 UI
     fn on_settings_ui(&mut self, _ui: &mut Ui) {}
     
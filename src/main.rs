@@ -1,18 +1,30 @@
 use bevy::prelude::*;
 use bevy_egui::{EguiPlugin, EguiPrimaryContextPass};
 use verbium::app::*;
+use verbium::ipc;
 
 #[cfg(feature = "plugin_modeling")]
 use verbium::plugins::modeling;
+#[cfg(feature = "plugin_modeling")]
+use bevy::pbr::wireframe::WireframePlugin;
 
 fn main() {
+    // 单实例：已有实例在运行时把路径交给它打开，然后退出
+    let args = ipc::LaunchArgs::parse();
+    if !args.new_window && ipc::forward_to_running_instance(&args.paths) {
+        return;
+    }
+
     let mut app = App::new();
+    app.insert_resource(ipc::start(args.paths, !args.new_window));
     
     app.add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Verbium (Bevy)".into(),
                 ..default()
             }),
+            // 关闭窗口先经过 quit_confirmation_system 确认未保存的标签页
+            close_when_requested: false,
             ..default()
         }))
         .add_plugins(EguiPlugin::default());
@@ -22,21 +34,39 @@ fn main() {
 
     // 核心逻辑更新
     app.add_systems(Update, (
+            restore_session_system,
             update_plugins_system,
+            ipc::receive_paths_system,
+            file_drop_system,
             process_commands_system,
         ).chain());
 
+    // 退出时保存会话与设置
+    app.add_systems(Last, (save_session_system, persist_settings_system));
+
     // UI 渲染逻辑
     app.add_systems(EguiPrimaryContextPass, (
             setup_fonts_system,
             ui_system,
+            quit_confirmation_system,
         ).chain());
 
     #[cfg(feature = "plugin_modeling")]
     {
         app.add_plugins(MaterialPlugin::<modeling::SdfMaterial>::default())
+           .add_plugins(WireframePlugin::default())
+           .init_resource::<modeling::ModelingView>()
+           .init_gizmo_group::<modeling::ModelingGizmos>()
            .add_systems(Startup, modeling::setup_modeling_scene)
-           .add_systems(Update, (modeling::update_sdf_time, modeling::sync_modeling_viewport));
+           .add_systems(Update, (
+               modeling::update_sdf_time,
+               modeling::edit_sdf_material,
+               modeling::sdf_scene_io,
+               modeling::add_sdf_object,
+               modeling::capture_modeling_viewport,
+               (modeling::apply_render_mode, modeling::draw_modeling_gizmos).chain(),
+               (modeling::control_modeling_camera, modeling::sync_modeling_viewport).chain(),
+           ));
     }
 
     app.run();
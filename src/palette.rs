@@ -0,0 +1,140 @@
+//! 命令面板：汇总插件声明的命令 (`Plugin::commands`) 与焦点标签页可用的操作，
+//! 按输入模糊过滤，用方向键选择、Enter 执行
+
+use egui::{Key, Modifiers};
+use crate::AppCommand;
+
+/// 面板中的一项
+pub struct PaletteEntry {
+    /// 显示文本，例如 `Tab: Tile All`
+    pub label: String,
+    /// 快捷键文本，可为空
+    pub shortcut: String,
+    pub action: PaletteAction,
+}
+
+/// 选中后执行的内容
+#[derive(Clone)]
+pub enum PaletteAction {
+    Command(AppCommand),
+    /// `TabAction::action`，交给焦点标签页的 `on_action`
+    TabAction(String),
+}
+
+/// 子序列匹配：`query` 的字符按顺序出现在 `text` 中即匹配 (忽略大小写)
+/// 连续匹配与单词开头的匹配得分更高；不匹配时返回 None
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let offset = text[next..].iter().position(|&c| c == q)?;
+        let index = next + offset;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !text[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(index);
+        next = index + 1;
+    }
+    // 同分时较短的文本更接近
+    Some(score * 100 - text.len() as i32)
+}
+
+#[derive(Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    query: String,
+    selected: usize,
+    /// 打开后的第一帧把焦点交给输入框
+    focus_input: bool,
+}
+
+impl CommandPalette {
+    pub fn show(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.focus_input = true;
+    }
+
+    /// 绘制面板，返回被选中执行的操作 (执行后面板关闭)
+    pub fn ui(&mut self, ctx: &egui::Context, entries: &[PaletteEntry]) -> Option<PaletteAction> {
+        if !self.open {
+            return None;
+        }
+        let mut matches: Vec<(i32, &PaletteEntry)> = entries.iter()
+            .filter_map(|entry| fuzzy_score(&self.query, &entry.label).map(|score| (score, entry)))
+            .collect();
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        // 方向键在输入框处理之前消耗，避免移动文本光标
+        let (up, down, enter, escape) = ctx.input_mut(|i| (
+            i.consume_key(Modifiers::NONE, Key::ArrowUp),
+            i.consume_key(Modifiers::NONE, Key::ArrowDown),
+            i.consume_key(Modifiers::NONE, Key::Enter),
+            i.consume_key(Modifiers::NONE, Key::Escape),
+        ));
+        if down && self.selected + 1 < matches.len() {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = enter.then(|| matches.get(self.selected).map(|(_, entry)| entry.action.clone())).flatten();
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .fixed_size([420.0, 0.0])
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Type a command…")
+                        .desired_width(f32::INFINITY),
+                );
+                if std::mem::take(&mut self.focus_input) {
+                    response.request_focus();
+                }
+                if response.changed() {
+                    self.selected = 0;
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    if matches.is_empty() {
+                        ui.weak("No matching commands");
+                    }
+                    for (i, (_, entry)) in matches.iter().enumerate() {
+                        let selected = i == self.selected;
+                        let item = ui.horizontal(|ui| {
+                            let item = ui.selectable_label(selected, &entry.label);
+                            if !entry.shortcut.is_empty() {
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    ui.weak(&entry.shortcut);
+                                });
+                            }
+                            item
+                        }).inner;
+                        if selected && (up || down) {
+                            item.scroll_to_me(None);
+                        }
+                        if item.clicked() {
+                            chosen = Some(entry.action.clone());
+                        }
+                    }
+                });
+            });
+
+        if chosen.is_some() || escape {
+            self.open = false;
+        }
+        chosen
+    }
+}
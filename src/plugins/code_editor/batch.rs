@@ -0,0 +1,245 @@
+//! 多文件批量编辑：写入磁盘前先列出所有将被修改的文件，逐个确认 (可展开查看差异) 后作为一个批次应用，
+//! 应用后的批次可以整体撤销
+
+use egui::{Color32, RichText, Ui};
+use regex::{NoExpand, Regex};
+use similar::{ChangeTag, TextDiff};
+use std::path::{Path, PathBuf};
+
+/// 扫描工作区时跳过的目录
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+pub struct FileEdit {
+    pub path: PathBuf,
+    pub original: String,
+    pub updated: String,
+    /// 用户是否勾选应用该文件
+    pub approved: bool,
+    /// 不能应用的原因 (例如文件在编辑器中有未保存的修改)，预览中显示且不可勾选
+    pub blocked: Option<String>,
+}
+
+/// 等待用户确认的批量修改
+pub struct BatchEdit {
+    pub title: String,
+    pub edits: Vec<FileEdit>,
+}
+
+/// 已写入磁盘的批次，用于撤销: (路径, 修改前, 修改后)
+pub struct AppliedBatch {
+    pub title: String,
+    pub files: Vec<(PathBuf, String, String)>,
+}
+
+pub enum PreviewAction {
+    Apply,
+    Cancel,
+}
+
+/// 递归收集 `root` 下被 `filter` 接受的文件，并用 `transform` 计算新内容；内容不变的文件不会出现在结果中
+pub fn collect_edits(
+    root: &Path,
+    filter: impl Fn(&Path) -> bool,
+    transform: impl Fn(&str) -> Option<String>,
+) -> Vec<FileEdit> {
+    let mut edits: Vec<FileEdit> = workspace_files(root, filter)
+        .into_iter()
+        .filter_map(|path| file_edit(path, &transform))
+        .collect();
+    edits.sort_by(|a, b| a.path.cmp(&b.path));
+    edits
+}
+
+/// 递归列出 `root` 下被 `filter` 接受的文件，跳过隐藏目录与常见的构建输出目录
+pub fn workspace_files(root: &Path, filter: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Ok(file_type) = entry.file_type() else { continue };
+            if file_type.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                    dirs.push(path);
+                }
+            } else if file_type.is_file() && filter(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// 对单个文件计算修改，读取失败或内容不变时返回 None
+pub fn file_edit(path: PathBuf, transform: impl Fn(&str) -> Option<String>) -> Option<FileEdit> {
+    let original = std::fs::read_to_string(&path).ok()?;
+    let updated = transform(&original).filter(|u| *u != original)?;
+    Some(FileEdit { path, original, updated, approved: true, blocked: None })
+}
+
+/// 在编辑器中有未保存修改的文件。按磁盘内容计算的修改会与缓冲区不一致，保存时又会被覆盖，因此跳过
+fn is_unsaved(path: &Path, unsaved: &[PathBuf]) -> bool {
+    unsaved.iter().any(|p| crate::same_path(p, path))
+}
+
+/// 是否为合法标识符 (字母或下划线开头，由字母、数字、下划线组成)
+pub fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// 文本重命名：区分大小写、全词匹配地把 `from` 替换为 `to`。
+/// 不理解语义，同名但无关的标识符 (以及字符串、注释中的同名单词) 也会被替换。
+pub fn rename_transform(from: &str, to: &str) -> impl Fn(&str) -> Option<String> + Send + 'static {
+    let pattern = Regex::new(&format!(r"\b{}\b", regex::escape(from))).ok();
+    let to = to.to_string();
+    move |text: &str| {
+        let pattern = pattern.as_ref()?;
+        pattern.is_match(text).then(|| pattern.replace_all(text, NoExpand(&to)).into_owned())
+    }
+}
+
+impl BatchEdit {
+    /// 取消勾选并锁定在编辑器中有未保存修改的文件，返回其中原本已勾选的文件
+    pub fn block_unsaved(&mut self, unsaved: &[PathBuf]) -> Vec<PathBuf> {
+        let mut unapproved = Vec::new();
+        for edit in self.edits.iter_mut().filter(|e| e.blocked.is_none() && is_unsaved(&e.path, unsaved)) {
+            if std::mem::take(&mut edit.approved) {
+                unapproved.push(edit.path.clone());
+            }
+            edit.blocked = Some("unsaved changes in an open editor; save or revert them first".to_string());
+        }
+        unapproved
+    }
+
+    /// 绘制预览窗口，用户确认或取消时返回对应操作
+    pub fn preview_ui(&mut self, ctx: &egui::Context) -> Option<PreviewAction> {
+        let mut action = None;
+        let mut open = true;
+        egui::Window::new(format!("Preview: {}", self.title))
+            .id(egui::Id::new("code_editor_batch_preview"))
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([640.0, 480.0])
+            .show(ctx, |ui| {
+                let approved = self.edits.iter().filter(|e| e.approved).count();
+                ui.label(format!("{} of {} files will be changed.", approved, self.edits.len()));
+                ui.horizontal(|ui| {
+                    if ui.small_button("Select All").clicked() {
+                        self.edits.iter_mut().for_each(|e| e.approved = e.blocked.is_none());
+                    }
+                    if ui.small_button("Select None").clicked() {
+                        self.edits.iter_mut().for_each(|e| e.approved = false);
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .id_salt("batch_preview_files")
+                    .max_height(ui.available_height() - 40.0)
+                    .show(ui, |ui| {
+                        for edit in &mut self.edits {
+                            ui.horizontal(|ui| {
+                                ui.add_enabled(edit.blocked.is_none(), egui::Checkbox::without_text(&mut edit.approved));
+                                let name = edit.path.to_string_lossy().to_string();
+                                egui::CollapsingHeader::new(RichText::new(name).monospace())
+                                    .id_salt(&edit.path)
+                                    .show(ui, |ui| diff_ui(ui, &edit.original, &edit.updated));
+                            });
+                            if let Some(reason) = &edit.blocked {
+                                ui.label(RichText::new(format!("Skipped: {}", reason)).color(ui.visuals().warn_fg_color));
+                            }
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(approved > 0, egui::Button::new(format!("Apply to {} files", approved))).clicked() {
+                        action = Some(PreviewAction::Apply);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        action = Some(PreviewAction::Cancel);
+                    }
+                });
+            });
+        if !open {
+            action = Some(PreviewAction::Cancel);
+        }
+        action
+    }
+
+    /// 写入所有已确认的修改，返回可撤销的批次与失败信息
+    pub fn apply(self) -> (AppliedBatch, Vec<String>) {
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        for edit in self.edits.into_iter().filter(|e| e.approved) {
+            // 预览期间文件被修改过则跳过，避免覆盖他人的改动
+            match std::fs::read_to_string(&edit.path) {
+                Ok(current) if current == edit.original => {}
+                _ => {
+                    errors.push(format!("{} changed since the preview was created", edit.path.display()));
+                    continue;
+                }
+            }
+            match std::fs::write(&edit.path, &edit.updated) {
+                Ok(_) => files.push((edit.path, edit.original, edit.updated)),
+                Err(e) => errors.push(format!("{}: {}", edit.path.display(), e)),
+            }
+        }
+        (AppliedBatch { title: self.title, files }, errors)
+    }
+}
+
+impl AppliedBatch {
+    /// 恢复批次修改前的内容，之后又被修改过或在编辑器中有未保存修改的文件会被跳过。
+    /// 返回 (已恢复的文件及其内容, 失败信息)
+    pub fn revert(self, unsaved: &[PathBuf]) -> (Vec<(PathBuf, String)>, Vec<String>) {
+        let mut restored = Vec::new();
+        let mut errors = Vec::new();
+        for (path, before, after) in self.files {
+            if is_unsaved(&path, unsaved) {
+                errors.push(format!("{} has unsaved changes in an open editor", path.display()));
+                continue;
+            }
+            match std::fs::read_to_string(&path) {
+                Ok(current) if current == after => {}
+                _ => {
+                    errors.push(format!("{} was modified after the batch edit", path.display()));
+                    continue;
+                }
+            }
+            match std::fs::write(&path, &before) {
+                Ok(_) => restored.push((path, before)),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        (restored, errors)
+    }
+}
+
+/// 按行显示差异，每处修改上下各保留两行上下文
+pub fn diff_ui(ui: &mut Ui, original: &str, updated: &str) {
+    let diff = TextDiff::from_lines(original, updated);
+    for (i, group) in diff.grouped_ops(2).iter().enumerate() {
+        if i > 0 {
+            ui.separator();
+        }
+        for op in group {
+            for change in diff.iter_changes(op) {
+                let (prefix, color) = match change.tag() {
+                    ChangeTag::Delete => ("-", Color32::from_rgb(230, 110, 110)),
+                    ChangeTag::Insert => ("+", Color32::from_rgb(110, 200, 110)),
+                    ChangeTag::Equal => (" ", ui.visuals().weak_text_color()),
+                };
+                let line_no = change.new_index().or(change.old_index()).map_or(0, |n| n + 1);
+                let text = change.value().trim_end_matches(['\n', '\r']);
+                // 让行尾空白可见
+                let visible = text.trim_end().len();
+                let shown = format!("{:>5} {}{}{}", line_no, prefix, &text[..visible], "·".repeat(text[visible..].chars().count()));
+                ui.label(RichText::new(shown).monospace().color(color));
+            }
+        }
+    }
+}
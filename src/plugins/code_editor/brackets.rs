@@ -0,0 +1,65 @@
+//! 括号配对：输入左括号 / 引号时补全右侧符号，输入已存在的右侧符号时越过它，以及查找光标旁括号的配对位置
+
+/// 参与配对高亮的括号
+const BRACKETS: &[(u8, u8)] = &[(b'(', b')'), (b'[', b']'), (b'{', b'}')];
+/// 查找配对括号时最多扫描的字节数，避免每帧扫描整个大文件
+const MAX_SCAN: usize = 100_000;
+
+/// 没有选区时输入 `open` 是否同时插入右侧符号。`before` / `after` 是光标前后紧邻的字符
+pub fn should_close(language: &str, open: char, before: Option<char>, after: Option<char>) -> bool {
+    // 右侧紧跟标识符等内容时不补全，例如在 `foo` 前输入 `(`
+    if after.is_some_and(|c| !c.is_whitespace() && !matches!(c, ')' | ']' | '}' | ',' | ';' | ':')) {
+        return false;
+    }
+    match open {
+        '"' | '\'' | '`' if before.is_some_and(|c| c.is_alphanumeric() || c == '_') => false,
+        // Rust 的生命周期与文本中的撇号
+        '\'' => !matches!(language, "rs" | "txt" | "md"),
+        // 只有这些语言用反引号包围字符串或代码
+        '`' => matches!(language, "js" | "md" | "go" | "sh"),
+        _ => true,
+    }
+}
+
+/// 输入的右括号或引号正好是光标右侧的字符，应越过它而不是再插入一个
+pub fn steps_over(typed: char, after: Option<char>) -> bool {
+    after == Some(typed) && matches!(typed, ')' | ']' | '}' | '"' | '\'' | '`')
+}
+
+/// 光标 (字节位置) 旁的括号及其配对括号的字节位置，按位置排序。
+/// 先看光标右侧的字符，再看左侧的；不区分字符串与注释中的括号
+pub fn matching_pair(text: &str, cursor: usize) -> Option<(usize, usize)> {
+    let bytes = text.as_bytes();
+    for at in [Some(cursor), cursor.checked_sub(1)].into_iter().flatten() {
+        let Some(&byte) = bytes.get(at) else { continue };
+        let Some(&(open, close)) = BRACKETS.iter().find(|(open, close)| *open == byte || *close == byte) else {
+            continue;
+        };
+        let partner = if byte == open {
+            scan(bytes.iter().enumerate().skip(at).take(MAX_SCAN), open, close)
+        } else {
+            let from = at.saturating_sub(MAX_SCAN);
+            scan(bytes[from..=at].iter().enumerate().rev().map(|(i, b)| (from + i, b)), close, open)
+        };
+        if let Some(partner) = partner {
+            return Some((at.min(partner), at.max(partner)));
+        }
+    }
+    None
+}
+
+/// 从 `start` 括号开始按顺序扫描，返回使层数回到 0 的 `end` 括号的位置
+fn scan<'a>(bytes: impl Iterator<Item = (usize, &'a u8)>, start: u8, end: u8) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, &byte) in bytes {
+        if byte == start {
+            depth += 1;
+        } else if byte == end {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,70 @@
+//! 切换行注释 (默认 Ctrl + /)：所有非空行都已注释时取消注释，否则在共同缩进处加上注释符号
+
+use std::ops::Range;
+
+/// 语言的注释符号: (开始, 结束)，只有块注释的语言 (HTML、CSS) 每行各自包裹
+fn tokens(language: &str) -> Option<(&'static str, Option<&'static str>)> {
+    match language {
+        "rs" | "js" | "c" | "cpp" | "cs" | "java" | "kt" | "go" | "php" => Some(("//", None)),
+        "py" | "toml" | "yaml" | "sh" | "rb" => Some(("#", None)),
+        "ini" => Some((";", None)),
+        "sql" | "lua" => Some(("--", None)),
+        "html" | "xml" | "md" => Some(("<!--", Some("-->"))),
+        "css" => Some(("/*", Some("*/"))),
+        _ => None,
+    }
+}
+
+fn is_commented(line: &str, open: &str, close: Option<&str>) -> bool {
+    let line = line.trim();
+    line.starts_with(open) && close.is_none_or(|close| line.len() >= open.len() + close.len() && line.ends_with(close))
+}
+
+/// 切换 `lines` (0 起始行号) 的注释，返回新文本；语言没有注释符号或范围内全是空行时返回 None
+pub fn toggle(text: &str, lines: Range<usize>, language: &str) -> Option<String> {
+    let (open, close) = tokens(language)?;
+    let selected: Vec<&str> = text.split('\n').skip(lines.start).take(lines.len())
+        .map(|l| l.trim_end_matches('\r'))
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+    if selected.is_empty() {
+        return None;
+    }
+    let uncomment = selected.iter().all(|l| is_commented(l, open, close));
+    // 注释符号统一插在最浅的缩进处，使注释后的各行仍然对齐
+    let column = selected.iter().map(|l| l.len() - l.trim_start().len()).min().unwrap_or(0);
+
+    let mut out = String::with_capacity(text.len() + lines.len() * (open.len() + 1));
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let ending = &line[content.len()..];
+        if !lines.contains(&i) || content.trim().is_empty() {
+            out.push_str(line);
+            continue;
+        }
+        if uncomment {
+            let trimmed = content.trim_end();
+            let indent = trimmed.len() - trimmed.trim_start().len();
+            let mut body = &trimmed[indent + open.len()..];
+            body = body.strip_prefix(' ').unwrap_or(body);
+            if let Some(close) = close {
+                body = body.strip_suffix(close).unwrap_or(body);
+                body = body.strip_suffix(' ').unwrap_or(body);
+            }
+            out.push_str(&content[..indent]);
+            out.push_str(body);
+        } else {
+            let at = column.min(content.len() - content.trim_start().len());
+            out.push_str(&content[..at]);
+            out.push_str(open);
+            out.push(' ');
+            out.push_str(&content[at..]);
+            if let Some(close) = close {
+                out.push(' ');
+                out.push_str(close);
+            }
+        }
+        out.push_str(ending);
+    }
+    Some(out)
+}
@@ -0,0 +1,84 @@
+//! 基于正则的"查找定义" (无需 LSP)：在工作区同语言的文件中查找名称匹配的定义行。
+//! 只按文本规则推测，同名的无关定义也会被找到，结果需要用户自行判断
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use crate::plugins::code_editor::language::LANGUAGE_PROFILES;
+use crate::plugins::code_editor::outline::{self, SymbolKind};
+use crate::plugins::code_editor::batch;
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub path: PathBuf,
+    /// 0 起始的行号
+    pub line: usize,
+    /// 定义所在行 (去掉首尾空白)，用于在候选列表中区分
+    pub preview: String,
+}
+
+/// 编译后的额外定义规则，按语言 ID 缓存
+fn definition_patterns(language: &str) -> &'static [Regex] {
+    static COMPILED: OnceLock<HashMap<&'static str, Vec<Regex>>> = OnceLock::new();
+    let compiled = COMPILED.get_or_init(|| {
+        LANGUAGE_PROFILES
+            .iter()
+            .map(|profile| {
+                let rules = profile.definition_patterns.iter().map(|re| Regex::new(re).unwrap()).collect();
+                (profile.id, rules)
+            })
+            .collect()
+    });
+    compiled.get(language).map(|v| v.as_slice()).unwrap_or_default()
+}
+
+/// 在一个文件的内容中查找 `name` 的定义行 (0 起始行号)
+fn definitions_in(language: &str, code: &str, name: &str) -> Vec<usize> {
+    // 大部分文件根本不包含该名称，先做一次廉价的子串检查
+    if !code.contains(name) {
+        return Vec::new();
+    }
+    let mut lines: Vec<usize> = outline::extract_symbols(language, code)
+        .into_iter()
+        .filter(|s| s.name == name && !matches!(s.kind, SymbolKind::Impl | SymbolKind::Heading))
+        .map(|s| s.line)
+        .collect();
+
+    let rules = definition_patterns(language);
+    if !rules.is_empty() {
+        for (line_idx, line) in code.lines().enumerate() {
+            let found = rules.iter().any(|re| re.captures(line).and_then(|c| c.get(1)).is_some_and(|m| m.as_str() == name));
+            if found && !lines.contains(&line_idx) {
+                lines.push(line_idx);
+            }
+        }
+    }
+    lines.sort_unstable();
+    lines
+}
+
+/// 在 `root` 下被 `filter` 接受的文件中查找 `name` 的定义，按路径与行号排序
+pub fn find_definitions(
+    root: &Path,
+    language: &str,
+    name: &str,
+    filter: impl Fn(&Path) -> bool,
+) -> Vec<Definition> {
+    let mut definitions = Vec::new();
+    for path in batch::workspace_files(root, filter) {
+        let Ok(code) = std::fs::read_to_string(&path) else { continue };
+        let lines = definitions_in(language, &code, name);
+        if lines.is_empty() {
+            continue;
+        }
+        let text: Vec<&str> = code.lines().collect();
+        definitions.extend(lines.into_iter().map(|line| Definition {
+            path: path.clone(),
+            line,
+            preview: text.get(line).map(|l| l.trim().to_string()).unwrap_or_default(),
+        }));
+    }
+    definitions.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    definitions
+}
@@ -0,0 +1,170 @@
+//! 查找 / 替换：普通文本或正则、区分大小写、全词匹配。
+//! 匹配结果以字节范围表示，由布局函数在语法高亮之上加背景色标出
+
+use egui::text::LayoutJob;
+use egui::Color32;
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+/// 一次最多高亮 / 统计的匹配数，避免在超大文件中匹配单个字符时卡顿
+pub const MAX_MATCHES: usize = 10_000;
+
+#[derive(Debug, Clone, Default)]
+pub struct FindState {
+    pub query: String,
+    pub replacement: String,
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// 显示替换栏 (Ctrl + H 打开时)
+    pub show_replace: bool,
+    /// 所有匹配的字节范围，按位置排序
+    pub matches: Vec<Range<usize>>,
+    /// 当前匹配的序号
+    pub current: usize,
+    /// 正则编译失败时的错误信息
+    pub error: Option<String>,
+    /// 计算匹配时的 (revision, 查询, 正则, 区分大小写, 全词)
+    computed_for: Option<(u64, String, bool, bool, bool)>,
+    /// 下一帧选中并滚动到当前匹配
+    pub reveal: bool,
+    /// 下一帧让查找输入框获得焦点
+    pub focus: bool,
+    /// 上一帧查找栏中的输入框是否有焦点
+    pub has_focus: bool,
+}
+
+impl FindState {
+    /// 内容或查询条件变化时重新查找；查询变化时从 `cursor` (字节偏移) 之后的第一个匹配开始
+    pub fn update(&mut self, text: &str, revision: u64, cursor: usize) {
+        let key = (revision, self.query.clone(), self.regex, self.case_sensitive, self.whole_word);
+        if self.computed_for.as_ref() == Some(&key) {
+            return;
+        }
+        let query_changed = self.computed_for.as_ref().is_none_or(|old| (&old.1, old.2, old.3, old.4) != (&key.1, key.2, key.3, key.4));
+        self.computed_for = Some(key);
+
+        self.matches.clear();
+        self.error = None;
+        match self.pattern() {
+            Ok(Some(pattern)) => self.matches = find_all(&pattern, text),
+            Ok(None) => {}
+            Err(e) => self.error = Some(e),
+        }
+
+        if query_changed {
+            let next = self.matches.partition_point(|m| m.start < cursor);
+            self.current = if next < self.matches.len() { next } else { 0 };
+            self.reveal = !self.matches.is_empty();
+        } else {
+            self.current = self.current.min(self.matches.len().saturating_sub(1));
+        }
+    }
+
+    /// 移动到下一个 (`forward`) 或上一个匹配，首尾循环
+    pub fn step(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        self.current = if forward { (self.current + 1) % len } else { (self.current + len - 1) % len };
+        self.reveal = true;
+    }
+
+    pub fn current_match(&self) -> Option<Range<usize>> {
+        self.matches.get(self.current).cloned()
+    }
+
+    /// 按当前选项编译查询；查询为空时返回 Ok(None)
+    pub fn pattern(&self) -> Result<Option<Regex>, String> {
+        if self.query.is_empty() {
+            return Ok(None);
+        }
+        let source = if self.regex { self.query.clone() } else { regex::escape(&self.query) };
+        let source = if self.whole_word { format!(r"\b(?:{})\b", source) } else { source };
+        RegexBuilder::new(&source)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 替换一处匹配时插入的文本；正则模式下支持 `$1` 等捕获组引用
+    pub fn replacement_for(&self, pattern: &Regex, text: &str, start: usize) -> String {
+        if !self.regex {
+            return self.replacement.clone();
+        }
+        let mut out = String::new();
+        // 在原文中从匹配处重新捕获，使 `\b` 等断言看到完整的上下文
+        if let Some(captures) = pattern.captures_at(text, start) {
+            captures.expand(&self.replacement, &mut out);
+        }
+        out
+    }
+
+    /// 替换全部匹配，返回新文本与替换的数量
+    pub fn replace_all(&self, pattern: &Regex, text: &str) -> (String, usize) {
+        let count = pattern.find_iter(text).filter(|m| !m.is_empty()).count();
+        let replaced = if self.regex {
+            pattern.replace_all(text, self.replacement.as_str())
+        } else {
+            pattern.replace_all(text, regex::NoExpand(&self.replacement))
+        };
+        (replaced.into_owned(), count)
+    }
+}
+
+/// 所有非空匹配的字节范围
+pub fn find_all(pattern: &Regex, text: &str) -> Vec<Range<usize>> {
+    pattern.find_iter(text)
+        .filter(|m| !m.is_empty())
+        .take(MAX_MATCHES)
+        .map(|m| m.range())
+        .collect()
+}
+
+/// 给布局中落在 `ranges` 内的文本加背景色，`current` 使用更醒目的颜色。
+/// 跨越匹配边界的 section 会被拆开
+pub fn highlight(job: &mut LayoutJob, ranges: &[Range<usize>], current: Option<usize>, color: Color32, current_color: Color32) {
+    // 本帧刚输入的内容还没有重新查找，过期的范围可能越界，此时跳过一帧
+    let text = &job.text;
+    if ranges.iter().any(|r| r.end > text.len() || !text.is_char_boundary(r.start) || !text.is_char_boundary(r.end)) {
+        return;
+    }
+    let mut sections = Vec::with_capacity(job.sections.len() + ranges.len() * 2);
+    let mut next = 0;
+    for section in std::mem::take(&mut job.sections) {
+        let mut start = section.byte_range.start;
+        let end = section.byte_range.end;
+        // 跳过已结束的匹配
+        while next < ranges.len() && ranges[next].end <= start {
+            next += 1;
+        }
+        let mut i = next;
+        while start < end {
+            let Some(range) = ranges.get(i).filter(|r| r.start < end) else {
+                let mut rest = section.clone();
+                rest.byte_range = start..end;
+                sections.push(rest);
+                break;
+            };
+            if range.start > start {
+                let mut before = section.clone();
+                before.byte_range = start..range.start;
+                sections.push(before);
+                start = range.start;
+            }
+            let stop = range.end.min(end);
+            let mut matched = section.clone();
+            matched.byte_range = start..stop;
+            matched.format.background = if current == Some(i) { current_color } else { color };
+            sections.push(matched);
+            start = stop;
+            if range.end <= end {
+                i += 1;
+            }
+        }
+    }
+    job.sections = sections;
+}
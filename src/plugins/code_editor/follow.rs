@@ -0,0 +1,110 @@
+//! "Follow File" 模式 (类似 `tail -f`)：文件增长时只从上次读取的位置读入新增内容并追加，
+//! 文件变短 (被截断) 或被替换 (日志轮转) 时从头重新载入
+
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+pub enum FollowUpdate {
+    /// 文件末尾新增的内容
+    Appended(String),
+    /// 文件被截断或替换后的完整内容
+    Reloaded(String),
+}
+
+#[derive(Clone)]
+pub struct Follower {
+    path: PathBuf,
+    /// 已读入的字节数
+    offset: u64,
+    /// 监听线程报告文件有变化
+    changed: Arc<AtomicBool>,
+    /// 文件被创建、删除或重命名 (日志轮转)，需要从头读取
+    replaced: Arc<AtomicBool>,
+    /// 保持监听器存活，标签页克隆时共享
+    _watcher: Arc<Mutex<RecommendedWatcher>>,
+    /// 用户向上滚动离开底部后暂停自动滚动
+    pub paused: bool,
+}
+
+impl std::fmt::Debug for Follower {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Follower").field("path", &self.path).field("offset", &self.offset).finish()
+    }
+}
+
+impl Follower {
+    /// 开始跟随 `path`。首次 `poll` 会载入完整内容。
+    /// 监听的是所在目录而不是文件本身，这样文件被轮转替换后仍能收到事件。
+    pub fn start(path: &Path, ctx: egui::Context) -> notify::Result<Self> {
+        let changed = Arc::new(AtomicBool::new(true));
+        let replaced = Arc::new(AtomicBool::new(true));
+        let (changed_flag, replaced_flag) = (changed.clone(), replaced.clone());
+        let target = path.to_path_buf();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else { return };
+            if !event.paths.iter().any(|p| *p == target) {
+                return;
+            }
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))) {
+                replaced_flag.store(true, Ordering::Relaxed);
+            }
+            changed_flag.store(true, Ordering::Relaxed);
+            ctx.request_repaint();
+        })?;
+        watcher.watch(path.parent().unwrap_or(path), RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            offset: 0,
+            changed,
+            replaced,
+            _watcher: Arc::new(Mutex::new(watcher)),
+            paused: false,
+        })
+    }
+
+    /// 文件有变化时读取新内容
+    pub fn poll(&mut self) -> Option<FollowUpdate> {
+        if !self.changed.swap(false, Ordering::Relaxed) {
+            return None;
+        }
+        let mut file = std::fs::File::open(&self.path).ok()?;
+        let len = file.metadata().ok()?.len();
+
+        if self.replaced.swap(false, Ordering::Relaxed) || len < self.offset {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes).ok()?;
+            let (text, used) = decode_prefix(&bytes);
+            self.offset = used as u64;
+            return Some(FollowUpdate::Reloaded(text));
+        }
+        if len == self.offset {
+            return None;
+        }
+
+        file.seek(SeekFrom::Start(self.offset)).ok()?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).ok()?;
+        let (text, used) = decode_prefix(&bytes);
+        self.offset += used as u64;
+        (!text.is_empty()).then_some(FollowUpdate::Appended(text))
+    }
+}
+
+/// 解码字节，末尾被截断的多字节字符留到下次读取。返回 (文本, 已消耗的字节数)
+fn decode_prefix(bytes: &[u8]) -> (String, usize) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), bytes.len()),
+        Err(e) if e.error_len().is_none() => {
+            let valid = e.valid_up_to();
+            (String::from_utf8_lossy(&bytes[..valid]).into_owned(), valid)
+        }
+        Err(_) => (String::from_utf8_lossy(bytes).into_owned(), bytes.len()),
+    }
+}
@@ -0,0 +1,168 @@
+//! 缩进设置：制表符的显示宽度，以及按 Tab 时是否插入空格 (软制表符)。
+//!
+//! 全局设置写在 `[code_editor]` 的 `tab_width` / `insert_spaces` 中，
+//! 按语言覆盖写在 `[code_editor.indent.<语言>]` 表中，例如 `tab_width = 2`、`insert_spaces = false`。
+
+use egui::text::LayoutJob;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentStyle {
+    /// 一级缩进的列数，同时是制表符的显示宽度
+    pub tab_width: usize,
+    /// 按 Tab 时插入空格而不是制表符
+    pub insert_spaces: bool,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        Self { tab_width: 4, insert_spaces: true }
+    }
+}
+
+impl IndentStyle {
+    /// 用配置表中的字段覆盖，缺省的字段保持不变
+    pub fn merge(mut self, table: &toml::Table) -> Self {
+        if let Some(v) = table.get("tab_width").and_then(|v| v.as_integer()) {
+            self.tab_width = v.clamp(1, 16) as usize;
+        }
+        if let Some(v) = table.get("insert_spaces").and_then(|v| v.as_bool()) {
+            self.insert_spaces = v;
+        }
+        self
+    }
+
+    /// 与 `merge` 读取的字段对应的配置表
+    pub fn to_table(&self) -> toml::Table {
+        let mut table = toml::Table::new();
+        table.insert("tab_width".to_string(), (self.tab_width as i64).into());
+        table.insert("insert_spaces".to_string(), self.insert_spaces.into());
+        table
+    }
+
+    /// 一级缩进的文本
+    pub fn unit(&self) -> String {
+        if self.insert_spaces { " ".repeat(self.tab_width) } else { "\t".to_string() }
+    }
+
+    /// 在 `column` 列按 Tab 时插入的空格，补齐到下一个制表位
+    pub fn spaces_at(&self, column: usize) -> String {
+        " ".repeat(self.tab_width - column % self.tab_width)
+    }
+}
+
+/// 使用花括号分块的语言
+fn uses_braces(language: &str) -> bool {
+    matches!(language, "rs" | "js" | "c" | "cpp" | "cs" | "java" | "kt" | "go" | "php" | "css" | "json")
+}
+
+/// 回车时是否在新行多缩进一级: `before` 是光标之前的本行内容 (已知语言才判断)
+pub fn opens_block(language: &str, before: &str) -> bool {
+    let Some(last) = before.trim_end().chars().last() else { return false };
+    match language {
+        "py" => matches!(last, ':' | '(' | '[' | '{'),
+        "toml" => last == '[',
+        "yaml" => last == ':',
+        l if uses_braces(l) => matches!(last, '{' | '(' | '['),
+        _ => false,
+    }
+}
+
+/// `next` 是否正好闭合 `before` 末尾的括号，例如在 `{|}` 中回车
+pub fn closes_block(before: &str, next: char) -> bool {
+    matches!((before.trim_end().chars().last(), next), (Some('{'), '}') | (Some('('), ')') | (Some('['), ']'))
+}
+
+/// 输入 `typed` 时是否减少一级缩进 (在只有空白的行首输入右花括号)
+pub fn is_electric(language: &str, typed: char) -> bool {
+    typed == '}' && uses_braces(language)
+}
+
+/// 去掉一行缩进末尾的一级: 一个制表符，或补齐到上一个制表位的空格
+pub fn dedent_once(indent: &str, style: IndentStyle) -> &str {
+    if let Some(rest) = indent.strip_suffix('\t') {
+        return rest;
+    }
+    let spaces = indent.chars().rev().take_while(|c| *c == ' ').count();
+    let remove = match indent.len() % style.tab_width {
+        0 => style.tab_width,
+        r => r,
+    }.min(spaces);
+    &indent[..indent.len() - remove]
+}
+
+/// 给 `lines` (0 起始行号) 的每一行加一级缩进，空行除外。返回新文本
+pub fn indent_lines(text: &str, lines: Range<usize>, style: IndentStyle) -> String {
+    let unit = style.unit();
+    map_lines(text, lines, |line| {
+        if line.trim().is_empty() { line.to_string() } else { format!("{}{}", unit, line) }
+    })
+}
+
+/// 去掉 `lines` 每一行的一级缩进: 一个制表符，或最多 `tab_width` 个空格。返回新文本
+pub fn dedent_lines(text: &str, lines: Range<usize>, style: IndentStyle) -> String {
+    map_lines(text, lines, |line| {
+        if let Some(rest) = line.strip_prefix('\t') {
+            return rest.to_string();
+        }
+        let spaces = line.chars().take(style.tab_width).take_while(|c| *c == ' ').count();
+        line[spaces..].to_string()
+    })
+}
+
+/// 对 `lines` 内每一行的内容 (不含换行符) 应用 `f`
+fn map_lines(text: &str, lines: Range<usize>, f: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(text.len() + lines.len() * 4);
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        if lines.contains(&i) {
+            let content = line.trim_end_matches(['\n', '\r']);
+            out.push_str(&f(content));
+            out.push_str(&line[content.len()..]);
+        } else {
+            out.push_str(line);
+        }
+    }
+    // 以换行结尾时最后一行为空，split_inclusive 不会产出它
+    if text.is_empty() || text.ends_with('\n') {
+        let last = text.matches('\n').count();
+        if lines.contains(&last) {
+            out.push_str(&f(""));
+        }
+    }
+    out
+}
+
+/// 按 `tab_width` 调整布局中制表符的宽度。
+/// egui 把制表符画成固定 4 个空格宽，这里把每个制表符拆成单独的 section，用字间距补差
+pub fn set_tab_width(job: &mut LayoutJob, tab_width: usize, space_width: f32) {
+    const EGUI_TAB_WIDTH: usize = 4;
+    if tab_width == EGUI_TAB_WIDTH || !job.text.contains('\t') {
+        return;
+    }
+    let extra = (tab_width as f32 - EGUI_TAB_WIDTH as f32) * space_width;
+    let text = &job.text;
+    let mut sections = Vec::with_capacity(job.sections.len());
+    for section in std::mem::take(&mut job.sections) {
+        let range = section.byte_range.clone();
+        let mut start = range.start;
+        for (offset, _) in text[range.clone()].match_indices('\t') {
+            let tab = range.start + offset;
+            if tab > start {
+                let mut before = section.clone();
+                before.byte_range = start..tab;
+                sections.push(before);
+            }
+            let mut tab_section = section.clone();
+            tab_section.byte_range = tab..tab + 1;
+            tab_section.format.extra_letter_spacing += extra;
+            sections.push(tab_section);
+            start = tab + 1;
+        }
+        if start < range.end {
+            let mut rest = section;
+            rest.byte_range = start..range.end;
+            sections.push(rest);
+        }
+    }
+    job.sections = sections;
+}
@@ -0,0 +1,56 @@
+//! 语言配置表：新增语言只需在 `LANGUAGE_PROFILES` 中补充一项
+
+use crate::plugins::code_editor::outline::SymbolKind;
+
+pub struct LanguageProfile {
+    /// 语法高亮 ID (与 `egui_extras::syntax_highlighting` 一致)
+    pub id: &'static str,
+    /// 大纲符号规则：(符号类型, 正则)，第一个捕获组为符号名
+    pub symbol_patterns: &'static [(SymbolKind, &'static str)],
+    /// 不出现在大纲中、但可用于查找定义的额外规则 (常量、变量等)，第一个捕获组为名称
+    pub definition_patterns: &'static [&'static str],
+}
+
+pub static LANGUAGE_PROFILES: &[LanguageProfile] = &[
+    LanguageProfile {
+        id: "rs",
+        symbol_patterns: &[
+            (SymbolKind::Function, r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern\s+"[^"]*")\s+)*fn\s+([A-Za-z_][A-Za-z0-9_]*)"#),
+            (SymbolKind::Type, r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:struct|enum|trait|union|type)\s+([A-Za-z_][A-Za-z0-9_]*)"),
+            (SymbolKind::Module, r"^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+([A-Za-z_][A-Za-z0-9_]*)\s*\{"),
+            (SymbolKind::Impl, r"^\s*(?:unsafe\s+)?impl(?:<[^>]*>)?\s+([^{]+?)\s*(?:where\b.*)?\{?\s*$"),
+        ],
+        definition_patterns: &[
+            r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const|static)\s+(?:mut\s+)?([A-Za-z_][A-Za-z0-9_]*)\s*:",
+            r"^\s*macro_rules!\s+([A-Za-z_][A-Za-z0-9_]*)",
+        ],
+    },
+    LanguageProfile {
+        id: "py",
+        symbol_patterns: &[
+            (SymbolKind::Function, r"^\s*(?:async\s+)?def\s+([A-Za-z_][A-Za-z0-9_]*)"),
+            (SymbolKind::Type, r"^\s*class\s+([A-Za-z_][A-Za-z0-9_]*)"),
+        ],
+        definition_patterns: &[
+            r"^([A-Za-z_][A-Za-z0-9_]*)\s*(?::[^=]*)?=(?:[^=]|$)",
+        ],
+    },
+    LanguageProfile {
+        id: "js",
+        symbol_patterns: &[
+            (SymbolKind::Function, r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?function\*?\s+([A-Za-z_$][\w$]*)"),
+            (SymbolKind::Type, r"^\s*(?:export\s+)?(?:default\s+)?class\s+([A-Za-z_$][\w$]*)"),
+            (SymbolKind::Function, r"^\s*(?:export\s+)?(?:const|let|var)\s+([A-Za-z_$][\w$]*)\s*=\s*(?:async\s*)?(?:\([^)]*\)|[A-Za-z_$][\w$]*)\s*=>"),
+        ],
+        definition_patterns: &[
+            r"^\s*(?:export\s+)?(?:const|let|var)\s+([A-Za-z_$][\w$]*)\s*=",
+        ],
+    },
+    LanguageProfile {
+        id: "md",
+        symbol_patterns: &[
+            (SymbolKind::Heading, r"^(#{1,6}\s+.+?)\s*#*\s*$"),
+        ],
+        definition_patterns: &[],
+    },
+];
@@ -1,8 +1,405 @@
 use egui::{Ui, WidgetText};
-use crate::{Tab, Plugin, AppCommand, TabInstance};
+use crate::{Tab, Plugin, AppCommand, TabInstance, TabAction, TabCapabilities, TabDescriptor, TabStatus};
+use crate::config::PluginConfig;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use parking_lot::RwLock;
 
+mod batch;
+mod brackets;
+mod comment;
+mod definition;
+mod find;
+mod follow;
+mod indent;
+mod language;
+mod outline;
+mod snippet;
+mod structured;
+mod sync;
+mod undo;
+
+static NEXT_EDITOR_KEY: AtomicU64 = AtomicU64::new(1);
+
+/// 编辑器插件的全局设置，由插件与其创建的所有标签页共享
+#[derive(Debug, Clone)]
+pub struct EditorSettings {
+    /// 按语言配置的运行命令模板
+    /// 支持占位符: `${file}` `${fileDir}` `${workspaceRoot}`
+    /// 设置文件中的 `[code_editor.run_commands]` 整体替换默认命令
+    pub run_commands: BTreeMap<String, String>,
+    /// 在编辑区上方显示路径与当前符号的面包屑导航
+    pub show_breadcrumbs: bool,
+    /// 相对行号 (当前行显示绝对行号，其余行显示与当前行的距离)
+    pub relative_line_numbers: bool,
+    /// 自动换行
+    pub word_wrap: bool,
+    /// 括号/引号自动配对 (包括选中文本时输入左括号将其包裹)
+    pub auto_close_brackets: bool,
+    /// 高亮光标旁的括号及与之配对的括号
+    pub match_brackets: bool,
+    /// 回车时沿用当前行的缩进，在块的开头之后多缩进一级；在空白行首输入 `}` 时减少一级
+    pub auto_indent: bool,
+    /// 超过该大小 (KB) 的文件以大文件模式打开
+    pub large_file_threshold_kb: u64,
+    /// 光标上下至少保留的可见行数 (类似 Vim 的 `scrolloff`)，0 表示关闭
+    pub scrolloff: usize,
+    /// 代码片段: 语言 -> (触发词 -> 模板)，格式见 `snippet` 模块
+    pub snippets: BTreeMap<String, BTreeMap<String, String>>,
+    /// 默认缩进设置
+    pub indent: indent::IndentStyle,
+    /// 按语言覆盖的缩进设置
+    pub language_indent: BTreeMap<String, indent::IndentStyle>,
+    /// 保存时的整理
+    pub on_save: SaveCleanup,
+    /// 按语言覆盖的保存时整理，写在 `[code_editor.on_save.<语言>]` 中；未覆盖的字段沿用 `on_save`
+    pub language_on_save: BTreeMap<String, SaveCleanupOverride>,
+}
+
+/// 保存前对内容的整理，使提交到版本库的差异更干净
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveCleanup {
+    /// 删除每行末尾的空白
+    pub trim_trailing_whitespace: bool,
+    /// 文件以且仅以一个换行符结尾 (空文件除外)
+    pub insert_final_newline: bool,
+}
+
+impl Default for SaveCleanup {
+    fn default() -> Self {
+        Self { trim_trailing_whitespace: true, insert_final_newline: true }
+    }
+}
+
+impl SaveCleanup {
+    /// 用配置表中的字段覆盖，缺省的字段保持不变
+    fn merge(mut self, table: &toml::Table) -> Self {
+        if let Some(v) = table.get("trim_trailing_whitespace").and_then(|v| v.as_bool()) {
+            self.trim_trailing_whitespace = v;
+        }
+        if let Some(v) = table.get("insert_final_newline").and_then(|v| v.as_bool()) {
+            self.insert_final_newline = v;
+        }
+        self
+    }
+
+    fn to_table(self) -> toml::Table {
+        let mut table = toml::Table::new();
+        table.insert("trim_trailing_whitespace".to_string(), self.trim_trailing_whitespace.into());
+        table.insert("insert_final_newline".to_string(), self.insert_final_newline.into());
+        table
+    }
+
+    /// 整理后的内容；内容不变时返回 None
+    fn apply(self, text: &str) -> Option<String> {
+        let mut out = if self.trim_trailing_whitespace { trim_trailing_whitespace(text) } else { text.to_string() };
+        if self.insert_final_newline && !out.is_empty() {
+            out.truncate(out.trim_end_matches('\n').len());
+            out.push('\n');
+        }
+        (out != text).then_some(out)
+    }
+}
+
+/// 某种语言对 `SaveCleanup` 的覆盖，`None` 的字段沿用全局设置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaveCleanupOverride {
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+}
+
+impl SaveCleanupOverride {
+    /// 用配置表中写出的字段更新覆盖
+    fn merge(mut self, table: &toml::Table) -> Self {
+        if let Some(v) = table.get("trim_trailing_whitespace").and_then(|v| v.as_bool()) {
+            self.trim_trailing_whitespace = Some(v);
+        }
+        if let Some(v) = table.get("insert_final_newline").and_then(|v| v.as_bool()) {
+            self.insert_final_newline = Some(v);
+        }
+        self
+    }
+
+    /// 只导出被覆盖的字段
+    fn to_table(self) -> toml::Table {
+        let mut table = toml::Table::new();
+        if let Some(v) = self.trim_trailing_whitespace {
+            table.insert("trim_trailing_whitespace".to_string(), v.into());
+        }
+        if let Some(v) = self.insert_final_newline {
+            table.insert("insert_final_newline".to_string(), v.into());
+        }
+        table
+    }
+
+    /// 叠加在全局设置上得到实际使用的设置
+    fn resolve(self, base: SaveCleanup) -> SaveCleanup {
+        SaveCleanup {
+            trim_trailing_whitespace: self.trim_trailing_whitespace.unwrap_or(base.trim_trailing_whitespace),
+            insert_final_newline: self.insert_final_newline.unwrap_or(base.insert_final_newline),
+        }
+    }
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        let mut run_commands = BTreeMap::new();
+        run_commands.insert("rs".to_string(), "cargo run".to_string());
+        run_commands.insert("py".to_string(), "python \"${file}\"".to_string());
+        run_commands.insert("js".to_string(), "node \"${file}\"".to_string());
+        Self {
+            run_commands,
+            show_breadcrumbs: true,
+            relative_line_numbers: false,
+            word_wrap: false,
+            auto_close_brackets: true,
+            match_brackets: true,
+            auto_indent: true,
+            large_file_threshold_kb: 2048,
+            scrolloff: 0,
+            snippets: snippet::builtin(),
+            indent: indent::IndentStyle::default(),
+            language_indent: BTreeMap::new(),
+            on_save: SaveCleanup::default(),
+            // Markdown 用行末的两个空格表示换行；结尾换行仍沿用全局设置
+            language_on_save: BTreeMap::from([(
+                "md".to_string(),
+                SaveCleanupOverride { trim_trailing_whitespace: Some(false), ..SaveCleanupOverride::default() },
+            )]),
+        }
+    }
+}
+
+impl EditorSettings {
+    /// 指定语言实际使用的缩进设置
+    pub fn indent_for(&self, language: &str) -> indent::IndentStyle {
+        self.language_indent.get(language).copied().unwrap_or(self.indent)
+    }
+
+    /// 指定语言实际使用的保存时整理设置
+    pub fn on_save_for(&self, language: &str) -> SaveCleanup {
+        match self.language_on_save.get(language) {
+            Some(overrides) => overrides.resolve(self.on_save),
+            None => self.on_save,
+        }
+    }
+}
+
+/// 按扩展名 (不含点，区分大小写) 映射到语法高亮 ID，未知扩展名返回 "txt"。
+/// ID 是 `egui_extras` 可查找的语法名或扩展名；没有对应语法的 (TOML、Kotlin、INI) 以纯文本显示
+fn ext_to_language(ext: &str) -> &'static str {
+    match ext {
+        "rs" => "rs",
+        "py" | "pyw" => "py",
+        "js" | "mjs" | "cjs" | "ts" => "js",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "json" => "json",
+        "md" | "markdown" => "md",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "xml" | "svg" => "xml",
+        "ini" | "cfg" => "ini",
+        "c" | "h" => "c",
+        "cpp" | "hpp" | "cc" | "cxx" => "cpp",
+        "cs" => "cs",
+        "java" => "java",
+        "kt" | "kts" => "kt",
+        "go" => "go",
+        "rb" => "rb",
+        "php" => "php",
+        "sh" | "bash" | "zsh" => "sh",
+        "sql" => "sql",
+        "lua" => "lua",
+        _ => "txt",
+    }
+}
+
+/// 按文件的扩展名推断语法高亮 ID
+fn language_for_path(path: &Path) -> &'static str {
+    ext_to_language(path.extension().and_then(|s| s.to_str()).unwrap_or(""))
+}
+
+/// 可手动选择的语法高亮 ID (`egui_extras` 按语法名或扩展名查找)
+const LANGUAGES: &[&str] = &[
+    "txt", "rs", "py", "js", "html", "css", "json", "md", "toml", "yaml", "xml", "ini",
+    "c", "cpp", "cs", "java", "kt", "go", "rb", "php", "sh", "sql", "lua",
+];
+
+/// 语法高亮 ID 对应的显示名称 (状态栏、语言菜单)
+fn language_name(language: &str) -> &str {
+    match language {
+        "rs" => "Rust",
+        "py" => "Python",
+        "js" => "JavaScript",
+        "html" => "HTML",
+        "css" => "CSS",
+        "json" => "JSON",
+        "md" => "Markdown",
+        "toml" => "TOML",
+        "yaml" => "YAML",
+        "xml" => "XML",
+        "ini" => "INI",
+        "cs" => "C#",
+        "java" => "Java",
+        "kt" => "Kotlin",
+        "go" => "Go",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "sh" => "Shell",
+        "c" => "C",
+        "cpp" => "C++",
+        "sql" => "SQL",
+        "lua" => "Lua",
+        "txt" => "Plain Text",
+        other => other,
+    }
+}
+
+/// 文件的换行符风格。编辑时内容统一使用 LF，保存时转换回文件原来的 (或用户选择的) 风格
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// 按数量占多数的换行符判断，没有换行符时为 LF
+    fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf { LineEnding::Crlf } else { LineEnding::Lf }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+        }
+    }
+
+    /// 把只含 LF 的内容转换为该风格，用于写入磁盘
+    fn apply(self, text: &str) -> std::borrow::Cow<'_, str> {
+        match self {
+            LineEnding::Lf => std::borrow::Cow::Borrowed(text),
+            LineEnding::Crlf => std::borrow::Cow::Owned(text.replace('\n', "\r\n")),
+        }
+    }
+}
+
+/// 把 CRLF 统一为 LF，返回新内容与原来占多数的换行符风格
+fn normalize_line_endings(text: String) -> (String, LineEnding) {
+    let ending = LineEnding::detect(&text);
+    let text = if text.contains('\r') { text.replace("\r\n", "\n") } else { text };
+    (text, ending)
+}
+
+/// 删除每行末尾的空格与制表符，保留原有换行符
+fn trim_trailing_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        out.push_str(content.trim_end_matches([' ', '\t']));
+        out.push_str(&line[content.len()..]);
+    }
+    out
+}
+
+/// 从文件所在目录向上查找项目根 (包含 Cargo.toml 或 .git 的目录)，找不到时返回文件所在目录
+fn workspace_root(file: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(file);
+    dir.ancestors()
+        .find(|d| d.join("Cargo.toml").exists() || d.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// 将运行命令模板中的占位符替换为实际路径
+fn expand_run_template(template: &str, file: &Path, root: &Path) -> String {
+    let file_dir = file.parent().unwrap_or(file);
+    template
+        .replace("${file}", &file.to_string_lossy())
+        .replace("${fileDir}", &file_dir.to_string_lossy())
+        .replace("${workspaceRoot}", &root.to_string_lossy())
+}
+
+/// 编辑器插件与其标签页共享的运行时状态 (供大纲面板等全局 UI 使用)
+#[derive(Debug, Default)]
+struct EditorShared {
+    /// 最近获得焦点的编辑器
+    focused: Option<u64>,
+    /// 该编辑器最后一次渲染时的 pass 编号，用于判断它是否仍然可见
+    focused_pass: u64,
+    focused_name: String,
+    focused_path: Option<PathBuf>,
+    /// 批量编辑写入磁盘后，等待已打开的编辑器同步的新内容
+    reloads: HashMap<PathBuf, String>,
+    /// 有未保存修改的编辑器及其文件，批量编辑会跳过这些文件
+    unsaved: HashMap<u64, PathBuf>,
+    /// 右键菜单请求文本重命名: (标识符, 所在文件)
+    rename_request: Option<(String, PathBuf)>,
+    /// 请求查找定义: (标识符, 所在文件, 发起请求的编辑器)
+    definition_request: Option<(String, PathBuf, u64)>,
+    /// 打开文件后跳转: (文件, 0 起始行号)，由载入该文件的编辑器领取
+    open_goto: Option<(PathBuf, usize)>,
+    /// 已发布的大纲 (编辑器, revision)
+    outline_source: Option<(u64, u64)>,
+    symbols: Vec<outline::Symbol>,
+    /// 大纲面板请求跳转: (编辑器, 0 起始行号)
+    goto_request: Option<(u64, usize)>,
+}
+
+/// 可自动配对的括号与引号: (左, 右)
+const BRACKET_PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\''), ('`', '`')];
+
+/// 将字符索引转换为字节索引
+fn byte_index(code: &str, char_index: usize) -> usize {
+    code.char_indices().nth(char_index).map(|(b, _)| b).unwrap_or(code.len())
+}
+
+/// 虚拟化编辑器在可见行之外额外布局的行数
+const VIRTUAL_MARGIN: usize = 20;
+
+/// 将全局 (行, 列) 转换为从 `first_line` 开始的窗口文本中的字符索引，窗口外的位置截断到窗口边缘
+fn window_index(window: &str, first_line: usize, (line, col): (usize, usize)) -> usize {
+    if line < first_line {
+        return 0;
+    }
+    let mut index = 0;
+    for (i, text) in window.split('\n').enumerate() {
+        let len = text.chars().count();
+        if first_line + i == line {
+            return index + col.min(len);
+        }
+        index += len + 1;
+    }
+    index.saturating_sub(1)
+}
+
+/// `window_index` 的逆运算
+fn window_line_col(window: &str, first_line: usize, index: usize) -> (usize, usize) {
+    let before: Vec<char> = window.chars().take(index).collect();
+    let line = before.iter().filter(|&&c| c == '\n').count();
+    let col = before.iter().rev().take_while(|&&c| c != '\n').count();
+    (first_line + line, col)
+}
+
+/// 返回第 `line` 行第 `col` 列 (均为 0 起始) 的字符索引，越界时截断到行尾/文末
+fn char_index_of(code: &str, line: usize, col: usize) -> usize {
+    let mut index = 0;
+    for (i, text) in code.split('\n').enumerate() {
+        let len = text.chars().count();
+        if i == line {
+            return index + col.min(len);
+        }
+        index += len + 1;
+    }
+    code.chars().count()
+}
+
 #[derive(Debug, Clone)]
 enum EditorState {
     Loading(Arc<RwLock<Option<Result<String, String>>>>),
@@ -17,40 +414,1458 @@ pub struct CodeEditorTab {
     pub code: String,
     pub language: String,
     pub is_dirty: bool,
-    pub sync_mode: bool,
-    pub last_sync_time: f64,
     state: EditorState,
+    settings: Arc<RwLock<EditorSettings>>,
+    shared: Arc<RwLock<EditorShared>>,
+    /// 编辑器实例的唯一标识 (用于与插件共享状态)
+    key: u64,
+    /// 待执行的跳转: (0 起始行号, 0 起始列号)
+    pending_goto: Option<(usize, usize)>,
+    /// 上一帧 TextEdit 的 id，用于在其处理输入前读取/修改光标状态
+    text_edit_id: Option<egui::Id>,
+    /// 内容版本号，每次 `code` 变化时递增，用于失效各类缓存
+    revision: u64,
+    /// 缓存的大纲 (计算时的 revision, 符号列表)
+    outline: Option<(u64, Vec<outline::Symbol>)>,
+    /// 缓存的项目根目录 (文件路径, 根目录)
+    root_cache: Option<(PathBuf, PathBuf)>,
+    /// 光标位置缓存: (字符索引, 计算时的 revision, 0 起始行号, 0 起始列号)
+    cursor: (usize, u64, usize, usize),
+    /// 选中的字符数 (状态栏)
+    selection_len: usize,
+    /// 保存时使用的换行符风格，载入时按文件内容检测
+    line_ending: LineEnding,
+    /// 大文件模式：关闭语法高亮、大纲等开销较大的功能，只渲染可见的行
+    large_file: bool,
+    /// 用户在大文件模式中选择了保留完整功能，内容再增长也不自动切换
+    full_features: bool,
+    /// 各行起始字节偏移的缓存 (计算时的 revision, 偏移列表)
+    line_starts: Option<(u64, Vec<usize>)>,
+    /// 虚拟化编辑器的光标: ((锚点行, 列), (光标行, 列))，与窗口位置无关
+    virtual_cursor: Option<((usize, usize), (usize, usize))>,
+    /// 下一帧让虚拟化编辑器获得焦点 (跳转后)
+    focus_virtual_editor: bool,
+    /// 最近一次读取或写入时文件的修改时间
+    disk_mtime: Option<std::time::SystemTime>,
+    /// 下一帧检查文件是否在外部被修改 (标签页获得焦点时设置)
+    check_disk: bool,
+    /// 监听文件在外部的修改 (同步与跟随模式下不需要)
+    disk_watch: Option<sync::SyncWatcher>,
+    /// 有未保存的修改时文件在外部被修改：磁盘上的新内容，等待用户选择保留哪一份
+    disk_conflict: Option<String>,
+    /// 在冲突提示下方显示差异
+    show_disk_diff: bool,
+    /// "Follow File" 模式：追加文件新增的内容并自动滚动到底部
+    follow: Option<follow::Follower>,
+    /// 同步模式：文件变化时重新载入，开启时为 Some
+    sync: Option<sync::SyncWatcher>,
+    /// 上次应用 scrolloff 时的光标位置，光标移动后才调整滚动
+    scrolloff_cursor: Option<(usize, usize)>,
+    /// 当前是否有非空选区 (供 `capabilities` 使用)
+    has_selection: bool,
+    /// 在编辑区旁显示 JSON/TOML 的结构视图
+    structure_view: bool,
+    /// 缓存的解析结果 (解析时的 revision, 结构树或错误)
+    structure: Option<(u64, Result<structured::Node, structured::ParseError>)>,
+    /// 结构视图中正在编辑的值: (源码范围, 输入内容)
+    structure_edit: Option<(std::ops::Range<usize>, String)>,
+    /// 正在填写的代码片段
+    snippet: Option<snippet::Session>,
+    /// 撤销 / 重做历史
+    history: undo::History,
+    /// 查找 / 替换栏，打开时为 Some
+    find: Option<find::FindState>,
+    /// 跳转到行弹窗的输入，打开时为 Some
+    goto_line: Option<GotoLine>,
+    /// 保存时整理前的内容，下一帧据此把光标与选区映射到整理后的内容中
+    cleaned_from: Option<String>,
+}
+
+/// 跳转到行 (默认 Ctrl + G) 弹窗的状态
+#[derive(Debug, Clone, Default)]
+struct GotoLine {
+    input: String,
+    /// 下一帧让输入框获得焦点
+    focus: bool,
+}
+
+/// 解析跳转输入 `行` 或 `行:列` (均为 1 起始)，返回 (行, 列)
+fn parse_goto(input: &str) -> Option<(usize, Option<usize>)> {
+    let (line, col) = match input.trim().split_once(':') {
+        Some((line, col)) => (line, Some(col.trim())),
+        None => (input.trim(), None),
+    };
+    let line = line.trim().parse().ok()?;
+    let col = match col {
+        Some(col) if !col.is_empty() => Some(col.parse().ok()?),
+        _ => None,
+    };
+    Some((line, col))
 }
 
 impl CodeEditorTab {
-    fn new(name: String, path: Option<std::path::PathBuf>, code: String, language: String) -> Self {
+    fn new(
+        name: String,
+        path: Option<std::path::PathBuf>,
+        code: String,
+        language: String,
+        settings: Arc<RwLock<EditorSettings>>,
+        shared: Arc<RwLock<EditorShared>>,
+    ) -> Self {
         Self {
             name,
             path,
             code,
             language,
             is_dirty: false,
-            sync_mode: false,
-            last_sync_time: 0.0,
             state: EditorState::Ready,
+            settings,
+            shared,
+            key: NEXT_EDITOR_KEY.fetch_add(1, Ordering::SeqCst),
+            pending_goto: None,
+            text_edit_id: None,
+            revision: 0,
+            outline: None,
+            root_cache: None,
+            cursor: (0, 0, 0, 0),
+            selection_len: 0,
+            line_ending: LineEnding::default(),
+            large_file: false,
+            full_features: false,
+            line_starts: None,
+            virtual_cursor: None,
+            focus_virtual_editor: false,
+            disk_mtime: None,
+            check_disk: false,
+            disk_watch: None,
+            disk_conflict: None,
+            show_disk_diff: false,
+            follow: None,
+            sync: None,
+            scrolloff_cursor: None,
+            has_selection: false,
+            structure_view: false,
+            structure: None,
+            structure_edit: None,
+            snippet: None,
+            history: undo::History::default(),
+            find: None,
+            goto_line: None,
+            cleaned_from: None,
+        }
+    }
+
+    /// 标记内容已变化，使依赖内容的缓存失效
+    fn bump_revision(&mut self) {
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    /// 当前光标所在行 (0 起始)
+    fn cursor_line(&self) -> usize {
+        self.cursor.2
+    }
+
+    /// 从 TextEdit 的持久化状态中读取光标位置，仅在光标或内容变化时重新计算行列号
+    fn update_cursor(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(range) = egui::TextEdit::load_state(ctx, id).and_then(|state| state.cursor.char_range()) else {
+            return;
+        };
+        self.has_selection = range.primary.index != range.secondary.index;
+        self.selection_len = range.primary.index.abs_diff(range.secondary.index);
+        let index = range.primary.index;
+        if (index, self.revision) != (self.cursor.0, self.cursor.1) {
+            let (mut line, mut col) = (0, 0);
+            for c in self.code.chars().take(index) {
+                if c == '\n' {
+                    line += 1;
+                    col = 0;
+                } else {
+                    col += 1;
+                }
+            }
+            self.cursor = (index, self.revision, line, col);
+        }
+    }
+
+    fn cached_workspace_root(&mut self, path: &Path) -> PathBuf {
+        match &self.root_cache {
+            Some((file, root)) if file == path => root.clone(),
+            _ => {
+                let root = workspace_root(path);
+                self.root_cache = Some((path.to_path_buf(), root.clone()));
+                root
+            }
+        }
+    }
+
+    /// 返回当前内容的大纲，内容未变化时复用缓存
+    fn symbols(&mut self) -> &[outline::Symbol] {
+        if self.large_file {
+            return &[];
+        }
+        if self.outline.as_ref().map(|(rev, _)| *rev) != Some(self.revision) {
+            let symbols = outline::extract_symbols(&self.language, &self.code);
+            self.outline = Some((self.revision, symbols));
+        }
+        self.outline.as_ref().map(|(_, s)| s.as_slice()).unwrap_or_default()
+    }
+
+    /// 更新各行起始字节偏移的缓存，内容未变化时不重新计算
+    fn update_line_starts(&mut self) {
+        if self.line_starts.as_ref().map(|(rev, _)| *rev) != Some(self.revision) {
+            let starts = std::iter::once(0)
+                .chain(self.code.match_indices('\n').map(|(i, _)| i + 1))
+                .collect();
+            self.line_starts = Some((self.revision, starts));
+        }
+    }
+
+    fn virtual_editor_id(&self) -> egui::Id {
+        egui::Id::new("code_editor_virtual").with(self.key)
+    }
+
+    /// 内容在打开后增长到超过阈值 (跟随的日志、同步的文件、粘贴的大段文本) 时切换到大文件模式，
+    /// 保持当前光标位置
+    fn check_large_file(&mut self) {
+        if self.large_file || self.full_features {
+            return;
+        }
+        let threshold = self.settings.read().large_file_threshold_kb.saturating_mul(1024);
+        if self.code.len() as u64 > threshold {
+            self.large_file = true;
+            let position = (self.cursor.2, self.cursor.3);
+            self.virtual_cursor = Some((position, position));
+        }
+    }
+
+    /// 大文件模式下的虚拟化编辑器：只把可见行 (上下各多留 `VIRTUAL_MARGIN` 行) 放入 TextEdit 布局，
+    /// 编辑结果再写回完整内容。该模式下不换行，选区限于当前窗口内。
+    fn virtual_editor_ui(&mut self, ui: &mut Ui, relative_numbers: bool) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("⚠ Large file mode: syntax highlighting and outline are off.").weak());
+            if ui.small_button("Enable full features").on_hover_text("May be slow for files of this size").clicked() {
+                self.large_file = false;
+                self.full_features = true;
+            }
+        });
+        ui.separator();
+
+        self.update_line_starts();
+        let line_count = self.line_starts.as_ref().map_or(1, |(_, s)| s.len());
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        let row_height = ui.fonts_mut(|f| f.row_height(&font_id));
+        let digits = line_count.to_string().len().max(2);
+        let gutter_width = digits as f32 * ui.fonts_mut(|f| f.glyph_width(&font_id, '0')) + 8.0;
+
+        let mut scroll_area = egui::ScrollArea::both().id_salt("code_editor_scroll").auto_shrink(false);
+        if let Some((line, col)) = self.pending_goto.take() {
+            let viewport_height = ui.available_height();
+            scroll_area = scroll_area.vertical_scroll_offset((line as f32 * row_height - viewport_height / 3.0).max(0.0));
+            self.virtual_cursor = Some(((line, col), (line, col)));
+            self.focus_virtual_editor = true;
+        }
+
+        let scroll_output = scroll_area.stick_to_bottom(self.follow.is_some()).show_viewport(ui, |ui, viewport| {
+            let origin = ui.max_rect().min;
+            let first = ((viewport.min.y / row_height) as usize).saturating_sub(VIRTUAL_MARGIN).min(line_count - 1);
+            let last = ((viewport.max.y / row_height).ceil() as usize + VIRTUAL_MARGIN).clamp(first + 1, line_count);
+            let (start, end) = {
+                let starts = self.line_starts.as_ref().map(|(_, s)| s.as_slice()).unwrap_or_default();
+                (starts[first], if last < line_count { starts[last] - 1 } else { self.code.len() })
+            };
+            let mut window = self.code[start..end].to_string();
+
+            // 把全局光标 (行, 列) 映射到当前窗口内
+            let id = self.virtual_editor_id();
+            let placed = self.virtual_cursor.map(|(anchor, cursor)| {
+                (window_index(&window, first, anchor), window_index(&window, first, cursor))
+            });
+            if let Some((anchor, cursor)) = placed {
+                let mut state = egui::TextEdit::load_state(ui.ctx(), id).unwrap_or_default();
+                state.cursor.set_char_range(Some(egui::text::CCursorRange::two(
+                    egui::text::CCursor::new(anchor),
+                    egui::text::CCursor::new(cursor),
+                )));
+                state.store(ui.ctx(), id);
+            }
+
+            let top = origin.y + first as f32 * row_height;
+            let gutter_rect = egui::Rect::from_min_size(egui::pos2(origin.x, top), egui::vec2(gutter_width, (last - first) as f32 * row_height));
+            let editor_rect = egui::Rect::from_min_max(
+                egui::pos2(gutter_rect.right() + 8.0, top),
+                egui::pos2(ui.max_rect().right().max(gutter_rect.right() + 100.0), gutter_rect.bottom()),
+            );
+            let output = ui.scope_builder(egui::UiBuilder::new().max_rect(editor_rect), |ui| {
+                ui.add_enabled_ui(!self.read_only(), |ui| {
+                    egui::TextEdit::multiline(&mut window)
+                        .id(id)
+                        .font(egui::TextStyle::Monospace)
+                        .code_editor()
+                        .lock_focus(true)
+                        .frame(false)
+                        .margin(egui::Margin::ZERO)
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(last - first)
+                        .show(ui)
+                }).inner
+            }).inner;
+
+            let jumped = std::mem::take(&mut self.focus_virtual_editor);
+            if jumped {
+                output.response.request_focus();
+            }
+            if output.response.changed() {
+                self.code.replace_range(start..end, &window);
+                self.is_dirty = true;
+                self.bump_revision();
+            }
+
+            // 用户移动了光标时才更新全局光标，否则窗口边缘的截断位置会覆盖原位置
+            if let Some(range) = output.state.cursor.char_range() {
+                let now = (range.secondary.index, range.primary.index);
+                if placed != Some(now) || output.response.changed() {
+                    self.virtual_cursor = Some((
+                        window_line_col(&window, first, now.0),
+                        window_line_col(&window, first, now.1),
+                    ));
+                }
+            }
+            if let Some((_, (line, col))) = self.virtual_cursor {
+                self.cursor = (0, self.revision, line, col);
+            }
+            // 以全局位置判断光标是否移动，窗口随滚动平移不算移动
+            if let Some((_, position)) = self.virtual_cursor {
+                if !jumped {
+                    self.apply_scrolloff(ui, &output, position);
+                }
+            }
+            // TextEdit 只向辅助功能报告窗口内的文本，在标签中注明窗口在整个文件中的位置
+            output.response.widget_info(|| egui::WidgetInfo {
+                label: Some(format!("{}, lines {} to {} of {}", self.name, first + 1, last, line_count)),
+                ..egui::WidgetInfo::new(egui::WidgetType::TextEdit)
+            });
+
+            self.paint_gutter(ui, gutter_rect, &output, &font_id, relative_numbers, first);
+            ui.set_min_size(egui::vec2(
+                gutter_width + 8.0 + output.galley.size().x,
+                line_count as f32 * row_height,
+            ));
+        });
+        self.update_follow_paused(&scroll_output);
+    }
+
+    /// 跟随模式下离开底部即视为暂停；`stick_to_bottom` 在回到底部后会自动恢复滚动
+    fn update_follow_paused<R>(&mut self, output: &egui::scroll_area::ScrollAreaOutput<R>) {
+        if let Some(follower) = &mut self.follow {
+            follower.paused = output.state.offset.y + output.inner_rect.height() < output.content_size.y - 1.0;
+        }
+    }
+
+    /// 撤销 (`redo == false`) 或重做一步，恢复当时的内容与选区
+    fn undo(&mut self, ctx: &egui::Context, redo: bool) {
+        let restored = if redo { self.history.redo() } else { self.history.undo() };
+        let Some((code, selection)) = restored else { return };
+        self.code = code;
+        self.bump_revision();
+        self.history.synced(self.revision);
+        self.is_dirty = !self.history.is_saved();
+        self.snippet = None;
+        if let Some((anchor, cursor)) = selection.filter(|_| !self.large_file) {
+            let len = self.code.chars().count();
+            self.set_selection(ctx, anchor.min(len), cursor.min(len));
+        }
+    }
+
+    /// 当前光标范围 (锚点, 光标) 的字符索引
+    fn cursor_range(&self, ctx: &egui::Context) -> Option<(usize, usize)> {
+        let range = egui::TextEdit::load_state(ctx, self.text_edit_id?)?.cursor.char_range()?;
+        Some((range.secondary.index, range.primary.index))
+    }
+
+    /// 当前选区 (起始, 结束) 的字符索引，起始不大于结束
+    fn selection(&self, ctx: &egui::Context) -> Option<(usize, usize)> {
+        let (a, b) = self.cursor_range(ctx)?;
+        Some((a.min(b), a.max(b)))
+    }
+
+    /// 选中的标识符；没有选区时取光标所在的标识符
+    fn identifier_at_cursor(&self, ctx: &egui::Context) -> Option<String> {
+        let chars: Vec<char> = self.code.chars().collect();
+        let (start, end) = self.selection(ctx).map(|(s, e)| (s.min(chars.len()), e.min(chars.len())))?;
+        let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+        let (start, end) = if start == end {
+            let before = chars[..start].iter().rev().take_while(|c| is_word(c)).count();
+            let after = chars[start..].iter().take_while(|c| is_word(c)).count();
+            (start - before, start + after)
+        } else {
+            (start, end)
+        };
+        let word: String = chars[start..end].iter().collect();
+        batch::is_identifier(&word).then_some(word)
+    }
+
+    /// 设置选区；`anchor == cursor` 时仅移动光标
+    fn set_selection(&self, ctx: &egui::Context, anchor: usize, cursor: usize) {
+        let Some(id) = self.text_edit_id else { return };
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        state.cursor.set_char_range(Some(egui::text::CCursorRange::two(
+            egui::text::CCursor::new(anchor),
+            egui::text::CCursor::new(cursor),
+        )));
+        state.store(ctx, id);
+    }
+
+    /// 在 TextEdit 处理输入之前拦截按键，实现各类编辑辅助功能
+    fn handle_editing_keys(&mut self, ui: &mut Ui) {
+        let Some(id) = self.text_edit_id else { return };
+        if self.read_only() || !ui.memory(|m| m.has_focus(id)) {
+            return;
+        }
+        let ctx = ui.ctx().clone();
+        let (auto_close, auto_indent) = {
+            let settings = self.settings.read();
+            (settings.auto_close_brackets, settings.auto_indent)
+        };
+
+        // 代码片段: Tab 展开触发词或跳到下一个占位符，Shift+Tab 回到上一个，Esc 退出
+        self.update_snippet(&ctx);
+        if self.snippet.is_some() && ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+            self.snippet = None;
+        }
+        let tab = ui.input(|i| i.events.iter().find_map(|e| match e {
+            egui::Event::Key { key: egui::Key::Tab, pressed: true, modifiers, .. }
+                if !modifiers.command && !modifiers.alt => Some(modifiers.shift),
+            _ => None,
+        }));
+        if let Some(backwards) = tab {
+            if self.snippet_tab(&ctx, backwards) || self.indent_tab(&ctx, backwards) {
+                ui.input_mut(|i| i.events.retain(|e| !matches!(e, egui::Event::Key { key: egui::Key::Tab, .. })));
+            }
+        }
+
+        // 选中文本时输入左括号/引号：用配对符号包裹选区，并保持选中
+        if auto_close {
+            if let Some((start, end)) = self.selection(&ctx).filter(|(s, e)| s != e) {
+                let typed = ui.input(|i| i.events.iter().find_map(|e| match e {
+                    egui::Event::Text(t) => {
+                        let mut chars = t.chars();
+                        let c = chars.next()?;
+                        if chars.next().is_some() { return None; }
+                        BRACKET_PAIRS.iter().find(|(open, _)| *open == c).copied()
+                    }
+                    _ => None,
+                }));
+                if let Some((open, close)) = typed {
+                    ui.input_mut(|i| i.events.retain(|e| !matches!(e, egui::Event::Text(_))));
+                    let (bs, be) = (byte_index(&self.code, start), byte_index(&self.code, end));
+                    self.code.insert(be, close);
+                    self.code.insert(bs, open);
+                    self.set_selection(&ctx, start + 1, end + 1);
+                    self.is_dirty = true;
+                    self.bump_revision();
+                }
+            }
+            self.auto_pair_keys(ui);
+        }
+
+        if auto_indent {
+            self.auto_indent_keys(ui);
+        }
+
+        // 智能 Home：在行首第一个非空白字符与第 0 列之间切换，Shift 时扩展选区
+        let home = ui.input_mut(|i| {
+            let mut shift = None;
+            i.events.retain(|e| match e {
+                egui::Event::Key { key: egui::Key::Home, pressed, modifiers, .. }
+                    if !modifiers.command && !modifiers.alt =>
+                {
+                    if *pressed {
+                        shift = Some(modifiers.shift);
+                    }
+                    false
+                }
+                _ => true,
+            });
+            shift
+        });
+        if let Some(shift) = home {
+            if let Some((anchor, cursor)) = self.cursor_range(&ctx) {
+                let line_start = self.code.chars().take(cursor).collect::<Vec<_>>()
+                    .iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+                let indent = self.code.chars().skip(line_start)
+                    .take_while(|c| *c == ' ' || *c == '\t').count();
+                let first_non_blank = line_start + indent;
+                let target = if cursor == first_non_blank { line_start } else { first_non_blank };
+                self.set_selection(&ctx, if shift { anchor } else { target }, target);
+            }
+        }
+    }
+
+    /// 没有选区时输入左括号 / 引号：同时插入右侧符号，光标留在两者之间；
+    /// 输入的右括号或引号正好在光标右侧时越过它
+    fn auto_pair_keys(&mut self, ui: &mut Ui) {
+        let ctx = ui.ctx().clone();
+        let Some((start, end)) = self.selection(&ctx) else { return };
+        if start != end {
+            return;
+        }
+        let typed = ui.input(|i| i.events.iter().find_map(|e| match e {
+            egui::Event::Text(t) => {
+                let mut chars = t.chars();
+                let c = chars.next()?;
+                chars.next().is_none().then_some(c)
+            }
+            _ => None,
+        }));
+        let Some(typed) = typed else { return };
+        let at = byte_index(&self.code, start);
+        let before = self.code[..at].chars().next_back();
+        let after = self.code[at..].chars().next();
+
+        if brackets::steps_over(typed, after) {
+            ui.input_mut(|i| i.events.retain(|e| !matches!(e, egui::Event::Text(_))));
+            self.set_selection(&ctx, start + 1, start + 1);
+            return;
+        }
+        let Some(&(open, close)) = BRACKET_PAIRS.iter().find(|(open, _)| *open == typed) else { return };
+        if !brackets::should_close(&self.language, open, before, after) {
+            return;
+        }
+        ui.input_mut(|i| i.events.retain(|e| !matches!(e, egui::Event::Text(_))));
+        self.code.insert(at, close);
+        self.code.insert(at, open);
+        self.set_selection(&ctx, start + 1, start + 1);
+        self.is_dirty = true;
+        self.bump_revision();
+    }
+
+    /// 自动缩进: 回车时新行沿用当前行的缩进 (块的开头之后多缩进一级)，在空白行首输入 `}` 时减少一级
+    fn auto_indent_keys(&mut self, ui: &mut Ui) {
+        let ctx = ui.ctx().clone();
+        let enter = ui.input(|i| i.events.iter().any(|e| matches!(e,
+            egui::Event::Key { key: egui::Key::Enter, pressed: true, modifiers, .. } if !modifiers.command && !modifiers.alt)));
+        let electric = ui.input(|i| i.events.iter().find_map(|e| match e {
+            egui::Event::Text(t) => {
+                let mut chars = t.chars();
+                let c = chars.next()?;
+                (chars.next().is_none() && indent::is_electric(&self.language, c)).then_some(c)
+            }
+            _ => None,
+        }));
+        if !enter && electric.is_none() {
+            return;
+        }
+        let Some((start, end)) = self.selection(&ctx) else { return };
+        let style = self.settings.read().indent_for(&self.language);
+        let (bs, be) = (byte_index(&self.code, start), byte_index(&self.code, end));
+        let line_start = self.code[..bs].rfind('\n').map_or(0, |i| i + 1);
+        let before = &self.code[line_start..bs];
+        let leading: String = before.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+
+        if enter {
+            ui.input_mut(|i| i.events.retain(|e| !matches!(e, egui::Event::Key { key: egui::Key::Enter, .. })));
+            let mut insert = format!("\n{}", leading);
+            if indent::opens_block(&self.language, before) {
+                insert.push_str(&style.unit());
+                // 光标正好在一对括号之间时，右括号移到再下一行并与当前行对齐
+                if self.code[be..].chars().next().is_some_and(|c| indent::closes_block(before, c)) {
+                    let caret = start + insert.chars().count();
+                    insert.push('\n');
+                    insert.push_str(&leading);
+                    self.code.replace_range(bs..be, &insert);
+                    self.set_selection(&ctx, caret, caret);
+                    self.is_dirty = true;
+                    self.bump_revision();
+                    return;
+                }
+            }
+            let caret = start + insert.chars().count();
+            self.code.replace_range(bs..be, &insert);
+            self.set_selection(&ctx, caret, caret);
+        } else if let Some(typed) = electric {
+            // 只在光标之前全是空白且没有选区时减少缩进
+            if start != end || before.is_empty() || before.len() != leading.len() {
+                return;
+            }
+            ui.input_mut(|i| i.events.retain(|e| !matches!(e, egui::Event::Text(_))));
+            let insert = format!("{}{}", indent::dedent_once(&leading, style), typed);
+            let caret = start - leading.chars().count() + insert.chars().count();
+            self.code.replace_range(line_start..bs, &insert);
+            self.set_selection(&ctx, caret, caret);
+        }
+        self.is_dirty = true;
+        self.bump_revision();
+    }
+
+    /// 软制表符: Tab 插入空格补齐到下一个制表位，选中多行时整体缩进；Shift+Tab 去掉一级缩进。
+    /// 返回是否已处理，未开启软制表符时交给 TextEdit 处理
+    fn indent_tab(&mut self, ctx: &egui::Context, backwards: bool) -> bool {
+        let style = self.settings.read().indent_for(&self.language);
+        if !style.insert_spaces {
+            return false;
+        }
+        let Some((anchor, cursor)) = self.cursor_range(ctx) else { return false };
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+        let (first, last) = self.selected_lines(start, end);
+
+        if !backwards && first == last {
+            let (bs, be) = (byte_index(&self.code, start), byte_index(&self.code, end));
+            let column = self.code[..bs].chars().rev().take_while(|c| *c != '\n').count();
+            let spaces = style.spaces_at(column);
+            self.code.replace_range(bs..be, &spaces);
+            let caret = start + spaces.len();
+            self.set_selection(ctx, caret, caret);
+        } else {
+            let code = if backwards {
+                indent::dedent_lines(&self.code, first..last + 1, style)
+            } else {
+                indent::indent_lines(&self.code, first..last + 1, style)
+            };
+            self.replace_lines(ctx, (anchor, cursor), first, code);
+        }
+        self.is_dirty = true;
+        self.bump_revision();
+        true
+    }
+
+    /// 选区 (字符索引) 覆盖的行: (首行, 末行)，均为 0 起始。
+    /// 多行选区的终点在行首时不包含该行
+    fn selected_lines(&self, start: usize, end: usize) -> (usize, usize) {
+        let line_of = |char_index: usize| self.code.chars().take(char_index).filter(|c| *c == '\n').count();
+        let (first, mut last) = (line_of(start), line_of(end));
+        if last > first && self.code.chars().nth(end.wrapping_sub(1)) == Some('\n') {
+            last -= 1;
+        }
+        (first, last)
+    }
+
+    /// 用逐行修改后的 `code` 替换内容，并让选区跟随修改:
+    /// 起点随首行的长度变化移动 (不越过行首)，终点随总长度的变化移动
+    fn replace_lines(&mut self, ctx: &egui::Context, (anchor, cursor): (usize, usize), first: usize, code: String) {
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+        let line_len = |code: &str, line: usize| code.split_inclusive('\n').nth(line).map_or(0, |l| l.chars().count());
+        let first_start: usize = self.code.split_inclusive('\n').take(first).map(|l| l.chars().count()).sum();
+        let first_delta = line_len(&code, first) as isize - line_len(&self.code, first) as isize;
+        let delta = code.chars().count() as isize - self.code.chars().count() as isize;
+        self.code = code;
+        let new_start = (start as isize + first_delta).max(first_start as isize) as usize;
+        let new_end = ((end as isize + delta).max(0) as usize).max(new_start);
+        let (new_anchor, new_cursor) = if anchor <= cursor { (new_start, new_end) } else { (new_end, new_start) };
+        self.set_selection(ctx, new_anchor, new_cursor);
+    }
+
+    /// 切换当前行或选中各行的行注释
+    fn toggle_comment(&mut self, ctx: &egui::Context) {
+        let Some((anchor, cursor)) = self.cursor_range(ctx) else { return };
+        let (first, last) = self.selected_lines(anchor.min(cursor), anchor.max(cursor));
+        let Some(code) = comment::toggle(&self.code, first..last + 1, &self.language) else { return };
+        if code == self.code {
+            return;
+        }
+        self.replace_lines(ctx, (anchor, cursor), first, code);
+        self.snippet = None;
+        self.is_dirty = true;
+        self.bump_revision();
+    }
+
+    /// 同步片段占位符的位置；光标离开片段区域时结束片段
+    fn update_snippet(&mut self, ctx: &egui::Context) {
+        let Some(session) = &mut self.snippet else { return };
+        let len = self.code.chars().count();
+        if len != session.len {
+            session.shift(len as isize - session.len as isize);
+            session.len = len;
+        }
+        let region = session.region.clone();
+        let cursor = self.cursor_range(ctx).map(|(_, cursor)| cursor);
+        if cursor.is_none_or(|c| c < region.start || c > region.end) {
+            self.snippet = None;
+        }
+    }
+
+    /// 处理 Tab / Shift+Tab，返回是否已处理 (已处理时不再交给 TextEdit 插入制表符)
+    fn snippet_tab(&mut self, ctx: &egui::Context, backwards: bool) -> bool {
+        if let Some(session) = &mut self.snippet {
+            if backwards {
+                session.current = session.current.saturating_sub(1);
+            } else if session.current + 1 < session.stops.len() {
+                session.current += 1;
+            }
+            let stop = session.current_stop();
+            // 到达最后的位置 ($0) 后结束片段
+            if session.current + 1 == session.stops.len() {
+                self.snippet = None;
+            }
+            self.set_selection(ctx, stop.start, stop.end);
+            return true;
+        }
+        !backwards && self.expand_snippet(ctx)
+    }
+
+    /// 光标前的单词是当前语言的片段触发词时展开片段，并选中第一个占位符
+    fn expand_snippet(&mut self, ctx: &egui::Context) -> bool {
+        let Some((start, end)) = self.selection(ctx) else { return false };
+        if start != end {
+            return false;
+        }
+        let before: Vec<char> = self.code.chars().take(end).collect();
+        let word_len = before.iter().rev().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+        if word_len == 0 {
+            return false;
+        }
+        let word_start = end - word_len;
+        let word: String = before[word_start..].iter().collect();
+        let body = self.settings.read().snippets.get(&self.language).and_then(|s| s.get(&word)).cloned();
+        let Some(body) = body else { return false };
+
+        let line_start = before.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+        let indent: String = before[line_start..].iter().take_while(|c| **c == ' ' || **c == '\t').collect();
+        let expansion = snippet::expand(&body, &indent);
+        let (bs, be) = (byte_index(&self.code, word_start), byte_index(&self.code, end));
+        self.code.replace_range(bs..be, &expansion.text);
+        self.is_dirty = true;
+        self.bump_revision();
+
+        let stops: Vec<std::ops::Range<usize>> = expansion.stops.iter()
+            .map(|r| word_start + r.start..word_start + r.end)
+            .collect();
+        let first = stops[0].clone();
+        let session = snippet::Session {
+            current: 0,
+            region: word_start..word_start + expansion.text.chars().count(),
+            len: self.code.chars().count(),
+            stops,
+        };
+        // 只有 $0 时展开后即结束
+        self.snippet = (session.stops.len() > 1).then_some(session);
+        self.set_selection(ctx, first.start, first.end);
+        true
+    }
+
+    /// 领取批量编辑写入的新内容；有未保存修改时保留缓冲区并提示
+    fn take_reload(&mut self, control: &mut Vec<AppCommand>) {
+        let Some(path) = &self.path else { return };
+        if self.shared.read().reloads.is_empty() {
+            return;
+        }
+        let Some(code) = self.shared.write().reloads.remove(path) else { return };
+        let (code, line_ending) = normalize_line_endings(code);
+        if self.is_dirty {
+            control.push(AppCommand::notify(
+                format!("{} has unsaved changes; the batch edit was only written to disk", self.name),
+                crate::NotificationLevel::Warning,
+            ));
+        } else if code != self.code {
+            self.code = code;
+            self.line_ending = line_ending;
+            self.bump_revision();
+        }
+        self.remember_disk_mtime();
+    }
+
+    /// 向插件登记本编辑器是否有未保存的修改
+    fn publish_unsaved(&self) {
+        let unsaved = self.path.as_ref().filter(|_| self.is_dirty);
+        if self.shared.read().unsaved.get(&self.key) == unsaved {
+            return;
+        }
+        let mut shared = self.shared.write();
+        match unsaved {
+            Some(path) => shared.unsaved.insert(self.key, path.clone()),
+            None => shared.unsaved.remove(&self.key),
+        };
+    }
+
+    /// 同步模式或跟随模式下内容来自磁盘，不允许编辑
+    fn read_only(&self) -> bool {
+        self.sync.is_some() || self.follow.is_some()
+    }
+
+    fn start_sync(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        let Some(path) = &self.path else { return };
+        match sync::SyncWatcher::start(path, ctx.clone()) {
+            Ok(watcher) => {
+                self.follow = None;
+                self.sync = Some(watcher);
+            }
+            Err(e) => control.push(AppCommand::notify(
+                format!("Cannot sync {}: {}", self.name, e),
+                crate::NotificationLevel::Error,
+            )),
+        }
+    }
+
+    /// 同步模式下载入磁盘上的最新内容
+    fn poll_sync(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        let Some(update) = self.sync.as_mut().and_then(|s| s.poll(ctx)) else { return };
+        match update {
+            sync::SyncUpdate::Changed(content) => {
+                let (content, line_ending) = normalize_line_endings(content);
+                self.line_ending = line_ending;
+                if content != self.code {
+                    self.code = content;
+                    self.bump_revision();
+                }
+                self.is_dirty = false;
+                self.remember_disk_mtime();
+            }
+            // 保留当前内容，文件重新出现时继续同步
+            sync::SyncUpdate::Removed => control.push(AppCommand::notify(
+                format!("{} was deleted; sync resumes if it reappears", self.name),
+                crate::NotificationLevel::Warning,
+            )),
+        }
+    }
+
+    fn start_follow(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        let Some(path) = &self.path else { return };
+        match follow::Follower::start(path, ctx.clone()) {
+            Ok(follower) => {
+                self.sync = None;
+                self.follow = Some(follower);
+            }
+            Err(e) => control.push(AppCommand::notify(
+                format!("Cannot follow {}: {}", self.name, e),
+                crate::NotificationLevel::Error,
+            )),
+        }
+    }
+
+    /// 读入跟随的文件新增的内容
+    fn poll_follow(&mut self) {
+        let Some(update) = self.follow.as_mut().and_then(|f| f.poll()) else { return };
+        match update {
+            follow::FollowUpdate::Appended(text) => self.code.push_str(&text),
+            follow::FollowUpdate::Reloaded(text) => {
+                self.code = text;
+                // 跳到末尾，之后由 stick_to_bottom 保持在底部
+                self.pending_goto = Some((self.code.matches('\n').count(), 0));
+            }
+        }
+        self.is_dirty = false;
+        self.bump_revision();
+        self.remember_disk_mtime();
+    }
+
+    /// 跟随模式的状态栏
+    fn follow_bar_ui(&mut self, ui: &mut Ui) {
+        let Some(follower) = &self.follow else { return };
+        let paused = follower.paused;
+        ui.horizontal(|ui| {
+            if paused {
+                ui.colored_label(ui.visuals().warn_fg_color, "⏸ Following paused")
+                    .on_hover_text("Scroll to the bottom to resume");
+            } else {
+                ui.colored_label(egui::Color32::from_rgb(100, 200, 100), "📡 Following");
+            }
+            if ui.small_button("Stop").clicked() {
+                self.follow = None;
+            }
+        });
+    }
+
+    /// 跳转到行弹窗: Enter 跳转 (超出范围时停在最后一行)，Esc 关闭
+    fn goto_line_ui(&mut self, ctx: &egui::Context) {
+        let Some(goto) = &mut self.goto_line else { return };
+        let line_count = self.code.matches('\n').count() + 1;
+        let input_id = egui::Id::new("code_editor_goto").with(self.key);
+        let (mut submit, mut cancel) = (false, false);
+        if ctx.memory(|m| m.has_focus(input_id)) {
+            submit = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter));
+            cancel = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape));
+        }
+
+        let parsed = parse_goto(&goto.input);
+        egui::Window::new("Go to Line")
+            .id(egui::Id::new("code_editor_goto_window").with(self.key))
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .fixed_size([280.0, 0.0])
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let field = ui.add(
+                    egui::TextEdit::singleline(&mut goto.input)
+                        .id(input_id)
+                        .hint_text(format!("Line (1-{}), optionally :column", line_count))
+                        .desired_width(f32::INFINITY),
+                );
+                if std::mem::take(&mut goto.focus) {
+                    field.request_focus();
+                }
+                if parsed.is_none() && !goto.input.trim().is_empty() {
+                    ui.colored_label(ui.visuals().error_fg_color, "Enter a line number, e.g. 42 or 42:7");
+                }
+            });
+
+        if submit {
+            let Some((line, col)) = parsed else { return };
+            let line = line.saturating_sub(1).min(line_count - 1);
+            self.pending_goto = Some((line, col.unwrap_or(1).saturating_sub(1)));
+            self.goto_line = None;
+        } else if cancel {
+            self.goto_line = None;
+            if self.large_file {
+                self.focus_virtual_editor = true;
+            } else if let Some(id) = self.text_edit_id {
+                ctx.memory_mut(|m| m.request_focus(id));
+            }
+        }
+    }
+
+    /// 打开查找栏 (`replace` 时同时显示替换栏)，单行选区作为查询内容
+    fn open_find(&mut self, ctx: &egui::Context, replace: bool) {
+        let selected = self.selection(ctx)
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| self.code.chars().skip(start).take(end - start).collect::<String>())
+            .filter(|text| !text.contains('\n'));
+        let find = self.find.get_or_insert_with(find::FindState::default);
+        if let Some(text) = selected {
+            find.query = if find.regex { regex::escape(&text) } else { text };
+        }
+        find.show_replace = replace;
+        find.focus = true;
+    }
+
+    fn find_bar_ui(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        let cursor = self.selection(ui.ctx()).map_or(0, |(start, _)| byte_index(&self.code, start));
+        let read_only = self.read_only();
+        let Some(find) = &mut self.find else { return };
+        find.update(&self.code, self.revision, cursor);
+
+        let find_id = egui::Id::new("code_editor_find").with(self.key);
+        let replace_id = egui::Id::new("code_editor_replace").with(self.key);
+        let (mut close, mut replace, mut replace_all) = (false, false, false);
+
+        // 在输入框处理之前拦截，否则 Enter 会让输入框失去焦点
+        if ui.memory(|m| m.has_focus(find_id)) {
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::Enter)) {
+                find.step(false);
+            }
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)) {
+                find.step(true);
+            }
+        }
+        if ui.memory(|m| m.has_focus(replace_id)) && ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)) {
+            replace = true;
+        }
+        if find.has_focus && ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+            close = true;
+        }
+
+        ui.horizontal(|ui| {
+            let field = ui.add(
+                egui::TextEdit::singleline(&mut find.query)
+                    .id(find_id)
+                    .hint_text("Find")
+                    .desired_width(220.0),
+            );
+            if std::mem::take(&mut find.focus) {
+                field.request_focus();
+            }
+            ui.toggle_value(&mut find.case_sensitive, "Aa").on_hover_text("Match case");
+            ui.toggle_value(&mut find.whole_word, "ab").on_hover_text("Match whole word");
+            ui.toggle_value(&mut find.regex, ".*").on_hover_text("Use regular expression");
+            if let Some(error) = &find.error {
+                ui.colored_label(ui.visuals().error_fg_color, "Invalid regex").on_hover_text(error);
+            } else if !find.query.is_empty() {
+                let status = match find.matches.len() {
+                    0 => "No results".to_string(),
+                    n if n >= find::MAX_MATCHES => format!("{}/{}+", find.current + 1, n),
+                    n => format!("{}/{}", find.current + 1, n),
+                };
+                ui.weak(status);
+            }
+            if ui.small_button("⬆").on_hover_text("Previous match (Shift+Enter)").clicked() {
+                find.step(false);
+            }
+            if ui.small_button("⬇").on_hover_text("Next match (Enter)").clicked() {
+                find.step(true);
+            }
+            if !read_only {
+                ui.toggle_value(&mut find.show_replace, "Replace").on_hover_text("Show replace (Ctrl+H)");
+            }
+            if ui.small_button("✖").on_hover_text("Close (Esc)").clicked() {
+                close = true;
+            }
+        });
+        if find.show_replace && !read_only {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut find.replacement)
+                        .id(replace_id)
+                        .hint_text(if find.regex { "Replace ($1 for groups)" } else { "Replace" })
+                        .desired_width(220.0),
+                );
+                let any = !find.matches.is_empty();
+                replace |= ui.add_enabled(any, egui::Button::new("Replace")).on_hover_text("Replace this match (Enter)").clicked();
+                replace_all |= ui.add_enabled(any, egui::Button::new("Replace All")).clicked();
+            });
+        }
+        find.has_focus = ui.memory(|m| m.has_focus(find_id) || m.has_focus(replace_id));
+        ui.separator();
+
+        if replace && !read_only {
+            self.replace_match();
+        }
+        if replace_all && !read_only {
+            self.replace_all(control);
+        }
+        if close {
+            self.find = None;
+            if let Some(id) = self.text_edit_id {
+                ui.memory_mut(|m| m.request_focus(id));
+            }
+        }
+    }
+
+    /// 替换当前匹配并移动到下一个
+    fn replace_match(&mut self) {
+        let Some(find) = &mut self.find else { return };
+        let (Some(range), Ok(Some(pattern))) = (find.current_match(), find.pattern()) else { return };
+        let replacement = find.replacement_for(&pattern, &self.code, range.start);
+        self.code.replace_range(range, &replacement);
+        // 重新查找后同一序号即为下一个匹配
+        find.reveal = true;
+        self.is_dirty = true;
+        self.snippet = None;
+        self.bump_revision();
+    }
+
+    fn replace_all(&mut self, control: &mut Vec<AppCommand>) {
+        let Some(find) = &self.find else { return };
+        let Ok(Some(pattern)) = find.pattern() else { return };
+        let (code, count) = find.replace_all(&pattern, &self.code);
+        if count == 0 {
+            return;
+        }
+        self.code = code;
+        self.is_dirty = true;
+        self.snippet = None;
+        self.bump_revision();
+        control.push(AppCommand::notify(
+            format!("Replaced {} occurrence{} in {}", count, if count == 1 { "" } else { "s" }, self.name),
+            crate::NotificationLevel::Success,
+        ));
+    }
+
+    /// 请求插件在工作区中查找光标处标识符的定义
+    fn peek_definition(&self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        let Some(path) = self.path.clone() else { return };
+        let symbol = if self.large_file { None } else { self.identifier_at_cursor(ctx) };
+        match symbol {
+            Some(symbol) => self.shared.write().definition_request = Some((symbol, path, self.key)),
+            None => control.push(AppCommand::notify(
+                "Place the cursor on an identifier to peek its definition",
+                crate::NotificationLevel::Info,
+            )),
+        }
+    }
+
+    /// 领取"打开文件后跳转"的请求
+    fn take_open_goto(&mut self) {
+        let Some(path) = &self.path else { return };
+        let mut shared = self.shared.write();
+        if shared.open_goto.as_ref().is_some_and(|(p, _)| p == path) {
+            if let Some((_, line)) = shared.open_goto.take() {
+                self.pending_goto = Some((line, 0));
+            }
+        }
+    }
+
+    /// 记录当前磁盘上文件的修改时间
+    fn remember_disk_mtime(&mut self) {
+        self.disk_mtime = self.path.as_ref().and_then(|p| std::fs::metadata(p).ok()).and_then(|m| m.modified().ok());
+    }
+
+    /// 获得焦点时按修改时间检查一次，作为文件监听不可用 (例如网络磁盘) 时的后备
+    fn check_disk_changes(&mut self, control: &mut Vec<AppCommand>) {
+        if !std::mem::take(&mut self.check_disk) || self.read_only() {
+            return;
+        }
+        let Some(path) = self.path.clone() else { return };
+        let Some(mtime) = std::fs::metadata(&path).ok().and_then(|m| m.modified().ok()) else { return };
+        if self.disk_mtime == Some(mtime) {
+            return;
+        }
+        self.disk_mtime = Some(mtime);
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            self.external_change(content, control);
+        }
+    }
+
+    /// 在后台监听文件，处理外部工具 (cargo fmt、git 等) 对文件的修改
+    fn poll_disk_changes(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        if self.read_only() {
+            self.disk_watch = None;
+            return;
+        }
+        let Some(path) = &self.path else { return };
+        // 另存为之后路径会变化
+        if self.disk_watch.as_ref().is_none_or(|watch| watch.path() != path) {
+            self.disk_watch = sync::SyncWatcher::start(path, ctx.clone()).ok();
+        }
+        let Some(update) = self.disk_watch.as_mut().and_then(|watch| watch.poll(ctx)) else { return };
+        match update {
+            sync::SyncUpdate::Changed(content) => self.external_change(content, control),
+            sync::SyncUpdate::Removed => control.push(AppCommand::notify(
+                format!("{} was deleted on disk", self.name),
+                crate::NotificationLevel::Warning,
+            )),
+        }
+    }
+
+    /// 磁盘上的内容与编辑器不同时：没有未保存的修改则直接重新载入，否则显示冲突提示。
+    /// 自己保存引起的事件读到的内容与编辑器相同，不会被当作外部修改
+    fn external_change(&mut self, content: String, control: &mut Vec<AppCommand>) {
+        let (content, line_ending) = normalize_line_endings(content);
+        if content == self.code {
+            self.disk_conflict = None;
+            return;
+        }
+        self.remember_disk_mtime();
+        if self.is_dirty {
+            self.disk_conflict = Some(content);
+            return;
+        }
+        self.code = content;
+        self.line_ending = line_ending;
+        self.bump_revision();
+        control.push(AppCommand::notify(
+            format!("Reloaded {} (changed on disk)", self.name),
+            crate::NotificationLevel::Info,
+        ));
+    }
+
+    /// "File changed on disk" 提示：重新载入、保留自己的修改或查看两者的差异
+    fn disk_conflict_ui(&mut self, ui: &mut Ui) {
+        let Some(disk) = &self.disk_conflict else { return };
+        let mut reload = None;
+        ui.horizontal(|ui| {
+            ui.colored_label(ui.visuals().warn_fg_color, "⚠ File changed on disk");
+            if ui.small_button("Reload").on_hover_text("Discard your changes and load the file from disk").clicked() {
+                reload = Some(true);
+            }
+            if ui.small_button("Keep Mine").on_hover_text("Keep your version; saving will overwrite the file on disk").clicked() {
+                reload = Some(false);
+            }
+            ui.toggle_value(&mut self.show_disk_diff, "View Diff");
+        });
+        if self.show_disk_diff {
+            ui.weak("- on disk   + yours");
+            egui::ScrollArea::vertical()
+                .id_salt("code_editor_disk_diff")
+                .max_height(240.0)
+                .show(ui, |ui| batch::diff_ui(ui, disk, &self.code));
+        }
+        ui.separator();
+
+        let Some(reload) = reload else { return };
+        let disk = self.disk_conflict.take().unwrap_or_default();
+        self.show_disk_diff = false;
+        if reload {
+            self.code = disk;
+            self.is_dirty = false;
+            self.snippet = None;
+            self.bump_revision();
+        }
+    }
+
+    /// 将本编辑器的大纲发布到共享状态，并领取面板发来的跳转请求
+    fn sync_shared(&mut self, ctx: &egui::Context, has_focus: bool) {
+        {
+            let mut shared = self.shared.write();
+            if has_focus {
+                shared.focused = Some(self.key);
+            }
+            if shared.focused != Some(self.key) {
+                return;
+            }
+            shared.focused_pass = ctx.cumulative_pass_nr();
+            if shared.focused_name != self.name {
+                shared.focused_name = self.name.clone();
+            }
+            if shared.focused_path != self.path {
+                shared.focused_path = self.path.clone();
+            }
+            if let Some((key, line)) = shared.goto_request {
+                if key == self.key {
+                    shared.goto_request = None;
+                    self.pending_goto = Some((line, 0));
+                }
+            }
+            if shared.outline_source == Some((self.key, self.revision)) {
+                return;
+            }
+        }
+        let symbols = self.symbols().to_vec();
+        let mut shared = self.shared.write();
+        shared.symbols = symbols;
+        shared.outline_source = Some((self.key, self.revision));
+    }
+
+    /// 将光标移动到指定位置并滚动到可见区域
+    fn apply_goto(&mut self, ui: &Ui, output: &egui::text_edit::TextEditOutput, line: usize, col: usize) {
+        let index = char_index_of(&self.code, line, col);
+        let ccursor = egui::text::CCursor::new(index);
+        let mut state = output.state.clone();
+        state.cursor.set_char_range(Some(egui::text::CCursorRange::one(ccursor)));
+        state.store(ui.ctx(), output.response.id);
+        ui.memory_mut(|m| m.request_focus(output.response.id));
+
+        let cursor_rect = output.galley.pos_from_cursor(ccursor).translate(output.galley_pos.to_vec2());
+        ui.scroll_to_rect(cursor_rect, Some(egui::Align::Center));
+    }
+
+    /// 选中查找到的匹配 (字节范围) 并滚动到可见区域，不抢走查找栏的焦点
+    fn reveal_match(&mut self, ui: &Ui, output: &egui::text_edit::TextEditOutput, range: std::ops::Range<usize>) {
+        let Some(prefix) = self.code.get(..range.start) else { return };
+        let start = prefix.chars().count();
+        let end = start + self.code.get(range).map_or(0, |m| m.chars().count());
+        let mut state = output.state.clone();
+        state.cursor.set_char_range(Some(egui::text::CCursorRange::two(
+            egui::text::CCursor::new(start),
+            egui::text::CCursor::new(end),
+        )));
+        state.store(ui.ctx(), output.response.id);
+
+        let rect = output.galley.pos_from_cursor(egui::text::CCursor::new(start)).translate(output.galley_pos.to_vec2());
+        ui.scroll_to_rect(rect, Some(egui::Align::Center));
+    }
+
+    /// 光标移动后滚动到使其上下各保留 `scrolloff` 行可见的位置。
+    /// `position` 用于判断光标是否移动过，只在编辑器获得焦点时生效，不会干扰用户自行滚动
+    fn apply_scrolloff(&mut self, ui: &Ui, output: &egui::text_edit::TextEditOutput, position: (usize, usize)) {
+        if self.scrolloff_cursor == Some(position) {
+            return;
+        }
+        self.scrolloff_cursor = Some(position);
+        let lines = self.settings.read().scrolloff;
+        let Some(range) = output.cursor_range else { return };
+        if lines == 0 || !output.response.has_focus() {
+            return;
+        }
+        let cursor_rect = output.galley.pos_from_cursor(range.primary).translate(output.galley_pos.to_vec2());
+        let margin = lines as f32 * cursor_rect.height();
+        ui.scroll_to_rect(cursor_rect.expand2(egui::vec2(0.0, margin)), None);
+    }
+
+    /// 在行号栏中绘制行号。每个逻辑行的编号与其在 galley 中的首个可视行对齐，
+    /// 因此自动换行时续行不会错位
+    fn paint_gutter(
+        &self,
+        ui: &Ui,
+        gutter: egui::Rect,
+        output: &egui::text_edit::TextEditOutput,
+        font_id: &egui::FontId,
+        relative: bool,
+        first_line: usize,
+    ) {
+        let current = self.cursor_line();
+        let clip = ui.clip_rect();
+        let painter = ui.painter();
+        let weak = ui.visuals().weak_text_color();
+        let strong = ui.visuals().strong_text_color();
+
+        let mut line = first_line;
+        let mut line_start = true;
+        for row in &output.galley.rows {
+            let rect = row.rect().translate(output.galley_pos.to_vec2());
+            if line_start && rect.bottom() >= clip.top() && rect.top() <= clip.bottom() {
+                let number = if relative && line != current { line.abs_diff(current) } else { line + 1 };
+                painter.text(
+                    egui::pos2(gutter.right() - 4.0, rect.top()),
+                    egui::Align2::RIGHT_TOP,
+                    number.to_string(),
+                    font_id.clone(),
+                    if line == current { strong } else { weak },
+                );
+            }
+            line_start = row.ends_with_newline;
+            if line_start {
+                line += 1;
+            }
+        }
+    }
+
+    /// JSON/TOML 结构视图。解析失败时只显示错误位置；修改值时替换源码中对应的片段，其余文本保持不变
+    fn structure_ui(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        if self.structure.as_ref().is_none_or(|(revision, _)| *revision != self.revision) {
+            self.structure = Some((self.revision, structured::parse(&self.language, &self.code)));
+            self.structure_edit = None;
+        }
+        let Some((revision, parsed)) = self.structure.take() else { return };
+
+        ui.horizontal(|ui| {
+            ui.strong("Structure");
+            if ui.small_button("✖").on_hover_text("Hide the structure view").clicked() {
+                self.structure_view = false;
+            }
+        });
+        ui.separator();
+
+        let editable = !self.read_only();
+        let mut view = structured::StructureView {
+            code: &self.code,
+            language: &self.language,
+            editable,
+            edit: &mut self.structure_edit,
+            change: None,
+            goto: None,
+        };
+        match &parsed {
+            Ok(root) => {
+                egui::ScrollArea::vertical()
+                    .id_salt("code_editor_structure_scroll")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| view.root_ui(ui, root));
+            }
+            Err(error) => {
+                let (line, col) = structured::line_col(&self.code, error.offset);
+                ui.colored_label(ui.visuals().error_fg_color, format!("⚠ Line {}, column {}: {}", line + 1, col + 1, error.message));
+                ui.label(egui::RichText::new("Fix the error in the text to use the structure view.").weak());
+                if ui.button("Go to Error").clicked() {
+                    view.goto = Some(error.offset);
+                }
+            }
+        }
+        let (change, goto) = (view.change, view.goto);
+        self.structure = Some((revision, parsed));
+
+        if let Some(offset) = goto {
+            self.pending_goto = Some(structured::line_col(&self.code, offset));
+        }
+        if let Some((span, literal)) = change {
+            let mut code = self.code.clone();
+            code.replace_range(span, &literal);
+            // 只接受修改后仍能解析的结果，避免在结构视图中把文件改坏
+            match structured::parse(&self.language, &code) {
+                Ok(_) => {
+                    self.code = code;
+                    self.is_dirty = true;
+                    self.bump_revision();
+                }
+                Err(e) => control.push(AppCommand::notify(
+                    format!("Edit not applied: {}", e.message),
+                    crate::NotificationLevel::Warning,
+                )),
+            }
+        }
+    }
+
+    fn breadcrumb_ui(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        let Some(path) = self.path.clone() else { return };
+        let root = self.cached_workspace_root(&path);
+        let relative = path.strip_prefix(&root).unwrap_or(&path).to_path_buf();
+        let cursor_line = self.cursor_line();
+        let symbol = outline::enclosing_symbol(self.symbols(), cursor_line).cloned();
+
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 4.0;
+            let root_name = root.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if ui.link(egui::RichText::new(root_name).small()).clicked() {
+                control.push(AppCommand::RevealInShell(root.clone()));
+            }
+
+            let components: Vec<_> = relative.components().collect();
+            let mut current = root.clone();
+            for (i, component) in components.iter().enumerate() {
+                ui.label(egui::RichText::new("›").small().weak());
+                current.push(component);
+                let text = egui::RichText::new(component.as_os_str().to_string_lossy()).small();
+                if i + 1 == components.len() {
+                    ui.label(text);
+                } else if ui.link(text).clicked() {
+                    control.push(AppCommand::RevealInShell(current.clone()));
+                }
+            }
+
+            if let Some(symbol) = symbol {
+                ui.label(egui::RichText::new("›").small().weak());
+                ui.label(egui::RichText::new(format!("{} {}", symbol.kind.icon(), symbol.name)).small().strong());
+            }
+        });
+    }
+
+    /// 按当前语言的模板运行文件，输出显示在新的终端标签页中
+    fn run(&mut self, control: &mut Vec<AppCommand>) {
+        if !matches!(self.state, EditorState::Ready) {
+            return;
+        }
+        // 运行前先保存
+        if !self.read_only() && (self.is_dirty || self.path.is_none()) {
+            self.save(control);
+        }
+        let Some(path) = self.path.clone() else { return };
+        if self.is_dirty {
+            // 保存失败，错误已经通知过了
+            return;
+        }
+
+        let template = self.settings.read().run_commands.get(&self.language)
+            .filter(|t| !t.trim().is_empty())
+            .cloned();
+        let Some(template) = template else {
+            control.push(AppCommand::notify(
+                format!("No run command configured for language '{}'", self.language),
+                crate::NotificationLevel::Warning,
+            ));
+            return;
+        };
+
+        let root = workspace_root(&path);
+        control.push(AppCommand::Run {
+            command: expand_run_template(&template, &path, &root),
+            cwd: Some(root),
+        });
+    }
+
+    /// 按语言设置整理内容 (删除行末空白、保证以一个换行符结尾)。只读模式下不修改内容
+    fn clean_up_before_save(&mut self) {
+        if self.read_only() {
+            return;
+        }
+        let cleanup = self.settings.read().on_save_for(&self.language);
+        if let Some(code) = cleanup.apply(&self.code) {
+            self.cleaned_from = Some(std::mem::replace(&mut self.code, code));
+            self.bump_revision();
+        }
+    }
+
+    /// 把保存时整理前的光标与选区映射到整理后的内容：行号不变，列截断到行尾
+    fn remap_cleaned_selection(&mut self, ctx: &egui::Context) {
+        let Some(old) = self.cleaned_from.take() else { return };
+        if self.large_file {
+            return;
         }
+        let Some((anchor, cursor)) = self.cursor_range(ctx) else { return };
+        let remap = |index: usize| {
+            let (line, col) = window_line_col(&old, 0, index);
+            char_index_of(&self.code, line, col)
+        };
+        self.set_selection(ctx, remap(anchor), remap(cursor));
     }
 
     fn save(&mut self, control: &mut Vec<AppCommand>) {
         if let EditorState::Ready = self.state {
+            if self.path.is_some() {
+                self.clean_up_before_save();
+            }
             if let Some(path) = &self.path {
-                match std::fs::write(path, &self.code) {
+                match std::fs::write(path, self.line_ending.apply(&self.code).as_bytes()) {
                     Ok(_) => {
                         self.is_dirty = false;
-                        control.push(AppCommand::Notify {
-                            message: format!("Saved {}", self.name),
-                            level: crate::NotificationLevel::Success,
-                        });
+                        self.remember_disk_mtime();
+                        control.push(AppCommand::notify(
+                            format!("Saved {}", self.name),
+                            crate::NotificationLevel::Success,
+                        ));
                     }
                     Err(e) => {
                         control.push(AppCommand::Notify {
                             message: format!("Save failed: {}", e),
                             level: crate::NotificationLevel::Error,
+                            actions: vec![crate::NotificationAction::new("Reveal", AppCommand::RevealInShell(path.clone()))],
                         });
                     }
                 }
@@ -66,38 +1881,27 @@ impl CodeEditorTab {
                 .set_file_name(&self.name)
                 .save_file() 
             {
-                match std::fs::write(&path, &self.code) {
+                self.clean_up_before_save();
+                match std::fs::write(&path, self.line_ending.apply(&self.code).as_bytes()) {
                     Ok(_) => {
                         self.path = Some(path.clone());
                         self.name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
                         self.is_dirty = false;
-                        
+                        self.remember_disk_mtime();
+
                         // 根据新扩展名更新语言
-                        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-                        self.language = match ext {
-                            "rs" => "rs",
-                            "py" => "py",
-                            "js" | "ts" => "js",
-                            "html" => "html",
-                            "css" => "css",
-                            "json" => "json",
-                            "md" => "md",
-                            "toml" => "toml",
-                            "c" | "h" => "c",
-                            "cpp" | "hpp" | "cc" | "cxx" => "cpp",
-                            _ => "txt",
-                        }.to_string();
+                        self.language = language_for_path(&path).to_string();
 
-                        control.push(AppCommand::Notify {
-                            message: format!("Saved as {}", self.name),
-                            level: crate::NotificationLevel::Success,
-                        });
+                        control.push(AppCommand::notify(
+                            format!("Saved as {}", self.name),
+                            crate::NotificationLevel::Success,
+                        ));
                     }
                     Err(e) => {
-                        control.push(AppCommand::Notify {
-                            message: format!("Save As failed: {}", e),
-                            level: crate::NotificationLevel::Error,
-                        });
+                        control.push(AppCommand::notify(
+                            format!("Save As failed: {}", e),
+                            crate::NotificationLevel::Error,
+                        ));
                     }
                 }
             }
@@ -132,8 +1936,10 @@ impl TabInstance for CodeEditorTab {
         if let Some(res) = loaded_content {
             match res {
                 Ok(content) => {
-                    self.code = content;
+                    (self.code, self.line_ending) = normalize_line_endings(content);
+                    self.bump_revision();
                     self.state = EditorState::Ready;
+                    self.remember_disk_mtime();
                 }
                 Err(e) => {
                     self.state = EditorState::Error(e);
@@ -159,7 +1965,25 @@ impl TabInstance for CodeEditorTab {
         }
 
         // 只有 Ready 状态才执行后续逻辑
+        self.take_reload(control);
+        self.publish_unsaved();
+        self.check_disk_changes(control);
+        self.poll_disk_changes(ui.ctx(), control);
+        self.take_open_goto();
+        self.poll_follow();
+        self.poll_sync(ui.ctx(), control);
+        self.check_large_file();
+
         let language = self.language.clone();
+        let tab_width = self.settings.read().indent_for(&self.language).tab_width;
+        let find_matches = self.find.as_ref().map(|find| (find.matches.clone(), find.current));
+        // 光标旁的括号与其配对括号 (字节范围)；有选区时及大文件模式下不显示
+        let bracket_pair = (self.settings.read().match_brackets && !self.large_file)
+            .then(|| self.cursor_range(ui.ctx()))
+            .flatten()
+            .filter(|(anchor, cursor)| anchor == cursor)
+            .and_then(|(_, cursor)| brackets::matching_pair(&self.code, byte_index(&self.code, cursor)))
+            .map(|(open, close)| [open..open + 1, close..close + 1]);
         let mut layouter = move |ui: &egui::Ui, string: &dyn egui::TextBuffer, wrap_width: f32| {
             let string = string.as_str();
             let theme = egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx(), ui.style());
@@ -170,80 +1994,173 @@ impl TabInstance for CodeEditorTab {
                 string,
                 &language,
             );
+            if let Some((matches, current)) = &find_matches {
+                find::highlight(
+                    &mut layout_job,
+                    matches,
+                    Some(*current),
+                    egui::Color32::from_rgba_unmultiplied(255, 200, 0, 50),
+                    egui::Color32::from_rgba_unmultiplied(255, 140, 0, 140),
+                );
+            }
+            if let Some(pair) = &bracket_pair {
+                let color = egui::Color32::from_rgba_unmultiplied(120, 160, 255, 80);
+                find::highlight(&mut layout_job, pair, None, color, color);
+            }
+            let space_width = ui.fonts_mut(|f| f.glyph_width(&egui::TextStyle::Monospace.resolve(ui.style()), ' '));
+            indent::set_tab_width(&mut layout_job, tab_width, space_width);
             layout_job.wrap.max_width = wrap_width;
             ui.fonts_mut(|f| f.layout_job(layout_job))
         };
 
-        // 处理同步模式逻辑
-        if self.sync_mode {
-            let current_time = ui.input(|i| i.time);
-            if current_time - self.last_sync_time > 1.0 {
-                if let Some(path) = &self.path {
-                    if let Ok(content) = std::fs::read_to_string(path) {
-                        if content != self.code {
-                            self.code = content;
-                            self.is_dirty = false;
-                        }
-                    }
+        ui.vertical(|ui| {
+            // 快捷键监听: 保存 (默认 Ctrl + S，同步模式下禁用)
+            if !self.read_only() && crate::keymap::consume(ui.ctx(), "code_editor.save") {
+                self.save(control);
+            }
+            // 撤销 / 重做 (默认 Ctrl + Z / Ctrl + Shift + Z，另接受 Ctrl + Y)
+            // 先记录上一帧的修改；在 TextEdit 之前消耗按键，以免触发其内置的撤销
+            self.remap_cleaned_selection(ui.ctx());
+            let now = ui.input(|i| i.time);
+            let selection = if self.large_file { None } else { self.cursor_range(ui.ctx()) };
+            self.history.record(&self.code, self.revision, selection, !self.is_dirty, now);
+            let editor_id = if self.large_file { Some(self.virtual_editor_id()) } else { self.text_edit_id };
+            if !self.read_only() && editor_id.is_some_and(|id| ui.memory(|m| m.has_focus(id))) {
+                let ctx = ui.ctx().clone();
+                // Ctrl + Shift + Z 也匹配 Ctrl + Z，因此先检查重做
+                if crate::keymap::consume(&ctx, "code_editor.redo")
+                    || ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Y))
+                {
+                    self.undo(&ctx, true);
+                } else if crate::keymap::consume(&ctx, "code_editor.undo") {
+                    self.undo(&ctx, false);
                 }
-                self.last_sync_time = current_time;
             }
-            // 确保 UI 持续刷新以检查同步
-            ui.ctx().request_repaint_after(std::time::Duration::from_millis(500));
-        }
+            // 运行当前文件 (默认 F5)
+            if crate::keymap::consume(ui.ctx(), "code_editor.run") {
+                self.run(control);
+            }
+            // 查找定义 (默认 F12)
+            if crate::keymap::consume(ui.ctx(), "code_editor.peek_definition") {
+                self.peek_definition(ui.ctx(), control);
+            }
 
-        ui.vertical(|ui| {
-            // 快捷键监听: Ctrl + S 保存 (同步模式下禁用)
-            if !self.sync_mode && ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::S)) {
-                self.save(control);
+            self.follow_bar_ui(ui);
+            self.disk_conflict_ui(ui);
+            if self.settings.read().show_breadcrumbs {
+                self.breadcrumb_ui(ui, control);
+                ui.separator();
+            }
+
+            let (word_wrap, relative_numbers) = {
+                let settings = self.settings.read();
+                (settings.word_wrap, settings.relative_line_numbers)
+            };
+
+            // 跳转到行 (默认 Ctrl + G)，大文件模式下同样可用
+            let editor_focused = ui.memory(|m| {
+                self.text_edit_id.is_some_and(|id| m.has_focus(id)) || m.has_focus(self.virtual_editor_id())
+            });
+            if (editor_focused || self.goto_line.is_some()) && crate::keymap::consume(ui.ctx(), "code_editor.goto_line") {
+                self.goto_line = Some(GotoLine { input: String::new(), focus: true });
+            }
+            self.goto_line_ui(&ui.ctx().clone());
+
+            if self.large_file {
+                self.virtual_editor_ui(ui, relative_numbers);
+                let focused = ui.memory(|m| m.has_focus(self.virtual_editor_id()));
+                self.sync_shared(ui.ctx(), focused);
+                return;
+            }
+
+            // 查找 / 替换 (默认 Ctrl + F / Ctrl + H)
+            let find_focused = self.find.as_ref().is_some_and(|find| find.has_focus);
+            if find_focused || self.text_edit_id.is_some_and(|id| ui.memory(|m| m.has_focus(id))) {
+                let ctx = ui.ctx().clone();
+                if crate::keymap::consume(&ctx, "code_editor.find") {
+                    self.open_find(&ctx, false);
+                } else if !self.read_only() && crate::keymap::consume(&ctx, "code_editor.replace") {
+                    self.open_find(&ctx, true);
+                } else if !self.read_only() && !find_focused && crate::keymap::consume(&ctx, "code_editor.toggle_comment") {
+                    self.toggle_comment(&ctx);
+                }
+            }
+            self.find_bar_ui(ui, control);
+
+            if self.structure_view && structured::supports(&self.language) {
+                egui::SidePanel::right(ui.id().with("code_editor_structure"))
+                    .resizable(true)
+                    .default_width(280.0)
+                    .show_inside(ui, |ui| self.structure_ui(ui, control));
             }
 
-            egui::ScrollArea::both()
+            self.handle_editing_keys(ui);
+
+            let scroll_area = if word_wrap { egui::ScrollArea::vertical() } else { egui::ScrollArea::both() };
+
+            let scroll_output = scroll_area
                 .id_salt("code_editor_scroll")
+                .stick_to_bottom(self.follow.is_some())
                 .show(ui, |ui| {
                     ui.horizontal_top(|ui| {
-                        // 1. 优化的行号显示
-                        let text_style = egui::TextStyle::Monospace;
-                        let line_count = self.code.lines().count().max(1);
-                        
-                        let mut line_numbers_str = String::new();
-                        for i in 1..=line_count {
-                            line_numbers_str.push_str(&format!("{}\n", i));
-                        }
-
-                        ui.add(
-                            egui::Label::new(
-                                egui::RichText::new(line_numbers_str)
-                                    .font(egui::FontId::monospace(12.0))
-                                    .color(ui.visuals().weak_text_color())
-                            )
+                        // 1. 行号栏：先占位，待编辑器布局完成后按 galley 的行位置绘制
+                        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+                        let line_count = self.code.matches('\n').count() + 1;
+                        let digits = line_count.to_string().len().max(2);
+                        let digit_width = ui.fonts_mut(|f| f.glyph_width(&font_id, '0'));
+                        let (gutter_rect, _) = ui.allocate_exact_size(
+                            egui::vec2(digits as f32 * digit_width + 8.0, 0.0),
+                            egui::Sense::hover(),
                         );
 
                         ui.separator();
 
                         // 2. 编辑器主体
-                        ui.add_enabled_ui(!self.sync_mode, |ui| {
+                        let output = ui.add_enabled_ui(!self.read_only(), |ui| {
                             let editor = egui::TextEdit::multiline(&mut self.code)
-                                .font(text_style)
+                                .font(egui::TextStyle::Monospace)
                                 .code_editor()
                                 .lock_focus(true)
-                                .desired_width(f32::INFINITY)
+                                .desired_width(if word_wrap { ui.available_width() } else { f32::INFINITY })
                                 .layouter(&mut layouter);
 
-                            let response = ui.add_sized(ui.available_size(), editor);
-                            if response.changed() {
+                            let output = ui.allocate_ui_with_layout(
+                                ui.available_size(),
+                                egui::Layout::centered_and_justified(ui.layout().main_dir()),
+                                |ui| editor.show(ui),
+                            ).inner;
+                            self.text_edit_id = Some(output.response.id);
+                            if output.response.changed() {
                                 self.is_dirty = true;
+                                self.bump_revision();
                             }
-                        });
+                            let reveal = self.find.as_mut()
+                                .filter(|find| std::mem::take(&mut find.reveal))
+                                .and_then(|find| find.current_match());
+                            if let Some((line, col)) = self.pending_goto.take() {
+                                self.apply_goto(ui, &output, line, col);
+                            } else if let Some(range) = reveal {
+                                self.reveal_match(ui, &output, range);
+                            } else if let Some(range) = output.cursor_range {
+                                // 跳转的帧不调整，否则会覆盖跳转的滚动目标
+                                self.apply_scrolloff(ui, &output, (0, range.primary.index));
+                            }
+                            self.update_cursor(ui.ctx(), output.response.id);
+                            self.sync_shared(ui.ctx(), output.response.has_focus());
+                            output
+                        }).inner;
+
+                        self.paint_gutter(ui, gutter_rect, &output, &font_id, relative_numbers, 0);
                     });
                 });
+            self.update_follow_paused(&scroll_output);
         });
     }
 
     fn on_context_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
         // 加载或错误时不显示完整菜单
         if let EditorState::Ready = self.state {
-            if ui.add_enabled(!self.sync_mode, egui::Button::new("💾 Save")).clicked() {
+            if ui.add_enabled(!self.read_only(), egui::Button::new("💾 Save")).clicked() {
                 self.save(control);
                 ui.close();
             }
@@ -251,12 +2168,89 @@ impl TabInstance for CodeEditorTab {
                 self.save_as(control);
                 ui.close();
             }
+            if ui.button("▶ Run (F5)").clicked() {
+                self.run(control);
+                ui.close();
+            }
+            ui.menu_button(format!("Language ({})", language_name(&self.language)), |ui| {
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for &language in LANGUAGES {
+                        if ui.radio(self.language == language, language_name(language)).clicked() {
+                            if self.language != language {
+                                self.language = language.to_string();
+                                // 大纲等按语言计算的缓存随内容版本失效
+                                self.bump_revision();
+                            }
+                            ui.close();
+                        }
+                    }
+                });
+            });
+            ui.add_enabled_ui(!self.read_only(), |ui| {
+                ui.menu_button(format!("Line Endings ({})", self.line_ending.label()), |ui| {
+                    for ending in [LineEnding::Lf, LineEnding::Crlf] {
+                        if ui.radio(self.line_ending == ending, ending.label()).clicked() {
+                            if self.line_ending != ending {
+                                self.line_ending = ending;
+                                self.is_dirty = true;
+                            }
+                            ui.close();
+                        }
+                    }
+                });
+            });
+            let symbol = if self.large_file { None } else { self.identifier_at_cursor(ui.ctx()) };
+            let rename = egui::Button::new("Rename Symbol (textual)…");
+            if ui.add_enabled(symbol.is_some() && self.path.is_some(), rename)
+                .on_hover_text("Whole-word find/replace, not a semantic rename")
+                .clicked()
+            {
+                if let (Some(symbol), Some(path)) = (symbol.clone(), self.path.clone()) {
+                    self.shared.write().rename_request = Some((symbol, path));
+                }
+                ui.close();
+            }
+            if ui.add_enabled(symbol.is_some() && self.path.is_some(), egui::Button::new("Peek Definition (heuristic)"))
+                .on_hover_text("Searches the workspace for lines that look like a definition of this name")
+                .clicked()
+            {
+                self.peek_definition(ui.ctx(), control);
+                ui.close();
+            }
             ui.separator();
             
-            let sync_text = if self.sync_mode { "🔄 Sync Mode: ON" } else { "🔄 Sync Mode: OFF" };
-            if ui.checkbox(&mut self.sync_mode, sync_text).clicked() {
-                if self.sync_mode {
-                    self.last_sync_time = ui.input(|i| i.time);
+            let mut syncing = self.sync.is_some();
+            let sync_text = if syncing { "🔄 Sync Mode: ON" } else { "🔄 Sync Mode: OFF" };
+            let response = ui.add_enabled(self.path.is_some(), egui::Checkbox::new(&mut syncing, sync_text))
+                .on_hover_text("Reload the file whenever it changes on disk");
+            if response.clicked() {
+                if syncing {
+                    self.start_sync(ui.ctx(), control);
+                } else {
+                    self.sync = None;
+                }
+                ui.close();
+            }
+
+            if structured::supports(&self.language) && !self.large_file {
+                if ui.checkbox(&mut self.structure_view, "🌳 Structure View")
+                    .on_hover_text("Show a collapsible tree of the parsed file next to the text")
+                    .clicked()
+                {
+                    ui.close();
+                }
+            }
+
+            let mut following = self.follow.is_some();
+            let can_follow = self.path.is_some() && (following || !self.is_dirty);
+            let response = ui.add_enabled(can_follow, egui::Checkbox::new(&mut following, "📡 Follow File (tail -f)"))
+                .on_hover_text("Append new content as the file grows and keep the view scrolled to the bottom")
+                .on_disabled_hover_text("Save or discard your changes first");
+            if response.clicked() {
+                if following {
+                    self.start_follow(ui.ctx(), control);
+                } else {
+                    self.follow = None;
                 }
                 ui.close();
             }
@@ -265,61 +2259,599 @@ impl TabInstance for CodeEditorTab {
         }
     }
 
+    fn on_focus(&mut self) {
+        self.check_disk = true;
+    }
+
+    fn status(&self) -> Option<TabStatus> {
+        if !matches!(self.state, EditorState::Ready) {
+            return None;
+        }
+        let mut left = vec![format!("Ln {}, Col {}", self.cursor.2 + 1, self.cursor.3 + 1)];
+        if self.large_file {
+            // 大文件模式下不逐字统计选区，只报告跨越的行数
+            if let Some(((anchor, _), (cursor, _))) = self.virtual_cursor.filter(|(anchor, cursor)| anchor != cursor) {
+                left.push(format!("{} lines selected", anchor.abs_diff(cursor) + 1));
+            }
+        } else if self.has_selection {
+            left.push(format!("{} selected", self.selection_len));
+        }
+        Some(TabStatus {
+            left,
+            right: vec![language_name(&self.language).to_string(), self.line_ending.label().to_string(), "UTF-8".to_string()],
+        })
+    }
+
+    fn capabilities(&self) -> TabCapabilities {
+        if !matches!(self.state, EditorState::Ready) {
+            return TabCapabilities::NONE;
+        }
+        let mut capabilities = TabCapabilities::NONE;
+        if !self.read_only() {
+            capabilities |= TabCapabilities::SAVEABLE;
+        }
+        if self.settings.read().run_commands.get(&self.language).is_some_and(|t| !t.trim().is_empty()) {
+            capabilities |= TabCapabilities::RUNNABLE;
+        }
+        let selection = if self.large_file {
+            self.virtual_cursor.is_some_and(|(anchor, cursor)| anchor != cursor)
+        } else {
+            self.has_selection
+        };
+        if selection {
+            capabilities |= TabCapabilities::HAS_SELECTION;
+        }
+        capabilities
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    fn save(&mut self, control: &mut Vec<AppCommand>) {
+        CodeEditorTab::save(self, control);
+        self.publish_unsaved();
+    }
+
+    fn on_close(&mut self) {
+        self.shared.write().unsaved.remove(&self.key);
+    }
+
+    fn on_action(&mut self, action: &str, control: &mut Vec<AppCommand>) {
+        match action {
+            "code_editor.save" => self.save(control),
+            "code_editor.run" => self.run(control),
+            _ => {}
+        }
+    }
+
+    /// 只记录文件路径，恢复时从磁盘重新读取 (未保存的修改不会被保留)
+    fn descriptor(&self) -> Option<TabDescriptor> {
+        let path = self.path.as_ref()?;
+        let mut state = toml::Table::new();
+        state.insert("path".to_string(), path.to_string_lossy().into_owned().into());
+        // 只记录用户手动选择的语言，按扩展名推断的语言在恢复时重新推断
+        if self.language != language_for_path(path) {
+            state.insert("language".to_string(), self.language.clone().into());
+        }
+        Some(TabDescriptor::new(crate::plugins::PLUGIN_NAME_CODE_EDITOR, state))
+    }
+
+    fn matches_path(&self, path: &Path) -> bool {
+        self.path.as_deref().is_some_and(|own| crate::same_path(own, path))
+    }
+
     fn box_clone(&self) -> Box<dyn TabInstance> {
         Box::new(self.clone())
     }
 }
 
-pub struct CodeEditorPlugin;
+#[derive(Default)]
+pub struct CodeEditorPlugin {
+    settings: Arc<RwLock<EditorSettings>>,
+    shared: Arc<RwLock<EditorShared>>,
+    new_run_language: String,
+    show_outline: bool,
+    config: PluginConfig,
+    /// 正在后台计算的批量编辑
+    batch_job: Option<Arc<RwLock<Option<batch::BatchEdit>>>>,
+    /// 等待确认的批量编辑
+    batch_preview: Option<batch::BatchEdit>,
+    /// 最近一次应用的批量编辑，可撤销
+    last_batch: Option<batch::AppliedBatch>,
+    /// 正在输入新名称的文本重命名
+    rename: Option<RenameDialog>,
+    /// 正在后台进行的定义查找
+    definition_job: Option<DefinitionJob>,
+    /// 找到多个候选定义时的选择窗口
+    definition_picker: Option<DefinitionJob>,
+}
+
+/// 一次查找定义: 请求来源与 (完成后的) 结果
+struct DefinitionJob {
+    symbol: String,
+    file: PathBuf,
+    editor: u64,
+    result: Arc<RwLock<Option<Vec<definition::Definition>>>>,
+}
+
+/// 批量编辑的范围
+enum BatchScope {
+    /// 包含该文件的工作区
+    Workspace(PathBuf),
+    /// 仅该文件
+    File(PathBuf),
+}
+
+struct RenameDialog {
+    symbol: String,
+    file: PathBuf,
+    new_name: String,
+    /// 替换整个工作区，否则只替换当前文件
+    whole_workspace: bool,
+}
+
+impl CodeEditorPlugin {
+    /// 创建编辑器标签页并在后台读取文件
+    fn open_file(&self, path: &Path) -> Option<CodeEditorTab> {
+        if path.is_dir() {
+            return None;
+        }
+        let path_owned = path.to_path_buf();
+        let result_store = Arc::new(RwLock::new(None));
+        let result_store_clone = result_store.clone();
+
+        std::thread::spawn(move || {
+            let res = std::fs::read_to_string(&path_owned).map_err(|e| e.to_string());
+            *result_store_clone.write() = Some(res);
+        });
+
+        let mut tab = CodeEditorTab::new(
+            path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            Some(path.to_path_buf()),
+            String::new(),
+            language_for_path(path).to_string(),
+            self.settings.clone(),
+            self.shared.clone(),
+        );
+        tab.state = EditorState::Loading(result_store);
+        let threshold = self.settings.read().large_file_threshold_kb.saturating_mul(1024);
+        tab.large_file = std::fs::metadata(path).is_ok_and(|m| m.len() > threshold);
+        Some(tab)
+    }
+
+    /// 在后台计算批量修改后进入预览
+    fn start_batch(
+        &mut self,
+        title: String,
+        scope: BatchScope,
+        transform: impl Fn(&str) -> Option<String> + Send + 'static,
+    ) {
+        let threshold = self.settings.read().large_file_threshold_kb.saturating_mul(1024);
+        let unsaved = self.unsaved_files();
+        let result = Arc::new(RwLock::new(None));
+        let result_thread = result.clone();
+        std::thread::spawn(move || {
+            let filter = |path: &Path| {
+                language_for_path(path) != "txt"
+                    && std::fs::metadata(path).is_ok_and(|m| m.len() <= threshold)
+            };
+            let edits = match scope {
+                BatchScope::Workspace(file) => batch::collect_edits(&workspace_root(&file), filter, transform),
+                BatchScope::File(file) => batch::file_edit(file, transform).into_iter().collect(),
+            };
+            let mut batch = batch::BatchEdit { title, edits };
+            batch.block_unsaved(&unsaved);
+            *result_thread.write() = Some(batch);
+        });
+        self.batch_job = Some(result);
+    }
+
+    /// 有未保存修改的已打开文件
+    fn unsaved_files(&self) -> Vec<PathBuf> {
+        self.shared.read().unsaved.values().cloned().collect()
+    }
+
+    /// 把写入磁盘的内容同步给已打开的编辑器
+    fn publish_reloads(&self, files: impl IntoIterator<Item = (PathBuf, String)>) {
+        let mut shared = self.shared.write();
+        shared.reloads.clear();
+        shared.reloads.extend(files);
+    }
+
+    fn batch_ui(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        let finished = self.batch_job.as_ref().and_then(|job| job.write().take());
+        if let Some(batch) = finished {
+            self.batch_job = None;
+            if batch.edits.is_empty() {
+                control.push(AppCommand::notify(
+                    format!("{}: nothing to change", batch.title),
+                    crate::NotificationLevel::Info,
+                ));
+            } else {
+                self.batch_preview = Some(batch);
+            }
+        }
+        if self.batch_job.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        let Some(preview) = &mut self.batch_preview else { return };
+        match preview.preview_ui(ctx) {
+            Some(batch::PreviewAction::Apply) => {
+                let Some(mut preview) = self.batch_preview.take() else { return };
+                // 预览期间才开始编辑的文件同样跳过
+                for path in preview.block_unsaved(&self.unsaved_files()) {
+                    control.push(AppCommand::notify(
+                        format!("{} was skipped: it has unsaved changes in an open editor", path.display()),
+                        crate::NotificationLevel::Warning,
+                    ));
+                }
+                let (applied, errors) = preview.apply();
+                self.publish_reloads(applied.files.iter().map(|(path, _, after)| (path.clone(), after.clone())));
+                control.push(AppCommand::notify(
+                    format!("{}: changed {} files", applied.title, applied.files.len()),
+                    crate::NotificationLevel::Success,
+                ));
+                for error in errors {
+                    control.push(AppCommand::notify(error, crate::NotificationLevel::Warning));
+                }
+                self.last_batch = (!applied.files.is_empty()).then_some(applied);
+            }
+            Some(batch::PreviewAction::Cancel) => self.batch_preview = None,
+            None => {}
+        }
+    }
+
+    fn rename_ui(&mut self, ctx: &egui::Context) {
+        if let Some((symbol, file)) = self.shared.write().rename_request.take() {
+            self.rename = Some(RenameDialog { new_name: symbol.clone(), symbol, file, whole_workspace: true });
+        }
+        let busy = self.batch_job.is_some() || self.batch_preview.is_some();
+        let Some(dialog) = &mut self.rename else { return };
+
+        let mut open = true;
+        let mut submit = false;
+        let mut cancel = false;
+        egui::Window::new("Rename Symbol (textual)")
+            .id(egui::Id::new("code_editor_rename"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(
+                        "This is a plain-text rename: every whole-word, case-sensitive match is replaced, \
+                         including unrelated identifiers, strings and comments with the same name. \
+                         Review the preview before applying.",
+                    )
+                    .weak(),
+                );
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!("Rename `{}` to", dialog.symbol));
+                    let response = ui.text_edit_singleline(&mut dialog.new_name);
+                    if ui.memory(|m| m.focused().is_none()) {
+                        response.request_focus();
+                    }
+                    submit = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                });
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut dialog.whole_workspace, true, "Workspace");
+                    let file_name = dialog.file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                    ui.radio_value(&mut dialog.whole_workspace, false, format!("Current file ({})", file_name));
+                });
+
+                let valid = batch::is_identifier(&dialog.new_name) && dialog.new_name != dialog.symbol;
+                if !batch::is_identifier(&dialog.new_name) {
+                    ui.colored_label(ui.visuals().warn_fg_color, "The new name must be an identifier");
+                }
+                submit &= valid && !busy;
+                ui.horizontal(|ui| {
+                    submit |= ui.add_enabled(valid && !busy, egui::Button::new("Preview…")).clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if submit {
+            let Some(dialog) = self.rename.take() else { return };
+            let title = format!("Rename {} to {}", dialog.symbol, dialog.new_name);
+            let transform = batch::rename_transform(&dialog.symbol, &dialog.new_name);
+            let scope = if dialog.whole_workspace { BatchScope::Workspace(dialog.file) } else { BatchScope::File(dialog.file) };
+            self.start_batch(title, scope, transform);
+        } else if cancel || !open {
+            self.rename = None;
+        }
+    }
+
+    /// 跳转到定义：同一文件直接跳转，否则打开该文件并在载入后跳转
+    fn goto_definition(&self, job: &DefinitionJob, target: &definition::Definition, control: &mut Vec<AppCommand>) {
+        let mut shared = self.shared.write();
+        if target.path == job.file {
+            shared.goto_request = Some((job.editor, target.line));
+        } else {
+            shared.open_goto = Some((target.path.clone(), target.line));
+            control.push(AppCommand::OpenFile(target.path.clone()));
+        }
+    }
+
+    fn definition_ui(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        let request = self.shared.write().definition_request.take();
+        if let Some((symbol, file, editor)) = request {
+            let language = language_for_path(&file);
+            if !language::LANGUAGE_PROFILES.iter().any(|p| p.id == language) {
+                control.push(AppCommand::notify(
+                    format!(
+                        "Peek Definition has no patterns for {}",
+                        file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                    ),
+                    crate::NotificationLevel::Info,
+                ));
+            } else {
+                let threshold = self.settings.read().large_file_threshold_kb.saturating_mul(1024);
+                let result = Arc::new(RwLock::new(None));
+                let result_thread = result.clone();
+                let (name, root) = (symbol.clone(), workspace_root(&file));
+                std::thread::spawn(move || {
+                    let filter = |path: &Path| {
+                        language_for_path(path) == language
+                            && std::fs::metadata(path).is_ok_and(|m| m.len() <= threshold)
+                    };
+                    *result_thread.write() = Some(definition::find_definitions(&root, language, &name, filter));
+                });
+                self.definition_picker = None;
+                self.definition_job = Some(DefinitionJob { symbol, file, editor, result });
+            }
+        }
+
+        let finished = self.definition_job.as_ref().is_some_and(|job| job.result.read().is_some());
+        if finished {
+            if let Some(job) = self.definition_job.take() {
+                let count = job.result.read().as_ref().map_or(0, |d| d.len());
+                match count {
+                    0 => control.push(AppCommand::notify(
+                        format!("No definition found for `{}` (heuristic search)", job.symbol),
+                        crate::NotificationLevel::Info,
+                    )),
+                    1 => {
+                        if let Some(target) = job.result.read().as_ref().and_then(|d| d.first().cloned()) {
+                            self.goto_definition(&job, &target, control);
+                        }
+                    }
+                    _ => self.definition_picker = Some(job),
+                }
+            }
+        } else if self.definition_job.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        let Some(job) = &self.definition_picker else { return };
+        let root = workspace_root(&job.file);
+        let definitions = job.result.read().clone().unwrap_or_default();
+        let mut open = true;
+        let mut chosen = None;
+        egui::Window::new(format!("Peek Definition (heuristic): {}", job.symbol))
+            .id(egui::Id::new("code_editor_definition_picker"))
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([520.0, 280.0])
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(
+                        "These lines match common definition patterns for this name. \
+                         This is a text search, not a semantic lookup.",
+                    )
+                    .weak(),
+                );
+                ui.separator();
+                egui::ScrollArea::vertical().id_salt("definition_picker_scroll").show(ui, |ui| {
+                    for (i, target) in definitions.iter().enumerate() {
+                        let relative = target.path.strip_prefix(&root).unwrap_or(&target.path);
+                        let location = format!("{}:{}", relative.to_string_lossy(), target.line + 1);
+                        let response = ui.add(egui::Button::new(egui::RichText::new(&location).strong()).frame(false))
+                            .on_hover_text(&target.preview);
+                        ui.label(egui::RichText::new(&target.preview).monospace().weak());
+                        if response.clicked() {
+                            chosen = Some(i);
+                        }
+                        ui.add_space(4.0);
+                    }
+                });
+            });
+
+        if let Some(target) = chosen.and_then(|i| definitions.get(i)) {
+            self.goto_definition(job, target, control);
+            self.definition_picker = None;
+        } else if !open {
+            self.definition_picker = None;
+        }
+    }
+
+    fn outline_panel_ui(&mut self, ui: &mut Ui) {
+        let mut shared = self.shared.write();
+        // 焦点编辑器在最近两个 pass 内没有渲染，说明它已被关闭或隐藏
+        let visible = shared.focused.is_some()
+            && ui.ctx().cumulative_pass_nr().saturating_sub(shared.focused_pass) <= 2;
+
+        ui.horizontal(|ui| {
+            ui.heading("Outline");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("✖").clicked() {
+                    self.show_outline = false;
+                }
+            });
+        });
+        ui.separator();
+
+        let Some(key) = shared.focused.filter(|_| visible) else {
+            ui.weak("No editor focused.");
+            return;
+        };
+        ui.label(egui::RichText::new(&shared.focused_name).small().weak());
+
+        let mut goto = None;
+        egui::ScrollArea::vertical()
+            .id_salt("code_editor_outline_scroll")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                if shared.symbols.is_empty() {
+                    ui.weak("No symbols found.");
+                }
+                for symbol in &shared.symbols {
+                    ui.horizontal(|ui| {
+                        ui.add_space(symbol.depth as f32 * 12.0);
+                        let text = format!("{} {}", symbol.kind.icon(), symbol.name);
+                        if ui.add(egui::Button::new(text).frame(false)).on_hover_text(format!("Line {}", symbol.line + 1)).clicked() {
+                            goto = Some(symbol.line);
+                        }
+                    });
+                }
+            });
+
+        if let Some(line) = goto {
+            shared.goto_request = Some((key, line));
+            ui.ctx().request_repaint();
+        }
+    }
+}
 
 impl Plugin for CodeEditorPlugin {
     fn name(&self) -> &str { crate::plugins::PLUGIN_NAME_CODE_EDITOR }
 
+    fn keybindings(&self) -> Vec<crate::keymap::Binding> {
+        use crate::keymap::Binding;
+        vec![
+            Binding::new("code_editor.save", "Code Editor", "Save", egui::Modifiers::COMMAND, egui::Key::S),
+            Binding::new("code_editor.undo", "Code Editor", "Undo", egui::Modifiers::COMMAND, egui::Key::Z),
+            Binding::new("code_editor.redo", "Code Editor", "Redo", egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z),
+            Binding::new("code_editor.find", "Code Editor", "Find", egui::Modifiers::COMMAND, egui::Key::F),
+            Binding::new("code_editor.replace", "Code Editor", "Find and Replace", egui::Modifiers::COMMAND, egui::Key::H),
+            Binding::new("code_editor.toggle_comment", "Code Editor", "Toggle Line Comment", egui::Modifiers::COMMAND, egui::Key::Slash),
+            Binding::new("code_editor.goto_line", "Code Editor", "Go to Line", egui::Modifiers::COMMAND, egui::Key::G),
+            Binding::new("code_editor.run", "Code Editor", "Run Current File", egui::Modifiers::NONE, egui::Key::F5),
+            Binding::new("code_editor.peek_definition", "Code Editor", "Peek Definition (heuristic)", egui::Modifiers::NONE, egui::Key::F12),
+        ]
+    }
+
+    fn tab_actions(&self) -> Vec<TabAction> {
+        vec![
+            TabAction::new("code_editor.save", "Save", TabCapabilities::SAVEABLE),
+            TabAction::new("code_editor.run", "Run Current File", TabCapabilities::RUNNABLE),
+        ]
+    }
+
     fn dependencies(&self) -> Vec<String> {
         vec!["core".to_string()]
     }
 
-    fn try_open_file(&mut self, path: &std::path::Path) -> Option<Box<dyn TabInstance>> {
-        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-        
-        // 映射扩展名到语法高亮 ID
-        let language = match ext {
-            "rs" => "rs",
-            "py" => "py",
-            "js" | "ts" => "js",
-            "html" => "html",
-            "css" => "css",
-            "json" => "json",
-            "md" => "md",
-            "toml" => "toml",
-            "c" | "h" => "c",
-            "cpp" | "hpp" | "cc" | "cxx" => "cpp",
-            _ => "txt",
-        };
+    fn restore_tab(&mut self, _ctx: &egui::Context, state: &toml::Table) -> Option<Box<dyn TabInstance>> {
+        let path = Path::new(state.get("path")?.as_str()?);
+        if !path.is_file() {
+            return None;
+        }
+        let mut tab = self.open_file(path)?;
+        if let Some(language) = state.get("language").and_then(|v| v.as_str()) {
+            tab.language = language.to_string();
+        }
+        Some(Box::new(tab))
+    }
 
-        // 如果是已知文本格式或没有扩展名（可能是 README 等）
-        if !language.is_empty() || ext.is_empty() {
-            let path_owned = path.to_path_buf();
-            let result_store = Arc::new(RwLock::new(None));
-            let result_store_clone = result_store.clone();
+    fn try_open_file(&mut self, path: &std::path::Path) -> Option<Box<dyn TabInstance>> {
+        self.open_file(path).map(|tab| Box::new(tab) as Box<dyn TabInstance>)
+    }
 
-            std::thread::spawn(move || {
-                let res = std::fs::read_to_string(&path_owned).map_err(|e| e.to_string());
-                *result_store_clone.write() = Some(res);
-            });
+    fn load_config(&mut self, config: &PluginConfig) {
+        let mut settings = EditorSettings::default();
+        if let Some(v) = config.get_bool("show_breadcrumbs") { settings.show_breadcrumbs = v; }
+        if let Some(v) = config.get_bool("relative_line_numbers") { settings.relative_line_numbers = v; }
+        if let Some(v) = config.get_bool("word_wrap") { settings.word_wrap = v; }
+        if let Some(v) = config.get_bool("auto_close_brackets") { settings.auto_close_brackets = v; }
+        if let Some(v) = config.get_bool("match_brackets") { settings.match_brackets = v; }
+        if let Some(v) = config.get_bool("auto_indent") { settings.auto_indent = v; }
+        if let Some(v) = config.get_integer("large_file_threshold_kb") {
+            settings.large_file_threshold_kb = v.max(0) as u64;
+        }
+        if let Some(v) = config.get_integer("scrolloff") {
+            settings.scrolloff = v.max(0) as usize;
+        }
+        if let Some(v) = config.get_integer("tab_width") { settings.indent.tab_width = v.clamp(1, 16) as usize; }
+        if let Some(v) = config.get_bool("insert_spaces") { settings.indent.insert_spaces = v; }
+        // 按语言的缩进设置以全局设置为基础，只覆盖写出的字段
+        if let Some(languages) = config.get_table("indent") {
+            for (language, table) in languages {
+                if let Some(table) = table.as_table() {
+                    settings.language_indent.insert(language.clone(), settings.indent.merge(table));
+                }
+            }
+        }
+        settings.on_save = settings.on_save.merge(&config.values);
+        // 按语言的保存时整理只记录写出的字段，其余字段在保存时沿用全局设置
+        if let Some(languages) = config.get_table("on_save") {
+            for (language, table) in languages {
+                if let Some(table) = table.as_table() {
+                    let entry = settings.language_on_save.entry(language.clone()).or_default();
+                    *entry = entry.merge(table);
+                }
+            }
+        }
+        // 配置中的片段与内置片段合并，同名触发词以配置为准
+        if let Some(languages) = config.get_table("snippets") {
+            for (language, snippets) in languages {
+                let Some(snippets) = snippets.as_table() else { continue };
+                let entry = settings.snippets.entry(language.clone()).or_default();
+                for (trigger, body) in snippets {
+                    if let Some(body) = body.as_str() {
+                        entry.insert(trigger.clone(), body.to_string());
+                    }
+                }
+            }
+        }
+        // 配置中有运行命令表时整体替换默认值，使删掉的默认命令不会在重启后恢复
+        if let Some(commands) = config.get_table("run_commands") {
+            settings.run_commands = commands.iter()
+                .filter_map(|(language, command)| Some((language.clone(), command.as_str()?.to_string())))
+                .collect();
+        }
+        *self.settings.write() = settings;
+        self.config = config.clone();
+    }
 
-            return Some(Box::new(CodeEditorTab {
-                name: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
-                path: Some(path.to_path_buf()),
-                code: String::new(),
-                language: language.to_string(),
-                is_dirty: false,
-                sync_mode: false,
-                last_sync_time: 0.0,
-                state: EditorState::Loading(result_store),
-            }));
+    fn save_config(&self) -> Option<toml::Table> {
+        let settings = self.settings.read();
+        let mut table = settings.indent.to_table();
+        table.extend(settings.on_save.to_table());
+        for (key, value) in [
+            ("show_breadcrumbs", settings.show_breadcrumbs),
+            ("relative_line_numbers", settings.relative_line_numbers),
+            ("word_wrap", settings.word_wrap),
+            ("auto_close_brackets", settings.auto_close_brackets),
+            ("match_brackets", settings.match_brackets),
+            ("auto_indent", settings.auto_indent),
+        ] {
+            table.insert(key.to_string(), value.into());
+        }
+        table.insert("large_file_threshold_kb".to_string(), (settings.large_file_threshold_kb as i64).into());
+        table.insert("scrolloff".to_string(), (settings.scrolloff as i64).into());
+        let indent: toml::Table = settings.language_indent.iter()
+            .map(|(language, style)| (language.clone(), style.to_table().into()))
+            .collect();
+        if !indent.is_empty() {
+            table.insert("indent".to_string(), indent.into());
         }
-        None
+        let on_save: toml::Table = settings.language_on_save.iter()
+            .map(|(language, overrides)| (language.clone(), overrides.to_table()))
+            .filter(|(_, overrides)| !overrides.is_empty())
+            .map(|(language, overrides)| (language, overrides.into()))
+            .collect();
+        if !on_save.is_empty() {
+            table.insert("on_save".to_string(), on_save.into());
+        }
+        let run_commands: toml::Table = settings.run_commands.iter()
+            .map(|(language, command)| (language.clone(), command.clone().into()))
+            .collect();
+        table.insert("run_commands".to_string(), run_commands.into());
+        // 片段只能在设置文件中编辑，且包含内置片段，不导出
+        Some(table)
     }
 
     fn on_settings_ui(&mut self, ui: &mut Ui) {
@@ -327,6 +2859,111 @@ impl Plugin for CodeEditorPlugin {
         ui.label("• Ctrl + S to save current file.");
         ui.label("• Syntax highlighting is automatically applied based on extension.");
         ui.label("• Right-click tab for Sync Mode (Read-only follow file).");
+        ui.label("• F5 to run the current file in a terminal.");
+        ui.label("• Ctrl + G to go to a line (`line` or `line:column`).");
+        ui.label("• Ctrl + / to comment or uncomment the current line or selected lines.");
+        ui.label("• Type a snippet trigger and press Tab to expand it; Tab / Shift+Tab move between placeholders, Esc exits.");
+        ui.label("• With Insert spaces on, Tab / Shift+Tab indent and dedent the selected lines. Override per language under [code_editor.indent.<language>].");
+        ui.label("• Saving trims trailing whitespace and ensures a final newline. Override per language under [code_editor.on_save.<language>].");
+        {
+            let mut settings = self.settings.write();
+            ui.checkbox(&mut settings.show_breadcrumbs, self.config.label("Show breadcrumbs", "show_breadcrumbs"));
+            ui.checkbox(&mut settings.relative_line_numbers, self.config.label("Relative line numbers", "relative_line_numbers"));
+            ui.checkbox(&mut settings.word_wrap, self.config.label("Word wrap", "word_wrap"));
+            ui.checkbox(&mut settings.auto_close_brackets, self.config.label("Auto-close brackets and quotes", "auto_close_brackets"));
+            ui.checkbox(&mut settings.match_brackets, self.config.label("Highlight matching brackets", "match_brackets"));
+            ui.checkbox(&mut settings.auto_indent, self.config.label("Auto-indent new lines", "auto_indent"));
+            ui.horizontal(|ui| {
+                ui.label(self.config.label("Large file mode above", "large_file_threshold_kb"));
+                ui.add(egui::DragValue::new(&mut settings.large_file_threshold_kb).range(64..=1_048_576).suffix(" KB"));
+            });
+            ui.horizontal(|ui| {
+                ui.label(self.config.label("Keep lines visible around the cursor", "scrolloff"));
+                ui.add(egui::DragValue::new(&mut settings.scrolloff).range(0..=20));
+            });
+            ui.horizontal(|ui| {
+                ui.label(self.config.label("Tab width", "tab_width"));
+                ui.add(egui::DragValue::new(&mut settings.indent.tab_width).range(1..=16));
+                ui.checkbox(&mut settings.indent.insert_spaces, self.config.label("Insert spaces", "insert_spaces"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("On save:");
+                ui.checkbox(&mut settings.on_save.trim_trailing_whitespace, self.config.label("Trim trailing whitespace", "trim_trailing_whitespace"));
+                ui.checkbox(&mut settings.on_save.insert_final_newline, self.config.label("Ensure final newline", "insert_final_newline"));
+            });
+            if !settings.language_on_save.is_empty() {
+                ui.collapsing(self.config.label("Per-language save cleanup", "on_save"), |ui| {
+                    let base = settings.on_save;
+                    egui::Grid::new("language_on_save_grid").num_columns(3).show(ui, |ui| {
+                        for (language, overrides) in settings.language_on_save.iter_mut() {
+                            // 显示实际生效的值，改动后该字段才成为覆盖
+                            let mut cleanup = overrides.resolve(base);
+                            ui.label(language);
+                            if ui.checkbox(&mut cleanup.trim_trailing_whitespace, "Trim trailing whitespace").changed() {
+                                overrides.trim_trailing_whitespace = Some(cleanup.trim_trailing_whitespace);
+                            }
+                            if ui.checkbox(&mut cleanup.insert_final_newline, "Final newline").changed() {
+                                overrides.insert_final_newline = Some(cleanup.insert_final_newline);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+            if !settings.language_indent.is_empty() {
+                ui.collapsing(self.config.label("Per-language indentation", "indent"), |ui| {
+                    egui::Grid::new("language_indent_grid").num_columns(3).show(ui, |ui| {
+                        for (language, style) in settings.language_indent.iter_mut() {
+                            ui.label(language);
+                            ui.add(egui::DragValue::new(&mut style.tab_width).range(1..=16));
+                            ui.checkbox(&mut style.insert_spaces, "Insert spaces");
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        }
+
+        ui.separator();
+        ui.label(self.config.label("Run Commands (placeholders: ${file}, ${fileDir}, ${workspaceRoot})", "run_commands"));
+        let mut settings = self.settings.write();
+        let mut to_remove = None;
+        egui::Grid::new("run_commands_grid").num_columns(3).show(ui, |ui| {
+            for (language, command) in settings.run_commands.iter_mut() {
+                ui.label(language);
+                ui.add(egui::TextEdit::singleline(command).desired_width(240.0));
+                if ui.small_button("🗑").clicked() {
+                    to_remove = Some(language.clone());
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(language) = to_remove {
+            settings.run_commands.remove(&language);
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_run_language).hint_text("language (e.g. sh)").desired_width(120.0));
+            if ui.button("Add").clicked() && !self.new_run_language.trim().is_empty() {
+                settings.run_commands.entry(self.new_run_language.trim().to_string()).or_default();
+                self.new_run_language.clear();
+            }
+        });
+
+        ui.separator();
+        ui.collapsing(self.config.label("Snippets", "snippets"), |ui| {
+            ui.label(
+                egui::RichText::new(
+                    "Add snippets under [code_editor.snippets.<language>] in the settings file, e.g.\n\
+                     fn = \"fn ${1:name}() {\\n    $0\\n}\"\n\
+                     $1, $2 … are placeholders visited with Tab, ${1:text} has default text, $0 is the final position.",
+                )
+                .weak(),
+            );
+            for (language, snippets) in &settings.snippets {
+                let triggers: Vec<&str> = snippets.keys().map(|t| t.as_str()).collect();
+                ui.label(format!("{}: {}", language, triggers.join(", ")));
+            }
+        });
     }
 
     fn on_tab_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
@@ -336,12 +2973,66 @@ impl Plugin for CodeEditorPlugin {
                 None,
                 String::new(),
                 "rs".into(),
+                self.settings.clone(),
+                self.shared.clone(),
             )))));
             ui.close();
         }
+        if ui.checkbox(&mut self.show_outline, "Outline Panel").clicked() {
+            ui.close();
+        }
+    }
+
+    fn on_file_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        let busy = self.batch_job.is_some() || self.batch_preview.is_some();
+        if ui.add_enabled(!busy, egui::Button::new("Trim Trailing Whitespace in Workspace…")).clicked() {
+            // 以最近使用的编辑器所在的工作区为范围
+            let focused_path = self.shared.read().focused_path.clone();
+            match focused_path {
+                Some(file) => {
+                    let transform = |text: &str| Some(trim_trailing_whitespace(text));
+                    self.start_batch("Trim Trailing Whitespace".to_string(), BatchScope::Workspace(file), transform);
+                }
+                None => control.push(AppCommand::notify(
+                    "Open a file from the workspace first",
+                    crate::NotificationLevel::Warning,
+                )),
+            }
+            ui.close();
+        }
+        if let Some(last) = &self.last_batch {
+            if ui.button(format!("Undo Batch: {}", last.title)).clicked() {
+                if let Some(last) = self.last_batch.take() {
+                    let title = last.title.clone();
+                    let (restored, errors) = last.revert(&self.unsaved_files());
+                    control.push(AppCommand::notify(
+                        format!("Undid {}: restored {} files", title, restored.len()),
+                        crate::NotificationLevel::Success,
+                    ));
+                    for error in errors {
+                        control.push(AppCommand::notify(error, crate::NotificationLevel::Warning));
+                    }
+                    self.publish_reloads(restored);
+                }
+                ui.close();
+            }
+        }
+    }
+
+    fn on_global_ui(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        self.rename_ui(ctx);
+        self.batch_ui(ctx, control);
+        self.definition_ui(ctx, control);
+
+        egui::SidePanel::right("code_editor_outline")
+            .resizable(true)
+            .default_width(220.0)
+            .show_animated(ctx, self.show_outline, |ui| {
+                self.outline_panel_ui(ui);
+            });
     }
 }
 
 pub fn create() -> CodeEditorPlugin {
-    CodeEditorPlugin
+    CodeEditorPlugin::default()
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+//! 基于正则的轻量级大纲提取 (无需 LSP)
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use crate::plugins::code_editor::language::LANGUAGE_PROFILES;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Type,
+    Module,
+    Impl,
+    Heading,
+}
+
+impl SymbolKind {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            SymbolKind::Function => "ƒ",
+            SymbolKind::Type => "◆",
+            SymbolKind::Module => "▣",
+            SymbolKind::Impl => "◇",
+            SymbolKind::Heading => "#",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 0 起始的行号
+    pub line: usize,
+    /// 嵌套深度 (缩进层级或标题级别)，0 表示顶层
+    pub depth: usize,
+}
+
+/// 编译后的符号规则，按语言 ID 缓存
+fn patterns(language: &str) -> &'static [(SymbolKind, Regex)] {
+    static COMPILED: OnceLock<HashMap<&'static str, Vec<(SymbolKind, Regex)>>> = OnceLock::new();
+    let compiled = COMPILED.get_or_init(|| {
+        LANGUAGE_PROFILES
+            .iter()
+            .map(|profile| {
+                let rules = profile.symbol_patterns
+                    .iter()
+                    .map(|(kind, re)| (*kind, Regex::new(re).unwrap()))
+                    .collect();
+                (profile.id, rules)
+            })
+            .collect()
+    });
+    compiled.get(language).map(|v| v.as_slice()).unwrap_or_default()
+}
+
+/// 计算一行的缩进层级 (Tab 视为 4 列)
+fn indent_depth(line: &str) -> usize {
+    let cols: usize = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    cols / 4
+}
+
+/// 从源码中提取符号列表，按行号排序
+pub fn extract_symbols(language: &str, code: &str) -> Vec<Symbol> {
+    let rules = patterns(language);
+    if rules.is_empty() {
+        return Vec::new();
+    }
+
+    let mut symbols = Vec::new();
+    for (line_idx, line) in code.lines().enumerate() {
+        for (kind, re) in rules {
+            if let Some(name) = re.captures(line).and_then(|c| c.get(1)) {
+                let name = name.as_str();
+                let (name, depth) = if *kind == SymbolKind::Heading {
+                    let level = name.chars().take_while(|&c| c == '#').count();
+                    (name[level..].trim().to_string(), level - 1)
+                } else {
+                    (name.trim().to_string(), indent_depth(line))
+                };
+                symbols.push(Symbol { name, kind: *kind, line: line_idx, depth });
+                break;
+            }
+        }
+    }
+    symbols
+}
+
+/// 查找光标所在行所属的顶层符号
+pub fn enclosing_symbol(symbols: &[Symbol], line: usize) -> Option<&Symbol> {
+    symbols.iter().rev().find(|s| s.depth == 0 && s.line <= line)
+}
@@ -0,0 +1,142 @@
+//! 代码片段：输入触发词后按 Tab 展开模板，并用 Tab / Shift+Tab 在占位符之间切换
+//!
+//! 模板格式 (与 VS Code / TextMate 的常用子集一致)：
+//! - `$1`、`$2` … 依次跳转的位置，`$0` 为最后的位置 (省略时为片段末尾)
+//! - `${1:默认值}` 带默认文本的占位符，跳到该位置时默认文本被选中
+//! - `\$` 输出字面量 `$`
+//!
+//! 用户片段写在配置的 `[code_editor.snippets.<语言>]` 表中，例如 `fn = "fn ${1:name}() {\n    $0\n}"`，
+//! 与内置片段同名时覆盖内置片段。
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// 内置片段: (语言, 触发词, 模板)
+const BUILTIN_SNIPPETS: &[(&str, &str, &str)] = &[
+    ("rs", "fn", "fn ${1:name}(${2}) {\n    $0\n}"),
+    ("rs", "main", "fn main() {\n    $0\n}"),
+    ("rs", "for", "for ${1:item} in ${2:iter} {\n    $0\n}"),
+    ("rs", "impl", "impl ${1:Type} {\n    $0\n}"),
+    ("rs", "test", "#[test]\nfn ${1:name}() {\n    $0\n}"),
+    ("py", "def", "def ${1:name}(${2}):\n    ${0:pass}"),
+    ("py", "main", "if __name__ == \"__main__\":\n    ${0:main()}"),
+    ("py", "for", "for ${1:item} in ${2:items}:\n    ${0:pass}"),
+    ("js", "fn", "function ${1:name}(${2}) {\n    $0\n}"),
+    ("js", "for", "for (const ${1:item} of ${2:items}) {\n    $0\n}"),
+    ("js", "log", "console.log($0);"),
+];
+
+/// 内置片段，按语言分组
+pub fn builtin() -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut snippets: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    for (language, trigger, body) in BUILTIN_SNIPPETS {
+        snippets.entry(language.to_string()).or_default().insert(trigger.to_string(), body.to_string());
+    }
+    snippets
+}
+
+/// 展开后的文本与各占位符在其中的字符范围 (已按跳转顺序排列，`$0` 在最后)
+pub struct Expansion {
+    pub text: String,
+    pub stops: Vec<Range<usize>>,
+}
+
+/// 展开模板；`indent` 被加到模板第一行之后的每一行前，使多行片段与触发位置对齐
+pub fn expand(body: &str, indent: &str) -> Expansion {
+    let mut text = String::new();
+    let mut len = 0;
+    let mut stops: Vec<(usize, Range<usize>)> = Vec::new();
+    let mut push = |text: &mut String, len: &mut usize, c: char| {
+        text.push(c);
+        *len += 1;
+        if c == '\n' {
+            text.push_str(indent);
+            *len += indent.chars().count();
+        }
+    };
+
+    let chars: Vec<char> = body.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&'$') => {
+                push(&mut text, &mut len, '$');
+                i += 2;
+            }
+            '$' => {
+                let digits = |from: usize| chars[from..].iter().take_while(|c| c.is_ascii_digit()).count();
+                if chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) {
+                    // $N
+                    let n = digits(i + 1);
+                    let index: usize = chars[i + 1..i + 1 + n].iter().collect::<String>().parse().unwrap_or(0);
+                    stops.push((index, len..len));
+                    i += 1 + n;
+                } else if chars.get(i + 1) == Some(&'{') && chars.get(i + 2).is_some_and(|c| c.is_ascii_digit()) {
+                    // ${N} 或 ${N:默认值}
+                    let n = digits(i + 2);
+                    let index: usize = chars[i + 2..i + 2 + n].iter().collect::<String>().parse().unwrap_or(0);
+                    let mut j = i + 2 + n;
+                    let start = len;
+                    if chars.get(j) == Some(&':') {
+                        j += 1;
+                        while j < chars.len() && chars[j] != '}' {
+                            push(&mut text, &mut len, chars[j]);
+                            j += 1;
+                        }
+                    }
+                    stops.push((index, start..len));
+                    i = (j + 1).min(chars.len());
+                } else {
+                    push(&mut text, &mut len, '$');
+                    i += 1;
+                }
+            }
+            c => {
+                push(&mut text, &mut len, c);
+                i += 1;
+            }
+        }
+    }
+
+    // 同一编号只使用第一次出现的位置；$0 排在最后，没有 $0 时在末尾补一个
+    let mut ordered: Vec<(usize, Range<usize>)> = Vec::new();
+    for (index, range) in stops {
+        if !ordered.iter().any(|(i, _)| *i == index) {
+            ordered.push((index, range));
+        }
+    }
+    if !ordered.iter().any(|(i, _)| *i == 0) {
+        ordered.push((0, len..len));
+    }
+    ordered.sort_by_key(|(index, _)| if *index == 0 { usize::MAX } else { *index });
+    Expansion { text, stops: ordered.into_iter().map(|(_, range)| range).collect() }
+}
+
+/// 正在进行的片段：记录各占位符在缓冲区中的字符范围，并随用户输入调整
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// 按跳转顺序排列的占位符
+    pub stops: Vec<Range<usize>>,
+    pub current: usize,
+    /// 整个片段的范围，光标离开后结束片段
+    pub region: Range<usize>,
+    /// 上次同步时缓冲区的字符数
+    pub len: usize,
+}
+
+impl Session {
+    /// 缓冲区长度变化了 `delta` 个字符：视为发生在当前占位符内，之后的占位符随之移动
+    pub fn shift(&mut self, delta: isize) {
+        let apply = |v: usize| v.saturating_add_signed(delta);
+        let current = &mut self.stops[self.current];
+        current.end = apply(current.end).max(current.start);
+        for stop in &mut self.stops[self.current + 1..] {
+            *stop = apply(stop.start)..apply(stop.end);
+        }
+        self.region.end = apply(self.region.end).max(self.region.start);
+    }
+
+    pub fn current_stop(&self) -> Range<usize> {
+        self.stops[self.current].clone()
+    }
+}
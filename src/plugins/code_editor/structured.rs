@@ -0,0 +1,439 @@
+//! JSON / TOML 配置文件的结构视图：解析出带源码位置的树。
+//! 修改值时只替换该值在源码中的片段，文本始终是唯一的数据来源，其余格式 (注释、缩进、顺序) 保持不变
+
+use std::ops::Range;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Table(Vec<(String, Node)>),
+    Array(Vec<Node>),
+    String(String),
+    Number,
+    Bool(bool),
+    Null,
+    /// 结构视图中只读显示的值 (例如 TOML 的日期时间)
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub value: Value,
+    /// 值在源码中的字节范围，未知时为空
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    /// 出错位置的字节偏移
+    pub offset: usize,
+}
+
+/// 支持结构视图的语言
+pub fn supports(language: &str) -> bool {
+    matches!(language, "json" | "toml")
+}
+
+pub fn parse(language: &str, text: &str) -> Result<Node, ParseError> {
+    match language {
+        "toml" => parse_toml(text),
+        _ => JsonParser { text, pos: 0 }.document(),
+    }
+}
+
+/// 把字符串编码为该语言的字符串字面量
+pub fn encode_string(value: &str) -> String {
+    // JSON 字符串与 TOML 基本字符串的转义规则在这些字符上一致
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `literal` 是否为合法的数字字面量
+pub fn is_number(language: &str, literal: &str) -> bool {
+    let literal = literal.trim();
+    if literal.is_empty() {
+        return false;
+    }
+    match language {
+        "toml" => toml_edit::ImDocument::parse(format!("v = {}", literal))
+            .ok()
+            .and_then(|doc| doc.as_table().get("v").and_then(|item| item.as_value()).map(|v| v.is_integer() || v.is_float()))
+            .unwrap_or(false),
+        _ => matches!(JsonParser { text: literal, pos: 0 }.document(), Ok(Node { value: Value::Number, .. })),
+    }
+}
+
+/// 字节偏移对应的 (0 起始行号, 0 起始列号)
+pub fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let col = before.rsplit('\n').next().map_or(0, |l| l.chars().count());
+    (line, col)
+}
+
+// ----------------------------------------------------------------------------
+// TOML
+// ----------------------------------------------------------------------------
+
+fn parse_toml(text: &str) -> Result<Node, ParseError> {
+    let doc = toml_edit::ImDocument::parse(text).map_err(|e| ParseError {
+        message: e.message().to_string(),
+        offset: e.span().map_or(0, |s| s.start),
+    })?;
+    Ok(Node { value: toml_table(doc.as_table()), span: 0..text.len() })
+}
+
+fn toml_table(table: &toml_edit::Table) -> Value {
+    Value::Table(table.iter().filter_map(|(key, item)| Some((key.to_string(), toml_item(item)?))).collect())
+}
+
+fn toml_item(item: &toml_edit::Item) -> Option<Node> {
+    match item {
+        toml_edit::Item::None => None,
+        toml_edit::Item::Value(value) => Some(toml_value(value)),
+        toml_edit::Item::Table(table) => Some(Node { value: toml_table(table), span: 0..0 }),
+        toml_edit::Item::ArrayOfTables(tables) => Some(Node {
+            value: Value::Array(tables.iter().map(|t| Node { value: toml_table(t), span: 0..0 }).collect()),
+            span: 0..0,
+        }),
+    }
+}
+
+fn toml_value(value: &toml_edit::Value) -> Node {
+    let span = value.span().unwrap_or_default();
+    let value = match value {
+        toml_edit::Value::String(s) => Value::String(s.value().clone()),
+        toml_edit::Value::Integer(_) | toml_edit::Value::Float(_) => Value::Number,
+        toml_edit::Value::Boolean(b) => Value::Bool(*b.value()),
+        toml_edit::Value::Datetime(_) => Value::Other,
+        toml_edit::Value::Array(array) => Value::Array(array.iter().map(toml_value).collect()),
+        toml_edit::Value::InlineTable(table) => {
+            Value::Table(table.iter().map(|(key, v)| (key.to_string(), toml_value(v))).collect())
+        }
+    };
+    Node { value, span }
+}
+
+// ----------------------------------------------------------------------------
+// JSON (serde_json 不提供源码位置，这里用一个记录位置的小型递归下降解析器)
+// ----------------------------------------------------------------------------
+
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError { message: message.to_string(), offset: self.pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn document(mut self) -> Result<Node, ParseError> {
+        self.skip_whitespace();
+        let node = self.value()?;
+        self.skip_whitespace();
+        if self.pos < self.text.len() {
+            return Err(self.error("unexpected content after the value"));
+        }
+        Ok(node)
+    }
+
+    fn value(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        let value = match self.peek() {
+            Some(b'{') => self.object()?,
+            Some(b'[') => self.array()?,
+            Some(b'"') => Value::String(self.string()?),
+            Some(b'-' | b'0'..=b'9') => self.number()?,
+            Some(_) if self.text[self.pos..].starts_with("true") => { self.pos += 4; Value::Bool(true) }
+            Some(_) if self.text[self.pos..].starts_with("false") => { self.pos += 5; Value::Bool(false) }
+            Some(_) if self.text[self.pos..].starts_with("null") => { self.pos += 4; Value::Null }
+            Some(_) => return Err(self.error("expected a value")),
+            None => return Err(self.error("unexpected end of input")),
+        };
+        Ok(Node { value, span: start..self.pos })
+    }
+
+    fn object(&mut self) -> Result<Value, ParseError> {
+        self.expect(b'{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Table(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => { self.pos += 1; return Ok(Value::Table(entries)); }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => { self.pos += 1; return Ok(Value::Array(items)); }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let Some(c) = rest.chars().next() else { return Err(self.error("unterminated string")) };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match escape {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => {
+                            let high = self.hex4()?;
+                            let code = if (0xD800..0xDC00).contains(&high) && self.text[self.pos..].starts_with("\\u") {
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                            } else {
+                                high
+                            };
+                            out.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        }
+                        _ => return Err(self.error("invalid escape sequence")),
+                    }
+                }
+                c if c.is_control() => return Err(self.error("control character in string")),
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let digits = self.text.get(self.pos..self.pos + 4).ok_or_else(|| self.error("invalid unicode escape"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Value, ParseError> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        let digits = |p: &mut Self| {
+            let from = p.pos;
+            while matches!(p.peek(), Some(b'0'..=b'9')) {
+                p.pos += 1;
+            }
+            p.pos > from
+        };
+        if !digits(self) {
+            return Err(self.error("expected digits"));
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if !digits(self) {
+                return Err(self.error("expected digits after '.'"));
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("expected exponent digits"));
+            }
+        }
+        // 前导零 ("01") 在 JSON 中不合法
+        let int = self.text[start..self.pos].trim_start_matches('-');
+        if int.len() > 1 && int.starts_with('0') && int.as_bytes()[1].is_ascii_digit() {
+            return Err(ParseError { message: "leading zeros are not allowed".to_string(), offset: start });
+        }
+        Ok(Value::Number)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// 树形视图
+// ----------------------------------------------------------------------------
+
+/// 绘制一帧结构视图所需的状态；绘制后由调用方应用 `change` 与 `goto`
+pub struct StructureView<'a> {
+    pub code: &'a str,
+    pub language: &'a str,
+    pub editable: bool,
+    /// 正在编辑的值: (源码范围, 输入内容)
+    pub edit: &'a mut Option<(Range<usize>, String)>,
+    /// 用户确认的修改: (源码范围, 新的字面量)
+    pub change: Option<(Range<usize>, String)>,
+    /// 用户点击了键名，跳转到该字节偏移
+    pub goto: Option<usize>,
+}
+
+impl StructureView<'_> {
+    /// 绘制根节点的子项 (根节点本身不显示标题)
+    pub fn root_ui(&mut self, ui: &mut egui::Ui, root: &Node) {
+        let id = ui.id().with("structure_root");
+        match &root.value {
+            Value::Table(entries) => {
+                for (key, child) in entries {
+                    self.node_ui(ui, key, child, id.with(key));
+                }
+            }
+            Value::Array(items) => {
+                for (i, child) in items.iter().enumerate() {
+                    self.node_ui(ui, &format!("[{}]", i), child, id.with(i));
+                }
+            }
+            _ => self.node_ui(ui, "(value)", root, id),
+        }
+    }
+
+    fn node_ui(&mut self, ui: &mut egui::Ui, key: &str, node: &Node, id: egui::Id) {
+        match &node.value {
+            Value::Table(entries) => {
+                egui::CollapsingHeader::new(format!("{}  {{{}}}", key, entries.len()))
+                    .id_salt(id)
+                    .show(ui, |ui| {
+                        for (child_key, child) in entries {
+                            self.node_ui(ui, child_key, child, id.with(child_key));
+                        }
+                    });
+            }
+            Value::Array(items) => {
+                egui::CollapsingHeader::new(format!("{}  [{}]", key, items.len()))
+                    .id_salt(id)
+                    .show(ui, |ui| {
+                        for (i, child) in items.iter().enumerate() {
+                            self.node_ui(ui, &format!("[{}]", i), child, id.with(i));
+                        }
+                    });
+            }
+            _ => {
+                ui.horizontal(|ui| {
+                    let label = ui.add(egui::Label::new(egui::RichText::new(key).strong()).sense(egui::Sense::click()));
+                    if label.on_hover_text("Go to this value in the text").clicked() && !node.span.is_empty() {
+                        self.goto = Some(node.span.start);
+                    }
+                    self.value_ui(ui, node);
+                });
+            }
+        }
+    }
+
+    fn value_ui(&mut self, ui: &mut egui::Ui, node: &Node) {
+        let raw = self.code.get(node.span.clone()).unwrap_or("");
+        let editable = self.editable && !node.span.is_empty();
+        match &node.value {
+            Value::Bool(value) => {
+                let mut value = *value;
+                if ui.add_enabled(editable, egui::Checkbox::without_text(&mut value)).changed() {
+                    self.change = Some((node.span.clone(), value.to_string()));
+                }
+            }
+            Value::String(_) | Value::Number => self.text_value_ui(ui, node, raw, editable),
+            _ => {
+                ui.label(egui::RichText::new(raw).monospace().weak());
+            }
+        }
+    }
+
+    /// 字符串与数字：点击后就地编辑，Enter 确认，焦点离开则放弃
+    fn text_value_ui(&mut self, ui: &mut egui::Ui, node: &Node, raw: &str, editable: bool) {
+        let is_string = matches!(node.value, Value::String(_));
+        if let Some((_, buffer)) = self.edit.as_mut().filter(|(span, _)| *span == node.span) {
+            let valid = is_string || is_number(self.language, buffer);
+            let mut edit = egui::TextEdit::singleline(buffer).font(egui::TextStyle::Monospace).desired_width(f32::INFINITY);
+            if !valid {
+                edit = edit.text_color(ui.visuals().error_fg_color);
+            }
+            let response = ui.add(edit);
+            if response.lost_focus() {
+                if ui.input(|i| i.key_pressed(egui::Key::Enter)) && valid {
+                    let literal = if is_string { encode_string(buffer) } else { buffer.trim().to_string() };
+                    self.change = Some((node.span.clone(), literal));
+                }
+                *self.edit = None;
+            } else if !response.has_focus() {
+                response.request_focus();
+            }
+            return;
+        }
+
+        let button = egui::Button::new(egui::RichText::new(raw).monospace()).frame(false);
+        if ui.add_enabled(editable, button).on_hover_text("Click to edit").clicked() {
+            let initial = match &node.value {
+                Value::String(value) => value.clone(),
+                _ => raw.to_string(),
+            };
+            *self.edit = Some((node.span.clone(), initial));
+        }
+    }
+}
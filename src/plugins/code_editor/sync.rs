@@ -0,0 +1,94 @@
+//! 监听文件变化并读取完整内容，用于同步模式与检测文件在外部被修改。
+//! 短时间内的连续事件 (编辑器保存时的截断 + 写入、原子保存的删除 + 重命名) 合并为一次读取
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 最后一个事件之后等待的时间，期间的新事件会重新计时
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+pub enum SyncUpdate {
+    /// 文件的最新内容
+    Changed(String),
+    /// 文件被删除 (每次删除只报告一次)；重新出现后会再次报告 `Changed`
+    Removed,
+}
+
+#[derive(Clone)]
+pub struct SyncWatcher {
+    path: PathBuf,
+    /// 尚未处理的变化在该时刻之后读取
+    due: Arc<Mutex<Option<Instant>>>,
+    /// 已报告文件被删除，避免重复提示
+    missing: bool,
+    /// 保持监听器存活，标签页克隆时共享；最后一个副本被丢弃时停止监听
+    _watcher: Arc<Mutex<RecommendedWatcher>>,
+}
+
+impl std::fmt::Debug for SyncWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncWatcher").field("path", &self.path).finish()
+    }
+}
+
+impl SyncWatcher {
+    /// 开始监听 `path`，首次 `poll` 会载入当前内容。
+    /// 与跟随模式一样监听所在目录，文件被删除后重新创建或被原子替换时仍能收到事件
+    pub fn start(path: &Path, ctx: egui::Context) -> notify::Result<Self> {
+        let due = Arc::new(Mutex::new(Some(Instant::now())));
+        let due_flag = due.clone();
+        let target = path.to_path_buf();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else { return };
+            if event.kind.is_access() || !event.paths.iter().any(|p| *p == target) {
+                return;
+            }
+            *due_flag.lock() = Some(Instant::now() + DEBOUNCE);
+            ctx.request_repaint_after(DEBOUNCE);
+        })?;
+        watcher.watch(path.parent().unwrap_or(path), RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            due,
+            missing: false,
+            _watcher: Arc::new(Mutex::new(watcher)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 事件平息后读取文件；仍在去抖等待中或没有变化时返回 None
+    pub fn poll(&mut self, ctx: &egui::Context) -> Option<SyncUpdate> {
+        {
+            let mut due = self.due.lock();
+            let wait = (*due)?.saturating_duration_since(Instant::now());
+            if !wait.is_zero() {
+                ctx.request_repaint_after(wait);
+                return None;
+            }
+            *due = None;
+        }
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => {
+                self.missing = false;
+                Some(SyncUpdate::Changed(content))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                (!std::mem::replace(&mut self.missing, true)).then_some(SyncUpdate::Removed)
+            }
+            // 读取失败 (例如文件正被独占写入) 时稍后重试
+            Err(_) => {
+                *self.due.lock() = Some(Instant::now() + DEBOUNCE);
+                ctx.request_repaint_after(DEBOUNCE);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! 撤销 / 重做历史：每帧按 revision 检查内容是否变化并记录快照，
+//! 短时间内的连续输入合并为一步。快照保存完整内容，因此数量与总大小都有上限
+
+use std::collections::VecDeque;
+
+/// 两次输入间隔小于该值 (秒) 时合并为一步
+const COALESCE_SECS: f64 = 1.0;
+/// 单次变化不超过该字节数才视为连续输入 (一个字符最多 4 字节)
+const TYPING_BYTES: usize = 4;
+const MAX_SNAPSHOTS: usize = 200;
+/// 撤销栈中快照的总大小上限 (字节)，超出时丢弃最早的快照
+const MAX_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    code: String,
+    /// (锚点, 光标) 的字符索引
+    selection: Option<(usize, usize)>,
+    id: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// 最近记录的状态
+    current: Option<Snapshot>,
+    /// 记录 `current` 时的 revision
+    revision: u64,
+    /// 与磁盘内容一致的快照
+    saved: Option<u64>,
+    next_id: u64,
+    /// 上一次输入的时间，用于合并连续输入
+    last_edit: f64,
+}
+
+impl History {
+    /// 记录自上次调用以来的修改；`clean` 表示当前内容与磁盘一致 (刚加载、保存或从磁盘重新读取)
+    pub fn record(&mut self, code: &str, revision: u64, selection: Option<(usize, usize)>, clean: bool, now: f64) {
+        if self.current.is_none() || revision != self.revision {
+            self.next_id += 1;
+            let snapshot = Snapshot { code: code.to_string(), selection, id: self.next_id };
+            if let Some(previous) = self.current.replace(snapshot) {
+                // 保存点不能被合并掉，否则撤销回到该处时无法判断内容是否已保存
+                let typing = !clean
+                    && now - self.last_edit < COALESCE_SECS
+                    && previous.code.len().abs_diff(code.len()) <= TYPING_BYTES
+                    && self.saved != Some(previous.id);
+                if !typing {
+                    self.push_undo(previous);
+                }
+                self.redo.clear();
+                self.last_edit = now;
+            }
+            self.revision = revision;
+        } else if let Some(current) = &mut self.current {
+            // 内容未变时只更新光标，撤销到此处时恢复最后的光标位置
+            current.selection = selection;
+        }
+        if clean {
+            self.saved = self.current.as_ref().map(|s| s.id);
+        }
+    }
+
+    fn push_undo(&mut self, snapshot: Snapshot) {
+        self.undo.push_back(snapshot);
+        let mut bytes: usize = self.undo.iter().map(|s| s.code.len()).sum();
+        while self.undo.len() > MAX_SNAPSHOTS || (bytes > MAX_BYTES && self.undo.len() > 1) {
+            if let Some(dropped) = self.undo.pop_front() {
+                bytes -= dropped.code.len();
+            }
+        }
+    }
+
+    /// 撤销一步，返回要恢复的内容与选区；之后需调用 `synced` 告知新的 revision
+    pub fn undo(&mut self) -> Option<(String, Option<(usize, usize)>)> {
+        let previous = self.undo.pop_back()?;
+        let current = self.current.replace(previous)?;
+        self.redo.push(current);
+        self.restored()
+    }
+
+    pub fn redo(&mut self) -> Option<(String, Option<(usize, usize)>)> {
+        let next = self.redo.pop()?;
+        let current = self.current.replace(next)?;
+        self.undo.push_back(current);
+        self.restored()
+    }
+
+    fn restored(&mut self) -> Option<(String, Option<(usize, usize)>)> {
+        // 撤销后的输入开始新的一步
+        self.last_edit = f64::NEG_INFINITY;
+        self.current.as_ref().map(|s| (s.code.clone(), s.selection))
+    }
+
+    /// 撤销 / 重做写回内容后调用，使这次变化不被当作新的修改记录
+    pub fn synced(&mut self, revision: u64) {
+        self.revision = revision;
+    }
+
+    /// 当前状态是否与磁盘内容一致
+    pub fn is_saved(&self) -> bool {
+        self.current.is_some() && self.current.as_ref().map(|s| s.id) == self.saved
+    }
+}
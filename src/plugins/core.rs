@@ -1,5 +1,23 @@
 use egui::Ui;
-use crate::{Plugin, AppCommand};
+use crate::{Plugin, AppCommand, Command, NotificationLevel};
+
+const REPOSITORY_URL: &str = "https://github.com/CuteZaiyuan2333/Somnium";
+const GIT_HASH: &str = env!("VERBIUM_GIT_HASH");
+const BEVY_VERSION: &str = env!("VERBIUM_BEVY_VERSION");
+const EGUI_VERSION: &str = env!("VERBIUM_EGUI_VERSION");
+
+/// 汇总运行环境信息，便于用户提交问题报告
+fn diagnostics() -> String {
+    format!(
+        "Verbium {}\nCommit: {}\nBevy: {}\negui: {}\nOS: {} ({})",
+        env!("CARGO_PKG_VERSION"),
+        GIT_HASH,
+        BEVY_VERSION,
+        EGUI_VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+    )
+}
 
 // ----------------------------------------------------------------------------
 // Core Plugin
@@ -23,9 +41,19 @@ impl Plugin for CorePlugin {
     // Core 不依赖任何东西
     fn dependencies(&self) -> Vec<String> { Vec::new() }
 
-    fn on_file_menu(&mut self, ui: &mut Ui, _control: &mut Vec<AppCommand>) {
+    fn commands(&self) -> Vec<Command> {
+        vec![
+            Command::new("Tab", "Tile All", AppCommand::TileAll),
+            Command::new("Tab", "Reset Layout", AppCommand::ResetLayout),
+            Command::new("Edit", "Settings", AppCommand::ToggleSettings),
+            Command::new("Help", "Source Code", AppCommand::OpenUrl(REPOSITORY_URL.to_string())),
+        ]
+    }
+
+    fn on_file_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
         if ui.button("Quit").clicked() {
-            ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+            control.push(AppCommand::Quit);
+            ui.close();
         }
     }
 
@@ -48,9 +76,12 @@ impl Plugin for CorePlugin {
              }
         });
 
-        if ui.button("About").clicked() {
-            self.show_about = true;
-        }
+        ui.menu_button("Help", |ui| {
+            if ui.button("About Verbium").clicked() {
+                self.show_about = true;
+                ui.close();
+            }
+        });
     }
     
     fn on_settings_ui(&mut self, ui: &mut Ui) {
@@ -58,13 +89,45 @@ impl Plugin for CorePlugin {
         ui.label("Manage global application preferences here.");
     }
 
-    fn on_global_ui(&mut self, ctx: &egui::Context, _control: &mut Vec<AppCommand>) {
+    fn on_global_ui(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
         egui::Window::new("About Verbium")
             .open(&mut self.show_about)
+            .collapsible(false)
+            .resizable(false)
             .show(ctx, |ui| {
                 ui.heading("Verbium");
                 ui.label("A plugin-based extensible editor framework.");
-                ui.label(format!("Version: {}", env!("CARGO_PKG_VERSION")));
+                ui.separator();
+
+                egui::Grid::new("about_build_info").num_columns(2).show(ui, |ui| {
+                    ui.label("Version:");
+                    ui.monospace(env!("CARGO_PKG_VERSION"));
+                    ui.end_row();
+                    ui.label("Commit:");
+                    ui.monospace(GIT_HASH);
+                    ui.end_row();
+                    ui.label("Bevy:");
+                    ui.monospace(BEVY_VERSION);
+                    ui.end_row();
+                    ui.label("egui:");
+                    ui.monospace(EGUI_VERSION);
+                    ui.end_row();
+                });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.link("Source Code").clicked() {
+                        control.push(AppCommand::OpenUrl(REPOSITORY_URL.to_string()));
+                    }
+                    if ui.link("Report an Issue").clicked() {
+                        control.push(AppCommand::OpenUrl(format!("{}/issues", REPOSITORY_URL)));
+                    }
+                });
+
+                if ui.button("📋 Copy diagnostics").clicked() {
+                    control.push(AppCommand::CopyToClipboard(diagnostics()));
+                    control.push(AppCommand::notify("Diagnostics copied to clipboard", NotificationLevel::Info));
+                }
             });
     }
 }
\ No newline at end of file
@@ -1,8 +1,13 @@
 use std::path::{Path, PathBuf};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use egui::{Ui, WidgetText, Id, Color32, RichText};
-use crate::{Plugin, AppCommand, TabInstance, Tab, NotificationLevel};
+use crate::{Plugin, AppCommand, Command, TabInstance, Tab, TabDescriptor, NotificationLevel};
+
+mod quick_open;
+mod watch;
+use quick_open::QuickOpen;
+use watch::TreeWatcher;
 
 // ----------------------------------------------------------------------------
 // Tab Instance
@@ -20,6 +25,13 @@ pub struct FileExplorerTab {
     selected_items: HashSet<PathBuf>,
     /// Queue for commands generated by background threads (e.g., move finished)
     pending_commands: Arc<Mutex<Vec<AppCommand>>>,
+
+    /// Watches `root_path`; started by `ui` whenever the root changes
+    watcher: Option<TreeWatcher>,
+    /// Sorted folder listings, dropped when the watcher reports a change
+    children: HashMap<PathBuf, Vec<PathBuf>>,
+    /// Watcher generation the cached listings belong to
+    children_generation: u64,
 }
 
 impl std::fmt::Debug for FileExplorerTab {
@@ -40,9 +52,33 @@ impl FileExplorerTab {
             input_text: String::new(),
             selected_items: HashSet::new(),
             pending_commands: Arc::new(Mutex::new(Vec::new())),
+            watcher: None,
+            children: HashMap::new(),
+            children_generation: 0,
         }
     }
 
+    /// Restarts the watcher after the root changed
+    fn update_watcher(&mut self, ctx: &egui::Context) {
+        if self.watcher.as_ref().map(TreeWatcher::root) == self.root_path.as_deref() {
+            return;
+        }
+        self.children.clear();
+        self.watcher = self.root_path.as_deref().map(|root| TreeWatcher::start(root, ctx.clone()));
+    }
+
+    /// Entries of `dir`, folders first. Cached while the watcher runs; listed every frame without one
+    fn children(&mut self, dir: &Path) -> Vec<PathBuf> {
+        let Some(generation) = self.watcher.as_ref().and_then(TreeWatcher::generation) else {
+            return list_dir(dir);
+        };
+        if generation != self.children_generation {
+            self.children.clear();
+            self.children_generation = generation;
+        }
+        self.children.entry(dir.to_path_buf()).or_insert_with(|| list_dir(dir)).clone()
+    }
+
     /// Helper to process commands from background threads
     fn process_pending_commands(&self, control: &mut Vec<AppCommand>) {
         if let Ok(mut pending) = self.pending_commands.lock() {
@@ -134,21 +170,8 @@ impl FileExplorerTab {
             }
 
             header_response.body(|ui| {
-                if let Ok(entries) = std::fs::read_dir(&path) {
-                    let mut paths: Vec<_> = entries.flatten().map(|e| e.path()).collect();
-                    paths.sort_by(|a, b| {
-                        let a_is_dir = a.is_dir();
-                        let b_is_dir = b.is_dir();
-                        if a_is_dir != b_is_dir {
-                            b_is_dir.cmp(&a_is_dir)
-                        } else {
-                            a.cmp(b)
-                        }
-                    });
-
-                    for child_path in paths {
-                        self.render_tree(ui, child_path, control);
-                    }
+                for child_path in self.children(&path) {
+                    self.render_tree(ui, child_path, control);
                 }
             });
 
@@ -270,16 +293,10 @@ impl FileExplorerTab {
 
             let mut cmds = pending.lock().unwrap();
             if success_count > 0 {
-                cmds.push(AppCommand::Notify {
-                    message: format!("Moved {} items", success_count),
-                    level: NotificationLevel::Success,
-                });
+                cmds.push(AppCommand::notify(format!("Moved {} items", success_count), NotificationLevel::Success));
             }
             if !errors.is_empty() {
-                cmds.push(AppCommand::Notify {
-                    message: format!("Errors: {}", errors.join(", ")),
-                    level: NotificationLevel::Error,
-                });
+                cmds.push(AppCommand::notify(format!("Errors: {}", errors.join(", ")), NotificationLevel::Error));
             }
             
             ctx_clone.request_repaint();
@@ -340,16 +357,29 @@ impl FileExplorerTab {
             }
             
             if success > 0 {
-                control.push(AppCommand::Notify { 
-                    message: format!("Deleted {} items", success), 
-                    level: NotificationLevel::Success 
-                });
+                control.push(AppCommand::notify(format!("Deleted {} items", success), NotificationLevel::Success));
             }
             ui.close();
         }
     }
 }
 
+/// Sorted entries of `dir`, folders first
+fn list_dir(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut paths: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+    paths.sort_by(|a, b| {
+        let a_is_dir = a.is_dir();
+        let b_is_dir = b.is_dir();
+        if a_is_dir != b_is_dir {
+            b_is_dir.cmp(&a_is_dir)
+        } else {
+            a.cmp(b)
+        }
+    });
+    paths
+}
+
 impl TabInstance for FileExplorerTab {
     fn title(&self) -> WidgetText {
         if let Some(path) = &self.root_path {
@@ -362,12 +392,14 @@ impl TabInstance for FileExplorerTab {
     fn ui(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
         // Poll background commands
         self.process_pending_commands(control);
+        self.update_watcher(ui.ctx());
 
         ui.vertical(|ui| {
             // Toolbar
             ui.horizontal(|ui| {
                 if ui.button("Open Folder...").clicked() {
                     if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                        control.push(AppCommand::OpenWorkspace(path.clone()));
                         self.root_path = Some(path);
                         self.selected_items.clear();
                     }
@@ -375,8 +407,10 @@ impl TabInstance for FileExplorerTab {
                 if self.root_path.is_some() {
                     if ui.button("Refresh").clicked() {
                         self.expanded_nodes.retain(|p| p.exists());
+                        self.children.clear();
                     }
                     if ui.button("Close").clicked() {
+                        control.push(AppCommand::CloseWorkspace);
                         self.root_path = None;
                         self.expanded_nodes.clear();
                         self.selected_items.clear();
@@ -424,16 +458,16 @@ impl TabInstance for FileExplorerTab {
                             match std::fs::rename(&path, new_path) {
                                 Ok(_) => {
                                     self.rename_path = None;
-                                    control.push(AppCommand::Notify { 
-                                        message: "Renamed successfully".into(), 
-                                        level: NotificationLevel::Success 
-                                    });
+                                    control.push(AppCommand::notify(
+                                        "Renamed successfully",
+                                        NotificationLevel::Success,
+                                    ));
                                 }
                                 Err(e) => {
-                                    control.push(AppCommand::Notify { 
-                                        message: format!("Rename failed: {}", e), 
-                                        level: NotificationLevel::Error 
-                                    });
+                                    control.push(AppCommand::notify(
+                                        format!("Rename failed: {}", e),
+                                        NotificationLevel::Error,
+                                    ));
                                 }
                             }
                         }
@@ -468,16 +502,16 @@ impl TabInstance for FileExplorerTab {
                                 Ok(_) => {
                                     self.new_item_parent = None;
                                     self.expanded_nodes.insert(parent);
-                                    control.push(AppCommand::Notify { 
-                                        message: format!("Created {}", if is_dir { "folder" } else { "file" }), 
-                                        level: NotificationLevel::Success 
-                                    });
+                                    control.push(AppCommand::notify(
+                                        format!("Created {}", if is_dir { "folder" } else { "file" }),
+                                        NotificationLevel::Success,
+                                    ));
                                 }
                                 Err(e) => {
-                                    control.push(AppCommand::Notify { 
-                                        message: format!("Creation failed: {}", e), 
-                                        level: NotificationLevel::Error 
-                                    });
+                                    control.push(AppCommand::notify(
+                                        format!("Creation failed: {}", e),
+                                        NotificationLevel::Error,
+                                    ));
                                 }
                             }
                         }
@@ -490,6 +524,19 @@ impl TabInstance for FileExplorerTab {
         }
     }
 
+    fn descriptor(&self) -> Option<TabDescriptor> {
+        let mut state = toml::Table::new();
+        if let Some(root) = &self.root_path {
+            state.insert("root".to_string(), root.to_string_lossy().into_owned().into());
+        }
+        Some(TabDescriptor::new(crate::plugins::PLUGIN_NAME_FILE_MANAGER, state))
+    }
+
+    /// An explorer already rooted at a dropped or opened directory is reused
+    fn matches_path(&self, path: &Path) -> bool {
+        self.root_path.as_deref().is_some_and(|root| crate::same_path(root, path))
+    }
+
     fn box_clone(&self) -> Box<dyn TabInstance> {
         Box::new(self.clone())
     }
@@ -499,13 +546,94 @@ impl TabInstance for FileExplorerTab {
 // Plugin Implementation
 // ----------------------------------------------------------------------------
 
-pub struct FileManagerPlugin;
+#[derive(Default)]
+pub struct FileManagerPlugin {
+    /// Tree of the open workspace, shown in a panel left of the dock area
+    sidebar: Option<FileExplorerTab>,
+    show_sidebar: bool,
+    quick_open: QuickOpen,
+}
 
 impl Plugin for FileManagerPlugin {
     fn name(&self) -> &str {
         crate::plugins::PLUGIN_NAME_FILE_MANAGER
     }
 
+    fn keybindings(&self) -> Vec<crate::keymap::Binding> {
+        use crate::keymap::Binding;
+        vec![Binding::new("file_manager.quick_open", "File Manager", "Quick Open File", egui::Modifiers::COMMAND, egui::Key::P)]
+    }
+
+    fn on_workspace_changed(&mut self, root: Option<&Path>) {
+        self.quick_open.set_root(root);
+        match root {
+            // Keep the expanded folders when the same workspace is reopened
+            Some(root) if self.sidebar.as_ref().is_some_and(|s| s.root_path.as_deref() == Some(root)) => {}
+            Some(root) => {
+                let mut sidebar = FileExplorerTab::new();
+                sidebar.root_path = Some(root.to_path_buf());
+                self.sidebar = Some(sidebar);
+                self.show_sidebar = true;
+            }
+            None => self.sidebar = None,
+        }
+    }
+
+    fn on_file_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        if ui.button("Open Folder...").clicked() {
+            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                control.push(AppCommand::OpenWorkspace(path));
+            }
+            ui.close();
+        }
+        let shortcut = crate::keymap::current(ui.ctx()).get("file_manager.quick_open");
+        let shortcut = shortcut.map(|s| ui.ctx().format_shortcut(&s)).unwrap_or_default();
+        if ui.add(egui::Button::new("Quick Open...").shortcut_text(shortcut)).clicked() {
+            self.quick_open.toggle(ui.ctx(), control);
+            ui.close();
+        }
+        if self.sidebar.is_some() {
+            ui.checkbox(&mut self.show_sidebar, "Folder Sidebar");
+        }
+    }
+
+    fn on_global_ui(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        if crate::keymap::consume(ctx, "file_manager.quick_open") {
+            self.quick_open.toggle(ctx, control);
+        }
+        self.quick_open.ui(ctx, control);
+
+        let Some(sidebar) = self.sidebar.as_mut().filter(|_| self.show_sidebar) else { return };
+        egui::SidePanel::left("workspace_sidebar")
+            .resizable(true)
+            .default_width(220.0)
+            .show(ctx, |ui| sidebar.ui(ui, control));
+    }
+
+    /// Explorers whose root folder has since disappeared come back empty
+    fn restore_tab(&mut self, _ctx: &egui::Context, state: &toml::Table) -> Option<Box<dyn TabInstance>> {
+        let mut tab = FileExplorerTab::new();
+        tab.root_path = state.get("root")
+            .and_then(toml::Value::as_str)
+            .map(PathBuf::from)
+            .filter(|root| root.is_dir());
+        Some(Box::new(tab))
+    }
+
+    /// Directories open as an explorer rooted there (e.g. a folder dropped on the window)
+    fn try_open_file(&mut self, path: &Path) -> Option<Box<dyn TabInstance>> {
+        if !path.is_dir() {
+            return None;
+        }
+        let mut tab = FileExplorerTab::new();
+        tab.root_path = Some(path.to_path_buf());
+        Some(Box::new(tab))
+    }
+
+    fn commands(&self) -> Vec<Command> {
+        vec![Command::new("Tab", "File Explorer", AppCommand::OpenTab(Tab::new(Box::new(FileExplorerTab::new()))))]
+    }
+
     fn on_tab_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
         if ui.button("File Explorer").clicked() {
             control.push(AppCommand::OpenTab(Tab::new(Box::new(FileExplorerTab::new()))));
@@ -515,5 +643,5 @@ impl Plugin for FileManagerPlugin {
 }
 
 pub fn create() -> FileManagerPlugin {
-    FileManagerPlugin
+    FileManagerPlugin::default()
 }
\ No newline at end of file
@@ -0,0 +1,319 @@
+//! Quick Open (Ctrl+P): a fuzzy file finder over the workspace.
+//!
+//! Files are indexed on a background thread and become searchable as they
+//! are found, so large repositories don't block the UI.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use egui::{Key, Modifiers};
+use crate::AppCommand;
+use crate::palette::fuzzy_score;
+
+/// Folders never worth searching, even without a `.gitignore`
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target"];
+/// Rows shown at once; the rest are reachable by typing more
+const MAX_RESULTS: usize = 50;
+const MAX_RECENT: usize = 50;
+/// Files found between two repaints while indexing
+const INDEX_BATCH: usize = 500;
+
+// ----------------------------------------------------------------------------
+// .gitignore
+// ----------------------------------------------------------------------------
+
+/// One pattern of a `.gitignore`. Supports the common subset: `*`, `?`,
+/// `**/`, a leading `/` (anchored), a trailing `/` (folders only) and `!`.
+#[derive(Clone)]
+struct IgnoreRule {
+    /// Folder holding the `.gitignore`; patterns are relative to it
+    base: PathBuf,
+    pattern: String,
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+}
+
+fn parse_gitignore(dir: &Path) -> Vec<IgnoreRule> {
+    let Ok(content) = std::fs::read_to_string(dir.join(".gitignore")) else { return Vec::new() };
+    content.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            // A slash anywhere but the end anchors the pattern to the .gitignore's folder
+            let anchored = line.contains('/');
+            let pattern = line.trim_start_matches('/').to_string();
+            IgnoreRule { base: dir.to_path_buf(), pattern, anchored, dir_only, negated }
+        })
+        .collect()
+}
+
+/// Glob match where `*` and `?` don't cross `/`, and `**` matches anything.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) if rest.first() == Some(&b'*') => {
+            let rest = rest[1..].strip_prefix(b"/").unwrap_or(&rest[1..]);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some((b'*', rest)) => {
+            let segment = text.iter().position(|&c| c == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some((b'?', rest)) => text.first().is_some_and(|&c| c != b'/') && glob_match(rest, &text[1..]),
+        Some((&c, rest)) => text.first() == Some(&c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Whether `path` is ignored; the last matching rule wins, as in git.
+fn is_ignored(rules: &[IgnoreRule], path: &Path, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for rule in rules {
+        if rule.dir_only && !is_dir {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(&rule.base) else { continue };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let matched = if rule.anchored {
+            glob_match(rule.pattern.as_bytes(), relative.as_bytes())
+        } else {
+            let name = relative.rsplit('/').next().unwrap_or(&relative);
+            glob_match(rule.pattern.as_bytes(), name.as_bytes())
+        };
+        if matched {
+            ignored = !rule.negated;
+        }
+    }
+    ignored
+}
+
+// ----------------------------------------------------------------------------
+// Index
+// ----------------------------------------------------------------------------
+
+/// Files found so far by the background walk
+#[derive(Default)]
+struct FileIndex {
+    files: Mutex<Vec<PathBuf>>,
+    done: AtomicBool,
+    /// Set when the index is replaced, so an outdated walk stops early
+    cancelled: AtomicBool,
+}
+
+fn spawn_indexer(root: PathBuf, ctx: egui::Context) -> Arc<FileIndex> {
+    let index = Arc::new(FileIndex::default());
+    let shared = index.clone();
+    std::thread::spawn(move || {
+        let mut batch = Vec::new();
+        // Each folder is walked with the rules of its own and its parents' .gitignore
+        let mut stack = vec![(root.clone(), Arc::new(parse_gitignore(&root)))];
+        while let Some((dir, rules)) = stack.pop() {
+            if shared.cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Ok(file_type) = entry.file_type() else { continue };
+                if file_type.is_dir() {
+                    let name = entry.file_name();
+                    if SKIPPED_DIRS.iter().any(|skipped| name == *skipped) || is_ignored(&rules, &path, true) {
+                        continue;
+                    }
+                    let own = parse_gitignore(&path);
+                    let rules = if own.is_empty() {
+                        rules.clone()
+                    } else {
+                        let mut combined = rules.as_ref().clone();
+                        combined.extend(own);
+                        Arc::new(combined)
+                    };
+                    stack.push((path, rules));
+                } else if file_type.is_file() && !is_ignored(&rules, &path, false) {
+                    batch.push(path);
+                    if batch.len() >= INDEX_BATCH {
+                        shared.files.lock().unwrap().append(&mut batch);
+                        ctx.request_repaint();
+                    }
+                }
+            }
+        }
+        shared.files.lock().unwrap().append(&mut batch);
+        shared.done.store(true, Ordering::Relaxed);
+        ctx.request_repaint();
+    });
+    index
+}
+
+// ----------------------------------------------------------------------------
+// Finder UI
+// ----------------------------------------------------------------------------
+
+#[derive(Default)]
+pub struct QuickOpen {
+    root: Option<PathBuf>,
+    index: Option<Arc<FileIndex>>,
+    open: bool,
+    query: String,
+    selected: usize,
+    focus_input: bool,
+    /// Ranked indices into the index, cached for (query, indexed file count)
+    ranked: Vec<usize>,
+    ranked_for: Option<(String, usize)>,
+    /// Files picked here before, most recent first; they rank higher
+    recent: VecDeque<PathBuf>,
+}
+
+impl QuickOpen {
+    /// Points the finder at a new workspace; indexing starts on first use.
+    pub fn set_root(&mut self, root: Option<&Path>) {
+        if self.root.as_deref() == root {
+            return;
+        }
+        if let Some(index) = self.index.take() {
+            index.cancelled.store(true, Ordering::Relaxed);
+        }
+        self.root = root.map(Path::to_path_buf);
+        self.ranked_for = None;
+        self.open = false;
+    }
+
+    pub fn toggle(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        if self.open {
+            self.open = false;
+            return;
+        }
+        let Some(root) = self.root.clone() else {
+            control.push(AppCommand::notify("Open a folder to search its files", crate::NotificationLevel::Info));
+            return;
+        };
+        // Re-index on every open so new files show up; results stream in as they are found
+        if let Some(index) = self.index.take() {
+            index.cancelled.store(true, Ordering::Relaxed);
+        }
+        self.index = Some(spawn_indexer(root, ctx.clone()));
+        self.ranked_for = None;
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.focus_input = true;
+    }
+
+    fn rank(&mut self, files: &[PathBuf]) {
+        let key = (self.query.clone(), files.len());
+        if self.ranked_for.as_ref() == Some(&key) {
+            return;
+        }
+        let root = self.root.as_deref().unwrap_or(Path::new(""));
+        let mut scored: Vec<(i32, usize)> = files.iter().enumerate()
+            .filter_map(|(i, path)| {
+                let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy();
+                let mut score = fuzzy_score(&self.query, &relative)?;
+                if let Some(rank) = self.recent.iter().position(|recent| recent == path) {
+                    score += 10_000 - rank as i32 * 100;
+                }
+                Some((score, i))
+            })
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        self.ranked = scored.into_iter().take(MAX_RESULTS).map(|(_, i)| i).collect();
+        self.ranked_for = Some(key);
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context, control: &mut Vec<AppCommand>) {
+        if !self.open {
+            return;
+        }
+        let Some(index) = self.index.clone() else { return };
+        // Held while drawing; the indexer only takes it briefly to append a batch
+        let files = index.files.lock().unwrap();
+        self.rank(&files);
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| (
+            i.consume_key(Modifiers::NONE, Key::ArrowUp),
+            i.consume_key(Modifiers::NONE, Key::ArrowDown),
+            i.consume_key(Modifiers::NONE, Key::Enter),
+            i.consume_key(Modifiers::NONE, Key::Escape),
+        ));
+        if down && self.selected + 1 < self.ranked.len() {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(self.ranked.len().saturating_sub(1));
+
+        let root = self.root.clone().unwrap_or_default();
+        let mut chosen = enter.then(|| self.ranked.get(self.selected).map(|&i| files[i].clone())).flatten();
+        egui::Window::new("Quick Open")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .fixed_size([520.0, 0.0])
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Search files by name…")
+                        .desired_width(f32::INFINITY),
+                );
+                if std::mem::take(&mut self.focus_input) {
+                    response.request_focus();
+                }
+                if response.changed() {
+                    self.selected = 0;
+                }
+                if !index.done.load(Ordering::Relaxed) {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.weak(format!("Indexing… {} files", files.len()));
+                    });
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    if self.ranked.is_empty() {
+                        ui.weak("No matching files");
+                    }
+                    for (row, &i) in self.ranked.iter().enumerate() {
+                        let path = &files[i];
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        let folder = path.parent()
+                            .and_then(|parent| parent.strip_prefix(&root).ok())
+                            .map(|parent| parent.to_string_lossy().into_owned())
+                            .unwrap_or_default();
+                        let selected = row == self.selected;
+                        let item = ui.horizontal(|ui| {
+                            let item = ui.selectable_label(selected, name.as_ref());
+                            ui.weak(folder);
+                            item
+                        }).inner;
+                        if selected && (up || down) {
+                            item.scroll_to_me(None);
+                        }
+                        if item.clicked() {
+                            chosen = Some(path.clone());
+                        }
+                    }
+                });
+            });
+
+        if let Some(path) = chosen {
+            self.recent.retain(|recent| *recent != path);
+            self.recent.push_front(path.clone());
+            self.recent.truncate(MAX_RECENT);
+            control.push(AppCommand::OpenFile(path));
+            self.open = false;
+        } else if escape {
+            self.open = false;
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Watches an explorer's root folder so the tree picks up files created, renamed
+//! or deleted outside the explorer.
+
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+pub struct TreeWatcher {
+    root: PathBuf,
+    /// Bumped whenever an entry below the root is added, removed or renamed
+    generation: Arc<AtomicU64>,
+    /// Shared by clones of the explorer; watching stops when the last one is dropped.
+    /// `None` if the watch could not be set up (e.g. the OS watch limit was reached)
+    watcher: Option<Arc<Mutex<RecommendedWatcher>>>,
+}
+
+impl TreeWatcher {
+    pub fn start(root: &Path, ctx: egui::Context) -> Self {
+        let generation = Arc::new(AtomicU64::new(0));
+        let counter = generation.clone();
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            // File contents don't show in the tree; errors may mean missed events, so they count
+            if let Ok(event) = &res {
+                if matches!(event.kind, EventKind::Access(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Metadata(_))) {
+                    return;
+                }
+            }
+            counter.fetch_add(1, Ordering::Relaxed);
+            ctx.request_repaint();
+        })
+        .and_then(|mut watcher| watcher.watch(root, RecursiveMode::Recursive).map(|_| watcher));
+
+        Self {
+            root: root.to_path_buf(),
+            generation,
+            watcher: watcher.ok().map(|w| Arc::new(Mutex::new(w))),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Current change count, or `None` when nothing is being watched and listings can't be cached
+    pub fn generation(&self) -> Option<u64> {
+        self.watcher.as_ref().map(|_| self.generation.load(Ordering::Relaxed))
+    }
+}
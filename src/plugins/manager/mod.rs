@@ -171,11 +171,22 @@ impl LauncherTab {
         }
 
         // 2. 同步并去重外部依赖
+        // 标记之前声明的是核心依赖，插件依赖中的同名项不再重复写入，否则 Cargo 会报重复键
+        let begin_dep = "# --- BEGIN PLUGIN DEPENDENCIES ---";
+        let core_deps: Vec<String> = content
+            .find(begin_dep)
+            .and_then(|idx| content[..idx].parse::<DocumentMut>().ok())
+            .and_then(|core| core.get("dependencies").and_then(|d| d.as_table_like()).map(|t| t.iter().map(|(k, _)| k.to_string()).collect()))
+            .unwrap_or_default();
+
         let mut merged_deps: BTreeMap<String, (toml::Value, Vec<String>)> = BTreeMap::new();
         for plugin in plugins.iter() {
             if plugin.enabled {
                 if let Some(deps) = &plugin.meta.external_dependencies {
                     for (name, val) in deps {
+                        if core_deps.contains(name) {
+                            continue;
+                        }
                         let entry = merged_deps.entry(name.clone()).or_insert_with(|| (val.clone(), Vec::new()));
                         entry.1.push(plugin.id.clone());
                     }
@@ -190,7 +201,6 @@ impl LauncherTab {
         }
 
         let mut final_content = doc.to_string();
-        let begin_dep = "# --- BEGIN PLUGIN DEPENDENCIES ---";
         let end_dep = "# --- END PLUGIN DEPENDENCIES ---";
 
         if let (Some(start_idx), Some(end_idx)) = (final_content.find(begin_dep), final_content.find(end_dep)) {
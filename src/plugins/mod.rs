@@ -2,9 +2,9 @@ pub mod core;
 include!("generated.rs");
 
 use crate::Plugin;
-use std::collections::{HashMap, HashSet};
 
-pub fn all_plugins() -> Vec<Box<dyn Plugin>> {
+/// 按依赖关系排好序的插件，以及排序时发现的问题 (缺失的依赖、循环依赖)
+pub fn all_plugins() -> (Vec<Box<dyn Plugin>>, Vec<String>) {
     let mut raw_plugins: Vec<Box<dyn Plugin>> = vec![
         Box::new(core::CorePlugin::default()),
     ];
@@ -13,56 +13,170 @@ pub fn all_plugins() -> Vec<Box<dyn Plugin>> {
     sort_plugins(raw_plugins)
 }
 
-/// 拓扑排序插件列表，确保依赖项排在前面
-fn sort_plugins(plugins: Vec<Box<dyn Plugin>>) -> Vec<Box<dyn Plugin>> {
-    let mut name_to_plugin: HashMap<String, Box<dyn Plugin>> = plugins
-        .into_iter()
-        .map(|p| (p.name().to_string(), p))
-        .collect();
+/// 拓扑排序插件列表，确保依赖项排在前面；没有依赖关系的插件保持声明顺序。
+/// 有问题的插件仍会被加载：缺失的依赖被忽略；循环中的插件及依赖它们的插件排在最后，
+/// 其中环被任意断开，其余依赖仍排在前面
+fn sort_plugins(plugins: Vec<Box<dyn Plugin>>) -> (Vec<Box<dyn Plugin>>, Vec<String>) {
+    let names: Vec<String> = plugins.iter().map(|p| p.name().to_string()).collect();
+    let dependencies: Vec<Vec<String>> = plugins.iter().map(|p| p.dependencies()).collect();
+    let mut errors = Vec::new();
 
-    let mut sorted_names = Vec::new();
-    let mut visited = HashSet::new();
-    let mut visiting = HashSet::new();
-
-    let names: Vec<String> = name_to_plugin.keys().cloned().collect();
+    for (name, deps) in names.iter().zip(&dependencies) {
+        for dep in deps.iter().filter(|dep| !names.contains(dep)) {
+            errors.push(format!("Plugin \"{}\" depends on \"{}\", which is not installed", name, dep));
+        }
+    }
 
-    for name in names {
-        if !visited.contains(&name) {
-            if !visit(&name, &name_to_plugin, &mut visited, &mut visiting, &mut sorted_names) {
-                // 如果发现循环依赖，这里简单处理：打印警告并继续
-                eprintln!("Warning: Circular dependency or missing dependency detected for plugin: {}", name);
-            }
+    let mut sorter = Sorter {
+        names: &names,
+        dependencies: &dependencies,
+        marks: vec![None; names.len()],
+        path: Vec::new(),
+        order: Vec::new(),
+        cycles: Vec::new(),
+    };
+    for i in 0..names.len() {
+        sorter.visit(i, false);
+    }
+    // 第二遍只处理排序失败的插件，遇到环时跳过回边，保证依赖环上插件的插件排在环之后
+    for i in 0..names.len() {
+        if sorter.marks[i] == Some(Mark::Failed) {
+            sorter.marks[i] = None;
         }
     }
+    for i in 0..names.len() {
+        sorter.visit(i, true);
+    }
+    for cycle in &sorter.cycles {
+        let cycle: Vec<&str> = cycle.iter().map(|&j| names[j].as_str()).collect();
+        errors.push(format!("Circular plugin dependency: {} -> {}", cycle.join(" -> "), cycle[0]));
+    }
+
+    let mut slots: Vec<Option<Box<dyn Plugin>>> = plugins.into_iter().map(Some).collect();
+    let sorted = sorter.order.into_iter().filter_map(|i| slots[i].take()).collect();
+    (sorted, errors)
+}
 
-    sorted_names
-        .into_iter()
-        .filter_map(|name| name_to_plugin.remove(&name))
-        .collect()
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    /// 正在访问 (位于当前路径上)
+    Visiting,
+    /// 已加入 `order`
+    Done,
+    /// 自身在环上或 (间接) 依赖环上的插件，暂不排序
+    Failed,
 }
 
-fn visit(
-    name: &str,
-    registry: &HashMap<String, Box<dyn Plugin>>,
-    visited: &mut HashSet<String>,
-    visiting: &mut HashSet<String>,
-    sorted: &mut Vec<String>,
-) -> bool {
-    if visiting.contains(name) { return false; } // 发现环
-    if visited.contains(name) { return true; }
-
-    visiting.insert(name.to_string());
-
-    if let Some(plugin) = registry.get(name) {
-        for dep in plugin.dependencies() {
-            if !visit(&dep, registry, visited, visiting, sorted) {
-                return false;
+struct Sorter<'a> {
+    names: &'a [String],
+    dependencies: &'a [Vec<String>],
+    marks: Vec<Option<Mark>>,
+    path: Vec<usize>,
+    order: Vec<usize>,
+    /// 发现的环，按依赖方向排列
+    cycles: Vec<Vec<usize>>,
+}
+
+impl Sorter<'_> {
+    /// 深度优先访问第 `i` 个插件，依赖先于插件本身加入 `order`，返回是否已排序。
+    /// `break_cycles` 为假时，在环上或依赖环的插件被标记为失败；为真时忽略指向路径上插件的回边
+    fn visit(&mut self, i: usize, break_cycles: bool) -> bool {
+        match self.marks[i] {
+            Some(Mark::Done) => return true,
+            Some(Mark::Failed) => return false,
+            Some(Mark::Visiting) => {
+                if !break_cycles {
+                    let start = self.path.iter().position(|&j| j == i).unwrap_or(0);
+                    self.cycles.push(self.path[start..].to_vec());
+                }
+                return break_cycles;
+            }
+            None => {}
+        }
+        self.marks[i] = Some(Mark::Visiting);
+        self.path.push(i);
+        let (names, dependencies) = (self.names, self.dependencies);
+        let mut ok = true;
+        for dep in &dependencies[i] {
+            if let Some(j) = names.iter().position(|name| name == dep) {
+                // 不短路：即使已失败也继续访问其余依赖，让它们照常排序
+                ok &= self.visit(j, break_cycles);
             }
         }
+        self.path.pop();
+        if ok {
+            self.marks[i] = Some(Mark::Done);
+            self.order.push(i);
+        } else {
+            self.marks[i] = Some(Mark::Failed);
+        }
+        ok
     }
+}
 
-    visiting.remove(name);
-    visited.insert(name.to_string());
-    sorted.push(name.to_string());
-    true
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestPlugin {
+        name: &'static str,
+        dependencies: &'static [&'static str],
+    }
+
+    impl Plugin for TestPlugin {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn dependencies(&self) -> Vec<String> {
+            self.dependencies.iter().map(|d| d.to_string()).collect()
+        }
+    }
+
+    fn sorted(plugins: &[(&'static str, &'static [&'static str])]) -> (Vec<String>, Vec<String>) {
+        let plugins = plugins
+            .iter()
+            .map(|&(name, dependencies)| Box::new(TestPlugin { name, dependencies }) as Box<dyn Plugin>)
+            .collect();
+        let (sorted, errors) = sort_plugins(plugins);
+        (sorted.iter().map(|p| p.name().to_string()).collect(), errors)
+    }
+
+    fn position(order: &[String], name: &str) -> usize {
+        order.iter().position(|n| n == name).unwrap()
+    }
+
+    #[test]
+    fn dependencies_come_first_and_independent_plugins_keep_their_order() {
+        let (order, errors) = sorted(&[("a", &["c"]), ("b", &[]), ("c", &[])]);
+        assert_eq!(order, ["c", "a", "b"]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn missing_dependencies_are_reported_and_ignored() {
+        let (order, errors) = sorted(&[("a", &["missing"]), ("b", &[])]);
+        assert_eq!(order, ["a", "b"]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("missing"));
+    }
+
+    #[test]
+    fn dependents_of_a_cycle_are_ordered_after_it() {
+        // "d" 在声明与访问顺序上都先于环，但必须排在环上的 "a" 之后
+        let (order, errors) = sorted(&[
+            ("d", &["a"]),
+            ("e", &["d", "f"]),
+            ("a", &["b"]),
+            ("b", &["a"]),
+            ("f", &[]),
+        ]);
+        assert_eq!(order.len(), 5);
+        assert!(position(&order, "a") < position(&order, "d"));
+        assert!(position(&order, "d") < position(&order, "e"));
+        assert!(position(&order, "f") < position(&order, "e"));
+        // 与环无关的插件照常排在前面
+        assert_eq!(order[0], "f");
+        assert_eq!(errors, ["Circular plugin dependency: a -> b -> a"]);
+    }
+}
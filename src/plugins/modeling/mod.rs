@@ -3,10 +3,184 @@ use bevy::pbr::Material;
 use bevy::render::render_resource::AsBindGroup;
 use bevy::shader::ShaderRef;
 use bevy::light::PointLight;
-use bevy::camera::Viewport;
+use bevy::pbr::wireframe::Wireframe;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::camera::{RenderTarget, Viewport};
+use bevy::image::BevyDefault;
+use bevy::render::render_resource::{TextureFormat, TextureUsages};
+use bevy::camera::visibility::RenderLayers;
+use bevy::color::palettes::css::{BLUE, LIME, RED};
 use bevy_egui::EguiContexts;
-use egui::{Ui, WidgetText, UiKind, Id, Rect};
-use crate::{Plugin, TabInstance, AppCommand, Tab};
+use egui::{Ui, WidgetText, UiKind, Id, Rect, PointerButton};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use crate::{Plugin, TabInstance, AppCommand, Command, Tab, TabDescriptor, NotificationLevel};
+use crate::app::CommandQueue;
+
+static NEXT_VIEWPORT_KEY: AtomicU64 = AtomicU64::new(1);
+/// 已关闭的模型标签页的 key，由 `sync_modeling_viewport` 取走并销毁对应相机。
+/// `on_close` 拿不到 egui 上下文，因此不放在 egui 临时数据中
+static CLOSED_VIEWPORTS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+/// SDF 材质使用的着色器
+const SDF_SHADER_PATH: &str = "plugins/modeling/sdf.wgsl";
+
+/// 本帧各模型标签页上报的可见视口: 标签页 key -> (可见区域, 新建相机时使用的轨道状态)
+fn viewports_id() -> Id {
+    Id::new("sdf_viewport_rects")
+}
+
+/// 各相机的最新状态，由 Bevy 系统写入供标签页显示: 标签页 key -> 相机信息
+fn camera_info_id() -> Id {
+    Id::new("sdf_camera_info")
+}
+
+/// 标签页请求重置相机的 key 集合
+fn camera_reset_id() -> Id {
+    Id::new("sdf_camera_reset")
+}
+
+/// 视口上累计的鼠标操作，由 Bevy 系统取走: 标签页 key -> 输入
+fn camera_input_id() -> Id {
+    Id::new("sdf_camera_input")
+}
+
+/// SDF 模型当前的材质参数，由 Bevy 系统写入供标签页显示
+fn material_id() -> Id {
+    Id::new("sdf_material")
+}
+
+/// 标签页修改后的材质参数，由 Bevy 系统取走并写入材质资源
+fn material_edit_id() -> Id {
+    Id::new("sdf_material_edit")
+}
+
+/// 标签页请求添加的物体，由 Bevy 系统在原点创建
+fn add_object_id() -> Id {
+    Id::new("sdf_add_object")
+}
+
+/// File 菜单发出的场景保存 / 打开请求，由 Bevy 系统处理
+fn scene_request_id() -> Id {
+    Id::new("sdf_scene_request")
+}
+
+/// 视口是否以线框显示，由标签页修改、Bevy 系统读取
+fn wireframe_id() -> Id {
+    Id::new("sdf_wireframe")
+}
+
+/// 地面网格与坐标轴的显示参数，由标签页修改、Bevy 系统读取
+fn grid_id() -> Id {
+    Id::new("sdf_grid")
+}
+
+/// 标签页请求截取视口: (标签页 key, 保存路径)
+fn capture_request_id() -> Id {
+    Id::new("sdf_capture_request")
+}
+
+#[derive(Debug, Clone)]
+enum SceneRequest {
+    Save(PathBuf),
+    Open(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CameraInfo {
+    position: Vec3,
+    /// 垂直视场角 (度)
+    fov_degrees: f32,
+    orbit: OrbitCamera,
+}
+
+/// 轨道相机：从 `target` 出发按偏航 / 俯仰角旋转，后退 `distance` 处观察目标
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct OrbitCamera {
+    pub target: Vec3,
+    /// 绕 Y 轴的角度 (弧度)，0 表示位于目标的 +Z 方向
+    pub yaw: f32,
+    /// 仰角 (弧度)，正值表示从上方俯视
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+impl Default for OrbitCamera {
+    /// 位于 (0, 5, 10) 看向原点
+    fn default() -> Self {
+        Self { target: Vec3::ZERO, yaw: 0.0, pitch: 0.5f32.atan(), distance: 125f32.sqrt() }
+    }
+}
+
+/// 旋转与平移的灵敏度 (每像素)
+const ORBIT_SPEED: f32 = 0.01;
+const PAN_SPEED: f32 = 0.0015;
+/// 滚轮每像素缩放的比例
+const ZOOM_SPEED: f32 = 0.0015;
+const MIN_DISTANCE: f32 = 0.5;
+const MAX_DISTANCE: f32 = 500.0;
+
+impl OrbitCamera {
+    fn rotation(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, -self.pitch, 0.0)
+    }
+
+    fn transform(&self) -> Transform {
+        let position = self.target + self.rotation() * Vec3::Z * self.distance;
+        Transform::from_translation(position).looking_at(self.target, Vec3::Y)
+    }
+
+    fn apply(&mut self, input: &CameraInput) {
+        // 俯仰角不越过正上方 / 正下方，否则 looking_at 的上方向会翻转
+        let limit = std::f32::consts::FRAC_PI_2 - 0.01;
+        self.yaw -= input.orbit.x * ORBIT_SPEED;
+        self.pitch = (self.pitch + input.orbit.y * ORBIT_SPEED).clamp(-limit, limit);
+
+        // 平移量随距离缩放，使画面中的物体跟随鼠标移动
+        let rotation = self.rotation();
+        let scale = self.distance * PAN_SPEED;
+        self.target += (rotation * Vec3::NEG_X * input.pan.x + rotation * Vec3::Y * input.pan.y) * scale;
+
+        self.distance = (self.distance * (-input.zoom * ZOOM_SPEED).exp()).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    /// 会话中保存的状态
+    fn to_table(self) -> toml::Table {
+        let mut table = toml::Table::new();
+        let target = [self.target.x, self.target.y, self.target.z].map(|v| toml::Value::Float(v as f64));
+        table.insert("target".to_string(), toml::Value::Array(target.to_vec()));
+        table.insert("yaw".to_string(), (self.yaw as f64).into());
+        table.insert("pitch".to_string(), (self.pitch as f64).into());
+        table.insert("distance".to_string(), (self.distance as f64).into());
+        table
+    }
+
+    fn from_table(table: &toml::Table) -> Option<Self> {
+        let float = |key: &str| table.get(key)?.as_float().map(|v| v as f32);
+        let target: Vec<f32> = table.get("target")?.as_array()?.iter().filter_map(|v| v.as_float()).map(|v| v as f32).collect();
+        let &[x, y, z] = target.as_slice() else { return None };
+        Some(Self {
+            target: Vec3::new(x, y, z),
+            yaw: float("yaw")?,
+            pitch: float("pitch")?,
+            distance: float("distance")?.clamp(MIN_DISTANCE, MAX_DISTANCE),
+        })
+    }
+}
+
+/// 一帧内视口上的鼠标操作 (egui 逻辑像素)
+#[derive(Debug, Clone, Copy, Default)]
+struct CameraInput {
+    /// 左键拖动：旋转
+    orbit: Vec2,
+    /// 中键拖动：平移
+    pan: Vec2,
+    /// 滚轮：推近 / 拉远
+    zoom: f32,
+}
 
 // --- Bevy Material ---
 
@@ -16,11 +190,163 @@ pub struct SdfMaterial {
     pub color: LinearRgba,
     #[uniform(0)]
     pub time: f32,
+    /// 自发光强度，按 `color` 叠加
+    #[uniform(0)]
+    pub emission: f32,
+    /// 0 为光滑 (高光集中)，1 为完全粗糙 (没有高光)
+    #[uniform(0)]
+    pub roughness: f32,
+    /// 着色器中的形状，见 `Primitive::shader_id`
+    #[uniform(0)]
+    pub primitive: u32,
+    /// 物体变换的逆矩阵，着色器在物体空间中光线步进；由 `update_sdf_time` 随变换更新
+    #[uniform(0)]
+    pub local_from_world: Mat4,
+}
+
+/// 可在标签页中编辑的材质参数
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialParams {
+    pub color: [f32; 3],
+    pub emission: f32,
+    pub roughness: f32,
+    /// 动画速度倍率
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
+        Self { color: [0.2, 0.7, 1.0], emission: 0.0, roughness: 0.5, speed: 1.0, paused: false }
+    }
+}
+
+/// SDF 物体的形状。着色器在物体空间中计算距离，包围网格在形状外留出余量，着色器从网格表面开始光线步进
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Primitive {
+    Cube,
+    Sphere,
+    Torus,
+    /// 有限大小的薄平板
+    Plane,
+}
+
+impl Primitive {
+    pub const ALL: [Primitive; 4] = [Primitive::Cube, Primitive::Sphere, Primitive::Torus, Primitive::Plane];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Primitive::Cube => "Cube",
+            Primitive::Sphere => "Sphere",
+            Primitive::Torus => "Torus",
+            Primitive::Plane => "Plane",
+        }
+    }
+
+    /// 与 `sdf.wgsl` 中 `map` 的分支一致
+    fn shader_id(self) -> u32 {
+        match self {
+            Primitive::Cube => 0,
+            Primitive::Sphere => 1,
+            Primitive::Torus => 2,
+            Primitive::Plane => 3,
+        }
+    }
+
+    /// 包围网格：比着色器中的形状 (边长 2 的立方体、半径 1 的球、环半径 1 / 管半径 0.35 的圆环、
+    /// 2.5 × 2.5 的平板) 大 0.1，容纳表面的波纹动画
+    fn bounding_mesh(self) -> Mesh {
+        match self {
+            Primitive::Cube => Cuboid::from_size(Vec3::splat(2.2)).into(),
+            Primitive::Sphere => Sphere::new(1.1).into(),
+            Primitive::Torus => Torus::new(0.55, 1.45).into(),
+            Primitive::Plane => Cuboid::new(2.7, 0.24, 2.7).into(),
+        }
+    }
+}
+
+/// 场景中的一个 SDF 物体，各自持有一份 `SdfMaterial`
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SdfObject {
+    pub primitive: Primitive,
+    pub params: MaterialParams,
+    /// 按速度倍率累计的动画时间，暂停时不增加
+    pub time: f32,
+}
+
+/// 模型视口的显示方式，由 `apply_render_mode` 从标签页同步
+#[derive(Resource, Default)]
+pub struct ModelingView {
+    /// 叠加显示网格的线框 (默认关闭)。只有模型相机渲染 3D 网格，不影响 egui 界面
+    pub wireframe: bool,
+    pub grid: GridParams,
+}
+
+/// 地面网格 (XZ 平面) 与世界坐标轴
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridParams {
+    pub show_grid: bool,
+    /// 网格线的间距 (世界单位)
+    pub spacing: f32,
+    pub show_axes: bool,
+}
+
+impl Default for GridParams {
+    fn default() -> Self {
+        Self { show_grid: true, spacing: 1.0, show_axes: true }
+    }
+}
+
+/// 模型视口的辅助线只画在这一渲染层上，主窗口的 2D 相机看不到它们
+const GIZMO_LAYER: usize = 1;
+/// 坐标轴的长度 (世界单位)
+const AXIS_LENGTH: f32 = 2.0;
+/// 地面网格最多的格数 (每个方向)
+const MAX_GRID_CELLS: u32 = 400;
+
+/// 模型视口的辅助线 (地面网格、坐标轴)
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct ModelingGizmos;
+
+/// SDF 场景的状态
+#[derive(Resource, Default)]
+pub struct SdfModel {
+    /// 材质面板编辑的物体
+    pub selected: Option<Entity>,
+}
+
+/// 场景文件 (JSON)
+#[derive(Debug, Serialize, Deserialize)]
+struct SceneFile {
+    objects: Vec<SceneObject>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SceneObject {
+    primitive: Primitive,
+    translation: [f32; 3],
+    /// 四元数 (x, y, z, w)
+    rotation: [f32; 4],
+    scale: [f32; 3],
+    #[serde(default)]
+    material: MaterialParams,
+}
+
+impl SceneObject {
+    fn transform(&self) -> Transform {
+        Transform {
+            translation: Vec3::from_array(self.translation),
+            rotation: Quat::from_array(self.rotation).normalize(),
+            scale: Vec3::from_array(self.scale),
+        }
+    }
 }
 
 impl Material for SdfMaterial {
     fn fragment_shader() -> ShaderRef {
-        "plugins/modeling/sdf.wgsl".into()
+        SDF_SHADER_PATH.into()
     }
 }
 
@@ -36,6 +362,37 @@ pub fn create() -> ModelingPlugin {
 impl Plugin for ModelingPlugin {
     fn name(&self) -> &str { "modeling" }
 
+    fn restore_tab(&mut self, _ctx: &egui::Context, state: &toml::Table) -> Option<Box<dyn TabInstance>> {
+        let mut tab = ModelingTab::default();
+        if let Some(orbit) = state.get("camera").and_then(|v| v.as_table()).and_then(OrbitCamera::from_table) {
+            tab.orbit = orbit;
+        }
+        Some(Box::new(tab))
+    }
+
+    fn commands(&self) -> Vec<Command> {
+        vec![Command::new("Tab", "SDF Base Model", AppCommand::OpenTab(Tab::new(Box::new(ModelingTab::default()))))]
+    }
+
+    fn on_file_menu(&mut self, ui: &mut Ui, _control: &mut Vec<AppCommand>) {
+        let mut request = None;
+        if ui.button("Open Scene...").clicked() {
+            ui.close();
+            request = rfd::FileDialog::new().add_filter("SDF Scene", &["json"]).pick_file().map(SceneRequest::Open);
+        }
+        if ui.button("Save Scene...").clicked() {
+            ui.close();
+            request = rfd::FileDialog::new()
+                .add_filter("SDF Scene", &["json"])
+                .set_file_name("scene.json")
+                .save_file()
+                .map(SceneRequest::Save);
+        }
+        if let Some(request) = request {
+            ui.ctx().data_mut(|d| d.insert_temp(scene_request_id(), request));
+        }
+    }
+
     fn on_tab_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
         if ui.button("SDF Base Model").clicked() {
             let tab = Tab::new(Box::new(ModelingTab::default()));
@@ -47,57 +404,257 @@ impl Plugin for ModelingPlugin {
 
 // --- Tab Implementation ---
 
-#[derive(Debug, Clone, Default)]
-pub struct ModelingTab;
+#[derive(Debug, Clone)]
+pub struct ModelingTab {
+    /// 与该标签页对应的 `ModelingCamera` 的 key
+    key: u64,
+    /// 相机的轨道状态：从 Bevy 系统同步回来，用于保存会话与重建相机
+    orbit: OrbitCamera,
+}
+
+impl Default for ModelingTab {
+    fn default() -> Self {
+        Self { key: NEXT_VIEWPORT_KEY.fetch_add(1, Ordering::Relaxed), orbit: OrbitCamera::default() }
+    }
+}
 
 impl TabInstance for ModelingTab {
     fn title(&self) -> WidgetText { "SDF Modeler".into() }
 
     fn ui(&mut self, ui: &mut Ui, _control: &mut Vec<AppCommand>) {
         ui.vertical(|ui| {
-            ui.heading("SDF Viewport");
-            ui.label("The 3D view is accurately synced to this area.");
+            self.info_ui(ui);
+            ui.horizontal(|ui| {
+                ui.menu_button("➕ Add", |ui| {
+                    for primitive in Primitive::ALL {
+                        if ui.button(primitive.label()).clicked() {
+                            ui.ctx().data_mut(|d| d.insert_temp(add_object_id(), primitive));
+                            ui.close();
+                        }
+                    }
+                });
+                ui.weak("New objects appear at the origin and are selected for editing");
+            });
             ui.separator();
+            self.material_ui(ui);
 
-            // 1. 获取扣除标题后的剩余可用区域
+            // 1. 获取扣除信息栏后的剩余可用区域
             let rect = ui.available_rect_before_wrap();
             
-            // 2. 在 egui 中占位，防止其他组件侵入
-            ui.allocate_rect(rect, egui::Sense::hover());
+            // 2. 在 egui 中占位，防止其他组件侵入；拖动与滚轮用于控制相机
+            let response = ui.allocate_rect(rect, egui::Sense::click_and_drag());
+            self.camera_input(ui, &response);
 
-            // 3. 将精确的矩形区域传递给 Bevy
-            ui.ctx().data_mut(|d| {
-                d.insert_temp(Id::new("sdf_viewport_rect"), rect);
-                d.insert_temp(Id::new("sdf_viewport_active"), true);
-            });
+            // 3. 只把实际可见的部分传递给 Bevy (被相邻面板遮挡或滚出的部分会被裁掉)
+            //    egui_dock 只为各叶子中当前激活的标签页调用 ui，因此标签页切到后台后不会再写入
+            report_visible(ui.ctx(), self.key, rect.intersect(ui.clip_rect()), self.orbit);
         });
     }
 
+    fn on_close(&mut self) {
+        report_closed(self.key);
+    }
+
+    /// 相机由每个视口自行创建，只需保存它的轨道状态
+    fn descriptor(&self) -> Option<TabDescriptor> {
+        let mut state = toml::Table::new();
+        state.insert("camera".to_string(), toml::Value::Table(self.orbit.to_table()));
+        Some(TabDescriptor::new(crate::plugins::PLUGIN_NAME_MODELING, state))
+    }
+
     fn box_clone(&self) -> Box<dyn TabInstance> { Box::new(self.clone()) }
 }
 
+impl ModelingTab {
+    /// 只有视口本身被拖动或悬停时才控制相机，拖动从其他控件开始时不会影响相机
+    fn camera_input(&self, ui: &Ui, response: &egui::Response) {
+        let drag = response.drag_delta();
+        let mut input = CameraInput::default();
+        if response.dragged_by(PointerButton::Primary) {
+            input.orbit = Vec2::new(drag.x, drag.y);
+        }
+        if response.dragged_by(PointerButton::Middle) {
+            input.pan = Vec2::new(drag.x, drag.y);
+        }
+        if response.hovered() {
+            input.zoom = ui.input(|i| i.smooth_scroll_delta.y);
+        }
+        if input.orbit == Vec2::ZERO && input.pan == Vec2::ZERO && input.zoom == 0.0 {
+            return;
+        }
+        // 累加：Bevy 系统取走之前可能已绘制了多帧
+        ui.ctx().data_mut(|d| {
+            let pending = d.get_temp_mut_or_default::<HashMap<u64, CameraInput>>(camera_input_id()).entry(self.key).or_default();
+            pending.orbit += input.orbit;
+            pending.pan += input.pan;
+            pending.zoom += input.zoom;
+        });
+    }
+
+    /// 可折叠的信息栏：相机位置、视场角与着色器路径 (均可选中复制)
+    fn info_ui(&mut self, ui: &mut Ui) {
+        let info = ui.ctx().data(|d| {
+            d.get_temp::<HashMap<u64, CameraInfo>>(camera_info_id()).and_then(|m| m.get(&self.key).copied())
+        });
+        if let Some(info) = info {
+            self.orbit = info.orbit;
+        }
+        egui::CollapsingHeader::new("SDF Viewport")
+            .id_salt(("sdf_viewport_info", self.key))
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new(("sdf_viewport_info_grid", self.key)).num_columns(2).show(ui, |ui| {
+                    let mut row = |ui: &mut Ui, name: &str, value: String| {
+                        ui.label(egui::RichText::new(name).weak());
+                        ui.add(egui::Label::new(egui::RichText::new(value).monospace()).selectable(true));
+                        ui.end_row();
+                    };
+                    match info {
+                        Some(info) => {
+                            let p = info.position;
+                            row(ui, "Camera", format!("{:.2}, {:.2}, {:.2}", p.x, p.y, p.z));
+                            row(ui, "FOV", format!("{:.1}°", info.fov_degrees));
+                            row(ui, "Distance", format!("{:.2}", info.orbit.distance));
+                        }
+                        None => row(ui, "Camera", "-".to_string()),
+                    }
+                    row(ui, "Shader", SDF_SHADER_PATH.to_string());
+                });
+                ui.weak("Left-drag to orbit, middle-drag to pan, scroll to zoom");
+                ui.horizontal(|ui| {
+                    if ui.small_button("Reset Camera").clicked() {
+                        ui.ctx().data_mut(|d| {
+                            d.get_temp_mut_or_default::<HashSet<u64>>(camera_reset_id()).insert(self.key);
+                        });
+                    }
+                    if ui.small_button("📷 Capture").on_hover_text("Save the viewport as a PNG image").clicked() {
+                        let path = rfd::FileDialog::new()
+                            .add_filter("PNG Image", &["png"])
+                            .set_file_name("viewport.png")
+                            .save_file();
+                        if let Some(path) = path {
+                            ui.ctx().data_mut(|d| d.insert_temp(capture_request_id(), (self.key, path)));
+                        }
+                    }
+                    let mut wireframe = ui.ctx().data(|d| d.get_temp::<bool>(wireframe_id())).unwrap_or(false);
+                    if ui.checkbox(&mut wireframe, "Wireframe").changed() {
+                        ui.ctx().data_mut(|d| d.insert_temp(wireframe_id(), wireframe));
+                    }
+                });
+                let mut grid = ui.ctx().data(|d| d.get_temp::<GridParams>(grid_id())).unwrap_or_default();
+                let before = grid;
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut grid.show_grid, "Grid");
+                    ui.add_enabled(
+                        grid.show_grid,
+                        egui::DragValue::new(&mut grid.spacing).range(0.05..=100.0).speed(0.05).prefix("spacing "),
+                    );
+                    ui.checkbox(&mut grid.show_axes, "Axes");
+                });
+                if grid != before {
+                    ui.ctx().data_mut(|d| d.insert_temp(grid_id(), grid));
+                }
+            });
+        ui.separator();
+    }
+
+    /// 材质面板：修改后交给 Bevy 系统写入 `SdfMaterial`，所有视口同时更新
+    fn material_ui(&self, ui: &mut Ui) {
+        let Some(current) = ui.ctx().data(|d| d.get_temp::<MaterialParams>(material_id())) else { return };
+        // 本帧其他标签页已修改但尚未应用时，以修改后的值为准
+        let mut params = ui.ctx().data(|d| d.get_temp::<MaterialParams>(material_edit_id())).unwrap_or(current);
+        let before = params;
+        egui::CollapsingHeader::new("Material")
+            .id_salt(("sdf_material", self.key))
+            .default_open(false)
+            .show(ui, |ui| {
+                egui::Grid::new(("sdf_material_grid", self.key)).num_columns(2).show(ui, |ui| {
+                    ui.label("Color");
+                    ui.color_edit_button_rgb(&mut params.color);
+                    ui.end_row();
+                    ui.label("Emission");
+                    ui.add(egui::Slider::new(&mut params.emission, 0.0..=5.0));
+                    ui.end_row();
+                    ui.label("Roughness");
+                    ui.add(egui::Slider::new(&mut params.roughness, 0.0..=1.0));
+                    ui.end_row();
+                    ui.label("Animation");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Slider::new(&mut params.speed, 0.0..=5.0).suffix("×"));
+                        let label = if params.paused { "▶ Resume" } else { "⏸ Pause" };
+                        if ui.button(label).clicked() {
+                            params.paused = !params.paused;
+                        }
+                    });
+                    ui.end_row();
+                });
+                if ui.small_button("Reset Material").clicked() {
+                    params = MaterialParams::default();
+                }
+            });
+        if params != before {
+            ui.ctx().data_mut(|d| d.insert_temp(material_edit_id(), params));
+        }
+        ui.separator();
+    }
+}
+
+/// 上报标签页本帧的可见区域
+fn report_visible(ctx: &egui::Context, key: u64, rect: Rect, orbit: OrbitCamera) {
+    ctx.data_mut(|d| {
+        d.get_temp_mut_or_default::<HashMap<u64, (Rect, OrbitCamera)>>(viewports_id()).insert(key, (rect, orbit));
+    });
+}
+
+/// 上报标签页已关闭
+fn report_closed(key: u64) {
+    if let Ok(mut closed) = CLOSED_VIEWPORTS.lock() {
+        closed.push(key);
+    }
+}
+
+/// 一帧内标签页上报给 `sync_modeling_viewport` 的内容
+#[derive(Default)]
+struct ViewportReports {
+    /// 本帧绘制过且未关闭的标签页: key -> (可见区域, 轨道状态)
+    visible: HashMap<u64, (Rect, OrbitCamera)>,
+    /// 请求重置相机的标签页
+    resets: HashSet<u64>,
+}
+
+impl ViewportReports {
+    /// 读取并清除本帧的上报。同一帧内先绘制后关闭的标签页不算可见
+    fn take(ctx: &egui::Context) -> Self {
+        let (mut visible, resets) = ctx.data_mut(|d| {
+            let v = d.get_temp::<HashMap<u64, (Rect, OrbitCamera)>>(viewports_id()).unwrap_or_default();
+            d.remove::<HashMap<u64, (Rect, OrbitCamera)>>(viewports_id());
+            let r = d.get_temp::<HashSet<u64>>(camera_reset_id()).unwrap_or_default();
+            d.remove::<HashSet<u64>>(camera_reset_id());
+            (v, r)
+        });
+        if let Ok(mut closed) = CLOSED_VIEWPORTS.lock() {
+            for key in closed.drain(..) {
+                visible.remove(&key);
+            }
+        }
+        Self { visible, resets }
+    }
+}
+
 // --- Bevy Systems ---
 
+/// 每个模型标签页对应一个相机，首次可见时创建
 #[derive(Component)]
-pub struct ModelingCamera;
+pub struct ModelingCamera(pub u64);
 
 pub fn setup_modeling_scene(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<SdfMaterial>>,
     mut std_materials: ResMut<Assets<StandardMaterial>>,
+    mut gizmo_config: ResMut<GizmoConfigStore>,
 ) {
-    // 3D 相机
-    commands.spawn((
-        Camera3d::default(),
-        Camera {
-            order: 1,
-            is_active: false,
-            ..default()
-        },
-        ModelingCamera,
-        Transform::from_xyz(0.0, 5.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
-    ));
+    gizmo_config.config_mut::<ModelingGizmos>().0.render_layers = RenderLayers::layer(GIZMO_LAYER);
 
     // 测试立方体
     commands.spawn((
@@ -110,14 +667,15 @@ pub fn setup_modeling_scene(
         Transform::from_xyz(-1.5, 0.0, 0.0),
     ));
 
-    commands.spawn((
-        Mesh3d(meshes.add(Cuboid::from_size(Vec3::splat(3.0)))),
-        MeshMaterial3d(materials.add(SdfMaterial {
-            color: LinearRgba::rgb(0.2, 0.7, 1.0),
-            time: 0.0,
-        })),
-        Transform::from_xyz(1.5, 0.0, 0.0),
-    ));
+    let object = SceneObject {
+        primitive: Primitive::Cube,
+        translation: [1.5, 0.0, 0.0],
+        rotation: Quat::IDENTITY.to_array(),
+        scale: [1.0; 3],
+        material: MaterialParams::default(),
+    };
+    let selected = spawn_sdf_object(&mut commands, &mut meshes, &mut materials, &object);
+    commands.insert_resource(SdfModel { selected: Some(selected) });
 
     commands.spawn((
         PointLight {
@@ -129,63 +687,402 @@ pub fn setup_modeling_scene(
     ));
 }
 
+/// 将 egui 逻辑坐标下的可见区域转换为相机视口，区域过小时返回 None
+fn physical_viewport(rect: Rect, window: &Window) -> Option<Viewport> {
+    if !rect.is_positive() {
+        return None;
+    }
+    // Bevy 0.17 Viewport.physical_position 原点是左上角 (Top-Left)，与 egui 一致
+    // 视口超出渲染目标会导致 Bevy 报错，因此再裁剪到窗口范围内
+    let scale_factor = window.scale_factor();
+    let window_size = UVec2::new(window.physical_width(), window.physical_height());
+    let min = UVec2::new((rect.min.x * scale_factor) as u32, (rect.min.y * scale_factor) as u32).min(window_size);
+    let max = UVec2::new((rect.max.x * scale_factor) as u32, (rect.max.y * scale_factor) as u32).min(window_size);
+    let size = max.saturating_sub(min);
+
+    (size.x > 1 && size.y > 1).then(|| Viewport {
+        physical_position: min,
+        physical_size: size,
+        depth: 0.0..1.0,
+    })
+}
+
+/// 把标签页上报的鼠标操作应用到对应的轨道相机
+pub fn control_modeling_camera(
+    mut contexts: EguiContexts,
+    mut query: Query<(&ModelingCamera, &mut OrbitCamera, &mut Transform)>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let inputs = ctx.data_mut(|d| d.remove_temp::<HashMap<u64, CameraInput>>(camera_input_id()).unwrap_or_default());
+    if inputs.is_empty() {
+        return;
+    }
+    for (ModelingCamera(key), mut orbit, mut transform) in query.iter_mut() {
+        let Some(input) = inputs.get(key) else { continue };
+        orbit.apply(input);
+        *transform = orbit.transform();
+    }
+}
+
 pub fn sync_modeling_viewport(
+    mut commands: Commands,
     mut contexts: EguiContexts,
-    mut query: Query<(&mut Camera, &mut Projection), With<ModelingCamera>>,
+    mut query: Query<(Entity, &ModelingCamera, &mut Camera, &mut Projection, &mut OrbitCamera, &mut Transform)>,
     window_query: Query<&Window>,
 ) {
     let Ok(ctx) = contexts.ctx_mut() else { return };
     let Some(window) = window_query.iter().next() else { return };
-    let scale_factor = window.scale_factor();
 
-    let mut camera_active = false;
-    
-    let (rect, active) = ctx.data_mut(|d| {
-        let r = d.get_temp::<Rect>(Id::new("sdf_viewport_rect"));
-        let a = d.get_temp::<bool>(Id::new("sdf_viewport_active")).unwrap_or(false);
-        (r, a)
-    });
+    // 只有本帧绘制过的标签页 (各叶子中激活的标签页) 会出现在上报中。
+    // 其余标签页 (切到后台或已关闭) 的相机直接销毁，避免过期的区域让错误的相机继续渲染；
+    // 标签页保存着轨道状态，再次可见时按它重建相机
+    let ViewportReports { mut visible, resets } = ViewportReports::take(ctx);
 
-    if let (Some(rect), true) = (rect, active) {
-        if let Some((mut camera, mut projection)) = query.iter_mut().next() {
-            // --- 修正后的坐标计算 ---
-            // Bevy 0.17 Viewport.physical_position 原点是左上角 (Top-Left)
-            // egui 的 rect.min 也是左上角偏移
-            let min_x = (rect.min.x * scale_factor) as u32;
-            let min_y = (rect.min.y * scale_factor) as u32; // 直接使用 min.y
-            let width = (rect.width() * scale_factor) as u32;
-            let height = (rect.height() * scale_factor) as u32;
-
-            if width > 1 && height > 1 {
-                camera.viewport = Some(Viewport {
-                    physical_position: UVec2::new(min_x, min_y),
-                    physical_size: UVec2::new(width, height),
-                    depth: 0.0..1.0,
-                });
-                camera.is_active = true;
-                camera_active = true;
-                
+    let mut infos = HashMap::new();
+    for (entity, ModelingCamera(key), mut camera, mut projection, mut orbit, mut transform) in query.iter_mut() {
+        if !visible.contains_key(key) {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if resets.contains(key) {
+            *orbit = OrbitCamera::default();
+            *transform = orbit.transform();
+            if let Projection::Perspective(ref mut p) = *projection {
+                p.fov = PerspectiveProjection::default().fov;
+            }
+        }
+        if let Projection::Perspective(ref p) = *projection {
+            infos.insert(*key, CameraInfo { position: transform.translation, fov_degrees: p.fov.to_degrees(), orbit: *orbit });
+        }
+
+        match visible.remove(key).and_then(|(rect, _)| physical_viewport(rect, window)) {
+            Some(viewport) => {
                 if let Projection::Perspective(ref mut p) = *projection {
-                    p.aspect_ratio = width as f32 / height as f32;
+                    p.aspect_ratio = viewport.physical_size.x as f32 / viewport.physical_size.y as f32;
                 }
+                camera.viewport = Some(viewport);
+                camera.is_active = true;
             }
+            None => camera.is_active = false,
+        }
+    }
+
+    // 剩下的是还没有相机的标签页，按标签页保存的轨道状态创建 (会话恢复后保持原来的视角)
+    for (key, (rect, orbit)) in visible {
+        let Some(viewport) = physical_viewport(rect, window) else { continue };
+        let aspect_ratio = viewport.physical_size.x as f32 / viewport.physical_size.y as f32;
+        commands.spawn((
+            Camera3d::default(),
+            Camera {
+                order: 1 + key as isize,
+                viewport: Some(viewport),
+                ..default()
+            },
+            Projection::Perspective(PerspectiveProjection { aspect_ratio, ..default() }),
+            // 默认层上的网格加上只有模型相机能看到的辅助线层
+            RenderLayers::from_layers(&[0, GIZMO_LAYER]),
+            ModelingCamera(key),
+            orbit,
+            orbit.transform(),
+        ));
+    }
+
+    ctx.data_mut(|d| d.insert_temp(camera_info_id(), infos));
+}
+
+/// 绘制地面网格与坐标轴。网格以各相机的观察目标为中心、范围随相机距离增大，看起来没有边界
+pub fn draw_modeling_gizmos(
+    view: Res<ModelingView>,
+    cameras: Query<(&Camera, &OrbitCamera), With<ModelingCamera>>,
+    mut gizmos: Gizmos<ModelingGizmos>,
+) {
+    let grid = view.grid;
+    if grid.show_grid {
+        let spacing = grid.spacing.max(0.01);
+        for (_, orbit) in cameras.iter().filter(|(camera, _)| camera.is_active) {
+            // 中心对齐到网格线上，格数取偶数，使网格线始终落在世界坐标的整数倍处
+            let center = (orbit.target / spacing).round() * spacing;
+            let cells = ((orbit.distance * 8.0 / spacing).ceil() as u32).clamp(10, MAX_GRID_CELLS);
+            gizmos.grid(
+                Isometry3d::new(Vec3::new(center.x, 0.0, center.z), Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)),
+                UVec2::splat(cells + cells % 2),
+                Vec2::splat(spacing),
+                Color::srgba(0.5, 0.5, 0.5, 0.35),
+            );
         }
     }
+    if grid.show_axes {
+        gizmos.line(Vec3::ZERO, Vec3::X * AXIS_LENGTH, RED);
+        gizmos.line(Vec3::ZERO, Vec3::Y * AXIS_LENGTH, LIME);
+        gizmos.line(Vec3::ZERO, Vec3::Z * AXIS_LENGTH, BLUE);
+    }
+}
 
-    if !camera_active {
-        for (mut camera, _) in query.iter_mut() {
-            camera.is_active = false;
+/// 读取标签页的线框开关与网格参数写入 `ModelingView`，并为场景中的网格 (包括之后打开的场景) 添加或移除 `Wireframe`
+pub fn apply_render_mode(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut view: ResMut<ModelingView>,
+    meshes: Query<(Entity, Has<Wireframe>), With<Mesh3d>>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let (wireframe, grid) = ctx.data(|d| {
+        (d.get_temp::<bool>(wireframe_id()).unwrap_or(false), d.get_temp::<GridParams>(grid_id()).unwrap_or_default())
+    });
+    if view.wireframe != wireframe || view.grid != grid {
+        view.wireframe = wireframe;
+        view.grid = grid;
+    }
+    for (entity, has_wireframe) in meshes.iter() {
+        match (view.wireframe, has_wireframe) {
+            (true, false) => { commands.entity(entity).insert(Wireframe); }
+            (false, true) => { commands.entity(entity).remove::<Wireframe>(); }
+            _ => {}
         }
     }
-    
-    ctx.data_mut(|d| d.insert_temp(Id::new("sdf_viewport_active"), false));
 }
 
+/// 按请求截图的标签页的相机参数，把场景渲染到一张与视口同尺寸的离屏图像并保存为 PNG。
+/// 不截取窗口画面，因此覆盖在视口上的 egui 弹窗、通知等不会出现在截图中
+pub fn capture_modeling_viewport(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut queue: ResMut<CommandQueue>,
+    mut images: ResMut<Assets<Image>>,
+    cameras: Query<(&ModelingCamera, &Camera, &Projection, &Transform, &RenderLayers)>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let Some((key, path)) = ctx.data_mut(|d| d.remove_temp::<(u64, PathBuf)>(capture_request_id())) else { return };
+    let source = cameras.iter()
+        .find(|(ModelingCamera(k), camera, ..)| *k == key && camera.is_active)
+        .and_then(|(_, camera, projection, transform, layers)| {
+            Some((camera.viewport.as_ref()?.physical_size, projection.clone(), *transform, layers.clone()))
+        });
+    let Some((size, projection, transform, layers)) = source else {
+        queue.queue.push(AppCommand::notify("Capture failed: the viewport is not visible", NotificationLevel::Error));
+        return;
+    };
+
+    let mut image = Image::new_target_texture(size.x, size.y, TextureFormat::bevy_default());
+    // 截图需要从纹理复制数据
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    let image = images.add(image);
+    // 只渲染这一帧的相机，截图完成后销毁
+    let capture_camera = commands.spawn((
+        Camera3d::default(),
+        Camera {
+            order: -1,
+            target: RenderTarget::Image(image.clone().into()),
+            ..default()
+        },
+        projection,
+        transform,
+        layers,
+    )).id();
+
+    commands.spawn(Screenshot::image(image)).observe(
+        move |captured: On<ScreenshotCaptured>, mut commands: Commands, mut queue: ResMut<CommandQueue>| {
+            commands.entity(capture_camera).despawn();
+            let result = captured.image.clone().try_into_dynamic()
+                .map_err(|e| e.to_string())
+                .and_then(|image| {
+                    // 画面不透明，去掉 alpha 通道
+                    image.to_rgb8()
+                        .save_with_format(&path, image::ImageFormat::Png)
+                        .map_err(|e| e.to_string())
+                });
+            let (message, level) = match result {
+                Ok(()) => (format!("Saved viewport capture to {}", path.display()), NotificationLevel::Success),
+                Err(e) => (format!("Capture failed: {}", e), NotificationLevel::Error),
+            };
+            queue.queue.push(AppCommand::notify(message, level));
+        },
+    );
+}
+
+fn sdf_material(primitive: Primitive, params: &MaterialParams, time: f32, transform: &Transform) -> SdfMaterial {
+    let [r, g, b] = params.color;
+    SdfMaterial {
+        color: LinearRgba::rgb(r, g, b),
+        time,
+        emission: params.emission,
+        roughness: params.roughness,
+        primitive: primitive.shader_id(),
+        local_from_world: transform.to_matrix().inverse(),
+    }
+}
+
+fn spawn_sdf_object(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<SdfMaterial>,
+    object: &SceneObject,
+) -> Entity {
+    let transform = object.transform();
+    commands.spawn((
+        Mesh3d(meshes.add(object.primitive.bounding_mesh())),
+        MeshMaterial3d(materials.add(sdf_material(object.primitive, &object.material, 0.0, &transform))),
+        transform,
+        SdfObject { primitive: object.primitive, params: object.material, time: 0.0 },
+    )).id()
+}
+
+/// 推进动画时间，并在物体移动后更新材质中的逆变换
 pub fn update_sdf_time(
     time: Res<Time>,
+    mut objects: Query<(&mut SdfObject, &MeshMaterial3d<SdfMaterial>, Ref<GlobalTransform>)>,
+    mut materials: ResMut<Assets<SdfMaterial>>,
+) {
+    for (mut object, handle, transform) in objects.iter_mut() {
+        let animate = !object.params.paused;
+        if !animate && !transform.is_changed() {
+            continue;
+        }
+        if animate {
+            object.time += time.delta_secs() * object.params.speed;
+        }
+        if let Some(material) = materials.get_mut(&handle.0) {
+            material.time = object.time;
+            if transform.is_changed() {
+                material.local_from_world = transform.to_matrix().inverse();
+            }
+        }
+    }
+}
+
+/// 应用标签页修改的材质参数，并把选中物体的当前参数发布给标签页
+pub fn edit_sdf_material(
+    mut contexts: EguiContexts,
+    model: Res<SdfModel>,
+    mut objects: Query<(&mut SdfObject, &MeshMaterial3d<SdfMaterial>)>,
+    mut materials: ResMut<Assets<SdfMaterial>>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let edit = ctx.data_mut(|d| d.remove_temp::<MaterialParams>(material_edit_id()));
+    let Some(Ok((mut object, handle))) = model.selected.map(|entity| objects.get_mut(entity)) else {
+        ctx.data_mut(|d| d.remove::<MaterialParams>(material_id()));
+        return;
+    };
+    if let Some(params) = edit {
+        object.params = params;
+        if let Some(material) = materials.get_mut(&handle.0) {
+            let [r, g, b] = params.color;
+            material.color = LinearRgba::rgb(r, g, b);
+            material.emission = params.emission;
+            material.roughness = params.roughness;
+        }
+    }
+    let params = object.params;
+    ctx.data_mut(|d| d.insert_temp(material_id(), params));
+}
+
+/// 在原点添加标签页请求的物体 (默认变换与材质)，并选中它以便在材质面板中编辑
+pub fn add_sdf_object(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<SdfMaterial>>,
+    mut model: ResMut<SdfModel>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let Some(primitive) = ctx.data_mut(|d| d.remove_temp::<Primitive>(add_object_id())) else { return };
+    let object = SceneObject {
+        primitive,
+        translation: [0.0; 3],
+        rotation: Quat::IDENTITY.to_array(),
+        scale: [1.0; 3],
+        material: MaterialParams::default(),
+    };
+    model.selected = Some(spawn_sdf_object(&mut commands, &mut meshes, &mut materials, &object));
+}
+
+/// 处理 File 菜单的保存 / 打开场景请求。打开时替换场景中所有的 SDF 物体
+pub fn sdf_scene_io(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<SdfMaterial>>,
+    mut model: ResMut<SdfModel>,
+    mut queue: ResMut<CommandQueue>,
+    objects: Query<(Entity, &SdfObject, &Transform)>,
 ) {
-    for (_, material) in materials.iter_mut() {
-        material.time = time.elapsed_secs();
+    let Ok(ctx) = contexts.ctx_mut() else { return };
+    let Some(request) = ctx.data_mut(|d| d.remove_temp::<SceneRequest>(scene_request_id())) else { return };
+
+    let (message, level) = match request {
+        SceneRequest::Save(path) => {
+            let scene = SceneFile {
+                objects: objects.iter().map(|(_, object, transform)| SceneObject {
+                    primitive: object.primitive,
+                    translation: transform.translation.to_array(),
+                    rotation: transform.rotation.to_array(),
+                    scale: transform.scale.to_array(),
+                    material: object.params,
+                }).collect(),
+            };
+            let result = serde_json::to_string_pretty(&scene)
+                .map_err(|e| e.to_string())
+                .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()));
+            match result {
+                Ok(()) => (format!("Saved scene to {}", path.display()), NotificationLevel::Success),
+                Err(e) => (format!("Failed to save scene: {}", e), NotificationLevel::Error),
+            }
+        }
+        SceneRequest::Open(path) => {
+            let result = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str::<SceneFile>(&json).map_err(|e| e.to_string()));
+            match result {
+                Ok(scene) => {
+                    for (entity, _, _) in objects.iter() {
+                        commands.entity(entity).despawn();
+                    }
+                    let spawned: Vec<Entity> = scene.objects.iter()
+                        .map(|object| spawn_sdf_object(&mut commands, &mut meshes, &mut materials, object))
+                        .collect();
+                    model.selected = spawned.first().copied();
+                    (format!("Opened scene {} ({} objects)", path.display(), spawned.len()), NotificationLevel::Success)
+                }
+                Err(e) => (format!("Failed to open scene: {}", e), NotificationLevel::Error),
+            }
+        }
+    };
+    queue.queue.push(AppCommand::notify(message, level));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_key() -> u64 {
+        NEXT_VIEWPORT_KEY.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 上报只在下一次读取前有效；关闭的标签页即使本帧绘制过也不再可见
+    #[test]
+    fn viewport_report_lifecycle() {
+        let ctx = egui::Context::default();
+        let rect = Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(100.0, 80.0));
+        let (shown, hidden) = (new_key(), new_key());
+
+        report_visible(&ctx, shown, rect, OrbitCamera::default());
+        report_visible(&ctx, hidden, rect, OrbitCamera::default());
+        let reports = ViewportReports::take(&ctx);
+        assert_eq!(reports.visible.get(&shown).map(|(r, _)| *r), Some(rect));
+        assert!(reports.visible.contains_key(&hidden));
+
+        // 下一帧只有 `shown` 被绘制，`hidden` 的相机应被清理
+        report_visible(&ctx, shown, rect, OrbitCamera::default());
+        let reports = ViewportReports::take(&ctx);
+        assert!(reports.visible.contains_key(&shown));
+        assert!(!reports.visible.contains_key(&hidden));
+
+        // 没有任何绘制的帧
+        assert!(ViewportReports::take(&ctx).visible.is_empty());
+
+        // 同一帧内绘制后关闭
+        report_visible(&ctx, shown, rect, OrbitCamera::default());
+        ModelingTab { key: shown, orbit: OrbitCamera::default() }.on_close();
+        assert!(!ViewportReports::take(&ctx).visible.contains_key(&shown));
     }
 }
@@ -1,65 +1,233 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
 use std::io::{Write, Read};
+use std::time::{Duration, Instant};
 use parking_lot::Mutex;
-use portable_pty::{native_pty_system, CommandBuilder, PtySize, MasterPty};
+use portable_pty::{native_pty_system, Child, ChildKiller, CommandBuilder, PtySize, MasterPty};
 use vte::{Parser, Perform};
 use egui::{Ui, WidgetText, Color32, FontId, Rect, Vec2, Key, Sense};
-use egui::text::{LayoutJob, TextFormat};
-use crate::{Tab, Plugin, AppCommand, TabInstance};
+use egui::text::{LayoutJob, LayoutSection, TextFormat};
+use crate::{Tab, Plugin, AppCommand, TabInstance, TabDescriptor, TabStatus, NotificationLevel};
+use crate::config::PluginConfig;
+
+mod theme;
+
+use theme::{TermColor, TerminalTheme};
 
 // ----------------------------------------------------------------------------
-// Constants & Colors
+// Constants
 // ----------------------------------------------------------------------------
 
-const TERM_BG: Color32 = Color32::from_rgb(15, 15, 15);
-const TERM_FG: Color32 = Color32::from_rgb(210, 210, 210);
+const DEFAULT_FONT_SIZE: f32 = 14.0;
+const FONT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 6.0..=48.0;
+
+/// Maximum number of scrollback rows kept above the screen
+const HISTORY_LIMIT: usize = 5000;
+
+/// Underline styles from SGR `4:n` (and `4`/`21`/`24`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+enum Underline {
+    #[default]
+    None,
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl Underline {
+    /// Style for the `n` in `4:n`; unknown styles fall back to a single line.
+    fn from_subparam(n: u16) -> Self {
+        match n {
+            0 => Underline::None,
+            2 => Underline::Double,
+            3 => Underline::Curly,
+            4 => Underline::Dotted,
+            5 => Underline::Dashed,
+            _ => Underline::Single,
+        }
+    }
+}
+
+/// Mouse tracking requested by the program with DECSET 1000/1002/1003.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MouseMode {
+    Off,
+    /// 1000: button presses and releases.
+    Press,
+    /// 1002: also motion while a button is held.
+    Drag,
+    /// 1003: all motion.
+    Any,
+}
+
+/// Cursor shape selected with DECSCUSR (`CSI Ps SP q`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CursorShape {
+    Block,
+    Underline,
+    Bar,
+}
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 struct Cell {
     c: char,
-    fg: Color32,
-    bg: Color32,
+    /// Zero-width mark (combining accent, variation selector, …) printed after `c`.
+    /// Only the first one is kept.
+    combining: Option<char>,
+    fg: TermColor,
+    bg: TermColor,
     bold: bool,
+    /// SGR 2: faint text.
+    dim: bool,
     italic: bool,
-    underline: bool,
+    underline: Underline,
+    /// Set by SGR 58; `None` draws the underline in the text color.
+    underline_color: Option<TermColor>,
     inverse: bool,
     is_wide_continuation: bool,
+    /// Set on a row's last cell when the line soft-wrapped onto the next row
+    wraps: bool,
+    /// OSC 8 hyperlink, as an index into `TerminalState::links`.
+    link: Option<u32>,
+}
+
+impl Cell {
+    /// Background color to paint, with inverse video applied; `None` shows the
+    /// terminal background.
+    fn background(&self, theme: &TerminalTheme) -> Option<Color32> {
+        if self.inverse {
+            Some(theme.resolve(self.fg, theme.foreground))
+        } else if self.bg == TermColor::Default {
+            None
+        } else {
+            Some(theme.resolve(self.bg, theme.background))
+        }
+    }
+
+    /// Text color to paint, with inverse video applied.
+    fn foreground(&self, theme: &TerminalTheme) -> Color32 {
+        let (color, default) = if self.inverse { (self.bg, theme.background) } else { (self.fg, theme.foreground) };
+        // Like most terminals, bold also selects the bright variant of the 8 standard colors
+        let color = match color {
+            TermColor::Indexed(n @ 0..=7) if self.bold => TermColor::Indexed(n + 8),
+            color => color,
+        };
+        let fg = theme.resolve(color, default);
+        if self.dim { fg.gamma_multiply(0.5) } else { fg }
+    }
+
+    /// The cell's text: its character followed by any combining mark.
+    fn text(&self) -> String {
+        self.combining.map_or_else(|| self.c.to_string(), |mark| format!("{}{}", self.c, mark))
+    }
+
+    /// Blank cell padding the end of a row whose next character was too wide to fit.
+    /// It is marked as a continuation so it isn't copied, and dropped when reflow joins the rows again.
+    fn wide_spacer() -> Self {
+        Cell { is_wide_continuation: true, wraps: true, ..Cell::default() }
+    }
+
+    fn underline_color(&self, theme: &TerminalTheme) -> Color32 {
+        match self.underline_color {
+            Some(color) => theme.resolve(color, theme.foreground),
+            None => self.foreground(theme),
+        }
+    }
 }
 
 impl Default for Cell {
     fn default() -> Self {
         Self {
             c: ' ',
-            fg: TERM_FG,
-            bg: Color32::TRANSPARENT,
+            combining: None,
+            fg: TermColor::Default,
+            bg: TermColor::Default,
             bold: false,
+            dim: false,
             italic: false,
-            underline: false,
+            underline: Underline::None,
+            underline_color: None,
             inverse: false,
-            is_wide_continuation: false 
+            is_wide_continuation: false,
+            wraps: false,
+            link: None,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Settings
+// ----------------------------------------------------------------------------
+
+/// What happens when a program rings the bell (BEL).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BellMode {
+    /// Briefly flash the terminal.
+    Visual,
+    /// Ring the bell of the console Verbium was started from.
+    Audible,
+    None,
+}
+
+impl BellMode {
+    const ALL: [BellMode; 3] = [BellMode::Visual, BellMode::Audible, BellMode::None];
+
+    fn label(self) -> &'static str {
+        match self {
+            BellMode::Visual => "Visual",
+            BellMode::Audible => "Audible",
+            BellMode::None => "None",
         }
     }
+
+    fn from_config(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.label().eq_ignore_ascii_case(value))
+    }
+}
+
+/// Terminal preferences, shared between the plugin and every terminal tab.
+#[derive(Debug, Clone)]
+pub struct TerminalSettings {
+    /// Ask before sending a paste that contains newlines to the shell.
+    pub confirm_multiline_paste: bool,
+    /// Set by "Don't ask again this session"; not persisted.
+    pub skip_paste_confirmation: bool,
+    /// Program for interactive terminals; empty uses the platform default.
+    /// Commands started through `AppCommand::Run` always use the default shell.
+    pub shell: String,
+    /// Arguments for `shell`, separated by whitespace.
+    pub shell_args: String,
+    /// Starting directory of new terminals that don't ask for one; empty inherits ours.
+    pub working_dir: String,
+    /// Extra environment variables for every terminal.
+    pub env: Vec<(String, String)>,
+    /// Expose the screen contents to assistive technology even when egui
+    /// hasn't detected a screen reader.
+    pub accessibility_text: bool,
+    /// Make plain `http(s)://` text clickable, not only OSC 8 hyperlinks.
+    pub detect_urls: bool,
+    pub bell: BellMode,
+    /// Colors of every terminal; tabs read it each frame, so edits apply live.
+    pub theme: TerminalTheme,
 }
 
-fn ansi_color(code: u8) -> Color32 {
-    match code {
-        0 => Color32::from_rgb(0, 0, 0),        // Black
-        1 => Color32::from_rgb(205, 0, 0),      // Red
-        2 => Color32::from_rgb(0, 205, 0),      // Green
-        3 => Color32::from_rgb(205, 205, 0),    // Yellow
-        4 => Color32::from_rgb(0, 0, 238),      // Blue
-        5 => Color32::from_rgb(205, 0, 205),    // Magenta
-        6 => Color32::from_rgb(0, 205, 205),    // Cyan
-        7 => Color32::from_rgb(229, 229, 229),  // White
-        8 => Color32::from_rgb(127, 127, 127),  // Bright Black
-        9 => Color32::from_rgb(255, 0, 0),      // Bright Red
-        10 => Color32::from_rgb(0, 255, 0),     // Bright Green
-        11 => Color32::from_rgb(255, 255, 0),   // Bright Yellow
-        12 => Color32::from_rgb(92, 92, 255),   // Bright Blue
-        13 => Color32::from_rgb(255, 0, 255),   // Bright Magenta
-        14 => Color32::from_rgb(0, 255, 255),   // Bright Cyan
-        15 => Color32::from_rgb(255, 255, 255), // Bright White
-        _ => TERM_FG,
+impl Default for TerminalSettings {
+    fn default() -> Self {
+        Self {
+            confirm_multiline_paste: true,
+            skip_paste_confirmation: false,
+            shell: String::new(),
+            shell_args: String::new(),
+            working_dir: String::new(),
+            env: Vec::new(),
+            accessibility_text: false,
+            detect_urls: true,
+            bell: BellMode::Visual,
+            theme: TerminalTheme::default(),
+        }
     }
 }
 
@@ -67,32 +235,91 @@ fn ansi_color(code: u8) -> Color32 {
 // Terminal State
 // ----------------------------------------------------------------------------
 
+/// Cursor position and text attributes saved by DECSC (`ESC 7`) and restored by DECRC (`ESC 8`).
+#[derive(Clone, Copy)]
+struct SavedCursor {
+    row: usize,
+    col: usize,
+    fg: TermColor,
+    bg: TermColor,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: Underline,
+    underline_color: Option<TermColor>,
+    inverse: bool,
+}
+
+impl Default for SavedCursor {
+    fn default() -> Self {
+        Self {
+            row: 0,
+            col: 0,
+            fg: TermColor::Default,
+            bg: TermColor::Default,
+            bold: false,
+            dim: false,
+            italic: false,
+            underline: Underline::None,
+            underline_color: None,
+            inverse: false,
+        }
+    }
+}
+
 struct TerminalState {
     rows: usize,
     cols: usize,
     cursor_row: usize,
     cursor_col: usize,
-    saved_cursor: (usize, usize),
+    saved_cursor: SavedCursor,
     
     primary_grid: Vec<Vec<Cell>>,
     alt_grid: Vec<Vec<Cell>>,
     history: Vec<Vec<Cell>>,
     is_alt_screen: bool,
     
-    current_fg: Color32,
-    current_bg: Color32,
+    current_fg: TermColor,
+    current_bg: TermColor,
     current_bold: bool,
+    current_dim: bool,
     current_italic: bool,
-    current_underline: bool,
+    current_underline: Underline,
+    current_underline_color: Option<TermColor>,
     current_inverse: bool,
     
     cursor_visible: bool,
+    cursor_shape: CursorShape,
+    cursor_blink: bool,
     application_cursor: bool,
+    /// DEC private mode 2004: wrap pastes in `ESC [200~` … `ESC [201~`.
+    bracketed_paste: bool,
+    mouse_mode: MouseMode,
+    /// DEC private mode 1006: SGR-encoded mouse reports.
+    mouse_sgr: bool,
+    /// DEC private mode 1004: send `ESC [I` / `ESC [O` on focus changes.
+    focus_reporting: bool,
+    /// Last printed graphic character, repeated by REP (`CSI Ps b`).
+    last_printed: Option<char>,
+    /// Window title set by the program via OSC 0 / OSC 2.
+    title: Option<String>,
+    /// URLs of OSC 8 hyperlinks, referenced by `Cell::link`.
+    links: Vec<String>,
+    /// Hyperlink stamped onto printed cells until the program closes it.
+    current_link: Option<u32>,
+    /// Set when the program rang the bell; taken by the tab on its next frame.
+    bell_pending: Option<Instant>,
+    /// Set by the reader thread when the PTY reached end of file, i.e. the shell exited.
+    exited: Option<Instant>,
+    /// The exit has been reported; holds the exit code when it could be read.
+    exit_reported: Option<Option<u32>>,
 
     scroll_top: usize,
     scroll_bottom: usize,
     
     dirty: bool,
+    /// Incremented on every change to the screen contents.
+    revision: u64,
 }
 
 impl TerminalState {
@@ -102,23 +329,81 @@ impl TerminalState {
             cols,
             cursor_row: 0,
             cursor_col: 0,
-            saved_cursor: (0, 0),
+            saved_cursor: SavedCursor::default(),
             primary_grid: vec![vec![Cell::default(); cols]; rows],
             alt_grid: vec![vec![Cell::default(); cols]; rows],
             history: Vec::new(),
             is_alt_screen: false,
-            current_fg: TERM_FG,
-            current_bg: Color32::TRANSPARENT,
+            current_fg: TermColor::Default,
+            current_bg: TermColor::Default,
             current_bold: false,
+            current_dim: false,
             current_italic: false,
-            current_underline: false,
+            current_underline: Underline::None,
+            current_underline_color: None,
             current_inverse: false,
             cursor_visible: true,
+            cursor_shape: CursorShape::Block,
+            cursor_blink: false,
             application_cursor: false,
+            bracketed_paste: false,
+            mouse_mode: MouseMode::Off,
+            mouse_sgr: false,
+            focus_reporting: false,
+            last_printed: None,
+            title: None,
+            links: Vec::new(),
+            current_link: None,
+            bell_pending: None,
+            exited: None,
+            exit_reported: None,
             scroll_top: 0,
             scroll_bottom: rows.saturating_sub(1),
             dirty: true,
+            revision: 0,
+        }
+    }
+
+    /// Index of `url` in the link table, adding it if needed. Programs usually
+    /// reopen the same link for every line of it, so look from the end.
+    fn link_id(&mut self, url: &str) -> u32 {
+        match self.links.iter().rposition(|link| link == url) {
+            Some(i) => i as u32,
+            None => {
+                self.links.push(url.to_string());
+                (self.links.len() - 1) as u32
+            }
+        }
+    }
+
+    /// Cell left behind by erase operations: blank, but keeping the current background color.
+    fn blank_cell(&self) -> Cell {
+        Cell { bg: self.current_bg, ..Cell::default() }
+    }
+
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    /// Visible screen as plain text (trailing blanks trimmed) and the cursor's
+    /// character offset within it, for the accessibility layer.
+    fn screen_text(&self) -> (String, usize) {
+        let mut text = String::new();
+        let mut cursor = 0;
+        for (row_idx, row) in self.grid().iter().enumerate() {
+            if row_idx > 0 {
+                text.push('\n');
+            }
+            let line: String = row.iter().filter(|cell| !cell.is_wide_continuation).map(|cell| cell.c).collect();
+            let line = line.trim_end();
+            if row_idx == self.cursor_row {
+                let line_len = line.chars().count();
+                cursor = text.chars().count() + self.cursor_col.min(line_len);
+            }
+            text.push_str(line);
         }
+        (text, cursor)
     }
 
     fn grid_mut(&mut self) -> &mut Vec<Vec<Cell>> {
@@ -143,42 +428,180 @@ impl TerminalState {
             grid.push(vec![Cell::default(); c]);
             if !is_alt {
                 self.history.push(old_row);
-                if self.history.len() > 5000 { self.history.remove(0); }
+                if self.history.len() > HISTORY_LIMIT { self.history.remove(0); }
             }
         } else {
             grid.remove(top);
             grid.insert(bottom, vec![Cell::default(); c]);
         }
-        self.dirty = true;
+        self.mark_dirty();
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = SavedCursor {
+            row: self.cursor_row,
+            col: self.cursor_col,
+            fg: self.current_fg,
+            bg: self.current_bg,
+            bold: self.current_bold,
+            dim: self.current_dim,
+            italic: self.current_italic,
+            underline: self.current_underline,
+            underline_color: self.current_underline_color,
+            inverse: self.current_inverse,
+        };
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.saved_cursor;
+        self.cursor_row = saved.row.min(self.rows - 1);
+        self.cursor_col = saved.col.min(self.cols - 1);
+        self.current_fg = saved.fg;
+        self.current_bg = saved.bg;
+        self.current_bold = saved.bold;
+        self.current_dim = saved.dim;
+        self.current_italic = saved.italic;
+        self.current_underline = saved.underline;
+        self.current_underline_color = saved.underline_color;
+        self.current_inverse = saved.inverse;
+    }
+
+    /// Moves the cursor down a row. On the bottom margin the scroll region scrolls
+    /// instead; below the region the cursor just stops at the last row.
+    fn line_feed(&mut self) {
+        if self.cursor_row == self.scroll_bottom {
+            self.scroll_up();
+        } else if self.cursor_row + 1 < self.rows {
+            self.cursor_row += 1;
+        }
     }
 
     fn resize(&mut self, new_rows: usize, new_cols: usize) {
         if new_rows == 0 || new_cols == 0 { return; }
         if new_rows == self.rows && new_cols == self.cols { return; }
 
-        let resize_one = |grid: &mut Vec<Vec<Cell>>| {
-            grid.truncate(new_rows);
-            while grid.len() < new_rows {
-                grid.push(vec![Cell::default(); new_cols]);
-            }
-            for row in grid.iter_mut() {
-                row.truncate(new_cols);
-                while row.len() < new_cols {
-                    row.push(Cell::default());
-                }
-            }
-        };
+        // The primary screen and its scrollback are reflowed. While the alternate screen is
+        // active the primary cursor lives in `saved_cursor` (see mode 1049).
+        let cursor = if self.is_alt_screen { (self.saved_cursor.row, self.saved_cursor.col) } else { (self.cursor_row, self.cursor_col) };
+        let cursor = (self.history.len() + cursor.0.min(self.rows - 1), cursor.1);
+        let mut rows = std::mem::take(&mut self.history);
+        rows.append(&mut self.primary_grid);
+        let (mut rows, (cursor_row, cursor_col)) = reflow_rows(rows, new_cols, cursor);
 
-        resize_one(&mut self.primary_grid);
-        resize_one(&mut self.alt_grid);
+        // Show as much content above the cursor as fits, keeping the cursor row on screen
+        let start = rows.len().saturating_sub(new_rows).min(cursor_row);
+        let mut screen = rows.split_off(start);
+        screen.truncate(new_rows);
+        screen.resize(new_rows, vec![Cell::default(); new_cols]);
+        if rows.len() > HISTORY_LIMIT {
+            rows.drain(..rows.len() - HISTORY_LIMIT);
+        }
+        self.history = rows;
+        self.primary_grid = screen;
+        let primary_cursor = (cursor_row - start, cursor_col.min(new_cols - 1));
+
+        // Full-screen programs redraw the alternate screen after SIGWINCH, so it is only cropped/padded
+        self.alt_grid.truncate(new_rows);
+        self.alt_grid.resize(new_rows, vec![Cell::default(); new_cols]);
+        for row in self.alt_grid.iter_mut() {
+            row.resize(new_cols, Cell::default());
+        }
 
         self.rows = new_rows;
         self.cols = new_cols;
         self.scroll_top = 0;
         self.scroll_bottom = new_rows.saturating_sub(1);
-        self.cursor_row = self.cursor_row.min(new_rows - 1);
-        self.cursor_col = self.cursor_col.min(new_cols - 1);
-        self.dirty = true;
+        if self.is_alt_screen {
+            (self.saved_cursor.row, self.saved_cursor.col) = primary_cursor;
+            self.cursor_row = self.cursor_row.min(new_rows - 1);
+            self.cursor_col = self.cursor_col.min(new_cols - 1);
+        } else {
+            (self.cursor_row, self.cursor_col) = primary_cursor;
+        }
+        self.mark_dirty();
+    }
+}
+
+/// Re-wraps rows (oldest first) to `new_cols`. Soft-wrapped rows are joined back into logical
+/// lines and split again at the new width, so growing pulls continuation text back up and
+/// shrinking pushes overflow onto new wrapped rows instead of truncating it.
+/// Returns the new rows and the new position of the cell at `cursor` (row index, column).
+fn reflow_rows(rows: Vec<Vec<Cell>>, new_cols: usize, cursor: (usize, usize)) -> (Vec<Vec<Cell>>, (usize, usize)) {
+    // Join soft-wrapped rows into logical lines, remembering where the cursor falls
+    let mut lines: Vec<Vec<Cell>> = Vec::new();
+    let mut current: Vec<Cell> = Vec::new();
+    let mut cursor_at = (0, 0);
+    for (r, mut row) in rows.into_iter().enumerate() {
+        let wraps = row.last().is_some_and(|c| c.wraps);
+        if let Some(last) = row.last_mut() {
+            last.wraps = false;
+        }
+        if r == cursor.0 {
+            cursor_at = (lines.len(), current.len() + cursor.1);
+        }
+        if wraps && ends_with_wide_spacer(&row) {
+            row.pop();
+        }
+        if !wraps {
+            let len = row.iter().rposition(|c| *c != Cell::default()).map_or(0, |i| i + 1);
+            row.truncate(len);
+        }
+        current.extend(row);
+        if !wraps {
+            lines.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    // Blank lines below the cursor are just unused screen space
+    while lines.len() > cursor_at.0 + 1 && lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    if let Some(line) = lines.get_mut(cursor_at.0) {
+        if line.len() <= cursor_at.1 {
+            line.resize(cursor_at.1 + 1, Cell::default());
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut new_cursor = (0, 0);
+    for (i, line) in lines.into_iter().enumerate() {
+        let mut row: Vec<Cell> = Vec::with_capacity(new_cols);
+        let mut k = 0;
+        while k < line.len() {
+            // Never split a wide character from its continuation cell
+            let width = if line.get(k + 1).is_some_and(|c| c.is_wide_continuation) { 2 } else { 1 };
+            if !row.is_empty() && row.len() + width > new_cols {
+                if row.len() < new_cols {
+                    row.resize(new_cols - 1, Cell::default());
+                    row.push(Cell::wide_spacer());
+                } else {
+                    row[new_cols - 1].wraps = true;
+                }
+                out.push(std::mem::replace(&mut row, Vec::with_capacity(new_cols)));
+            }
+            if i == cursor_at.0 && (k..k + width).contains(&cursor_at.1) {
+                new_cursor = (out.len(), row.len() + (cursor_at.1 - k));
+            }
+            row.extend_from_slice(&line[k..k + width]);
+            k += width;
+        }
+        row.resize(new_cols, Cell::default());
+        out.push(row);
+    }
+    (out, new_cursor)
+}
+
+/// Whether a soft-wrapped row ends in a `Cell::wide_spacer` rather than the second half of a wide character.
+fn ends_with_wide_spacer(row: &[Cell]) -> bool {
+    match row {
+        [.., before, last] => {
+            last.is_wide_continuation
+                && (before.is_wide_continuation || unicode_width::UnicodeWidthChar::width(before.c) != Some(2))
+        }
+        [last] => last.is_wide_continuation,
+        [] => false,
     }
 }
 
@@ -186,23 +609,111 @@ impl TerminalState {
 // ANSI Logic (LogHandler)
 // ----------------------------------------------------------------------------
 
+/// Returns the `idx`-th CSI parameter. Per ECMA-48 an omitted parameter, an empty
+/// one (`CSI ;5H`) and an explicit `0` all select the sequence's default value.
+fn csi_param(params: &vte::Params, idx: usize, default: usize) -> usize {
+    match params.iter().nth(idx).and_then(|v| v.first()) {
+        Some(&v) if v != 0 => v as usize,
+        _ => default,
+    }
+}
+
+/// Color of SGR 38/48/58, given either as subparameters of the same parameter
+/// (`38:5:n`, `38:2::r:g:b`) or as the following parameters (`38;5;n`, `38;2;r;g;b`).
+/// Unsupported color modes return `None`.
+fn sgr_extended_color<'a>(param: &[u16], rest: &mut impl Iterator<Item = &'a [u16]>) -> Option<TermColor> {
+    if param.len() > 1 {
+        return match param[1] {
+            5 => param.get(2).map(|&n| TermColor::Indexed(n as u8)),
+            2 => {
+                // The color space id is optional: `2::r:g:b` or `2:id:r:g:b` versus `2:r:g:b`
+                let rgb = if param.len() >= 6 { &param[3..6] } else { param.get(2..5)? };
+                Some(TermColor::Rgb(Color32::from_rgb(rgb[0] as u8, rgb[1] as u8, rgb[2] as u8)))
+            }
+            _ => None,
+        };
+    }
+    match rest.next().map(|v| v[0]) {
+        Some(5) => rest.next().map(|v| TermColor::Indexed(v[0] as u8)),
+        Some(2) => {
+            let mut channel = || rest.next().map(|v| v[0] as u8).unwrap_or(0);
+            Some(TermColor::Rgb(Color32::from_rgb(channel(), channel(), channel())))
+        }
+        _ => None,
+    }
+}
+
+/// Encodes a mouse report for the cell at (row, col), both 0-based. `button` holds
+/// the button, motion (32) and modifier bits. X10 reports can't address cells
+/// past column/row 223.
+fn mouse_report(button: u8, (row, col): (usize, usize), release: bool, sgr: bool) -> Option<Vec<u8>> {
+    if sgr {
+        let kind = if release { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{};{};{}{}", button, col + 1, row + 1, kind).into_bytes());
+    }
+    // X10 doesn't say which button was released: button bits 3 mean "release"
+    let button = if release { button | 3 } else { button };
+    let coordinate = |v: usize| u8::try_from(v + 33).ok();
+    Some(vec![0x1b, b'[', b'M', 32 + button, coordinate(col)?, coordinate(row)?])
+}
+
+/// Modifier bits of a mouse report.
+fn mouse_modifiers(modifiers: egui::Modifiers) -> u8 {
+    (if modifiers.alt { 8 } else { 0 }) | (if modifiers.ctrl { 16 } else { 0 })
+}
+
+/// Bytes sent for a paste: newlines become CR as if typed, and the text is
+/// wrapped in bracketed-paste markers when the program enabled mode 2004.
+fn paste_payload(text: &str, bracketed: bool) -> String {
+    let text = text.replace("\r\n", "\r").replace('\n', "\r");
+    if bracketed {
+        // An end marker inside the text would let it escape the paste
+        format!("\x1b[200~{}\x1b[201~", text.replace("\x1b[201~", ""))
+    } else {
+        text
+    }
+}
+
 struct LogHandler<'a> {
     state: &'a mut TerminalState,
+    /// Answers to queries (DA, DSR), written back to the PTY once the chunk is parsed.
+    replies: Vec<u8>,
 }
 
 impl<'a> Perform for LogHandler<'a> {
     fn print(&mut self, c: char) {
-        let is_wide = unicode_width::UnicodeWidthChar::width(c).unwrap_or(1) > 1;
+        let width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(1);
+        if width == 0 {
+            // Combining characters join the glyph before the cursor instead of taking a cell
+            let (r, mut col) = (self.state.cursor_row, self.state.cursor_col);
+            let row = self.state.grid_mut().get_mut(r);
+            if let Some(row) = row.filter(|_| col > 0) {
+                col = col.min(row.len());
+                if col >= 2 && row[col - 1].is_wide_continuation {
+                    col -= 1;
+                }
+                let cell = &mut row[col - 1];
+                cell.combining = cell.combining.or(Some(c));
+                self.state.mark_dirty();
+            }
+            return;
+        }
+        let is_wide = width > 1;
         let width = if is_wide { 2 } else { 1 };
         
         let cols = self.state.cols;
         if self.state.cursor_col + width > cols {
+            let r = self.state.cursor_row;
+            let wide_at_edge = self.state.cursor_col < cols;
+            if let Some(last) = self.state.grid_mut().get_mut(r).and_then(|row| row.last_mut()) {
+                if wide_at_edge {
+                    *last = Cell::wide_spacer();
+                } else {
+                    last.wraps = true;
+                }
+            }
             self.state.cursor_col = 0;
-            self.state.cursor_row += 1;
-        }
-        if self.state.cursor_row > self.state.scroll_bottom {
-            self.state.cursor_row = self.state.scroll_bottom;
-            self.state.scroll_up();
+            self.state.line_feed();
         }
 
         let r = self.state.cursor_row;
@@ -210,13 +721,18 @@ impl<'a> Perform for LogHandler<'a> {
         if r < self.state.rows {
             let cell_style = Cell {
                 c,
+                combining: None,
                 fg: self.state.current_fg,
                 bg: self.state.current_bg,
                 bold: self.state.current_bold,
+                dim: self.state.current_dim,
                 italic: self.state.current_italic,
                 underline: self.state.current_underline,
+                underline_color: self.state.current_underline_color,
                 inverse: self.state.current_inverse,
                 is_wide_continuation: false,
+                wraps: false,
+                link: self.state.current_link,
             };
 
             let grid = self.state.grid_mut();
@@ -229,29 +745,24 @@ impl<'a> Perform for LogHandler<'a> {
                 grid[r][c_idx + 1] = continuation;
             }
             self.state.cursor_col += width;
-            self.state.dirty = true;
+            self.state.last_printed = Some(c);
+            self.state.mark_dirty();
         }
     }
 
     fn execute(&mut self, byte: u8) {
         match byte {
             b'\r' => self.state.cursor_col = 0,
-            b'\n' | b'\x0b' | b'\x0c' => {
-                self.state.cursor_row += 1;
-                if self.state.cursor_row > self.state.scroll_bottom {
-                    self.state.cursor_row = self.state.scroll_bottom;
-                    self.state.scroll_up();
-                }
-            }
+            b'\n' | b'\x0b' | b'\x0c' => self.state.line_feed(),
             b'\x08' => { if self.state.cursor_col > 0 { self.state.cursor_col -= 1; } }
             b'\t' => {
                 let next = (self.state.cursor_col / 8 + 1) * 8;
                 self.state.cursor_col = next.min(self.state.cols - 1);
             }
-            7 => { /* Bell */ } // This is ASCII BEL character
+            7 => self.state.bell_pending = Some(Instant::now()),
             _ => {} // Other control characters are ignored for now
         }
-        self.state.dirty = true;
+        self.state.mark_dirty();
     }
 
     fn csi_dispatch(&mut self, params: &vte::Params, intermediates: &[u8], _ignore: bool, c: char) {
@@ -263,172 +774,205 @@ impl<'a> Perform for LogHandler<'a> {
                 while let Some(param) = it.next() {
                     match param[0] {
                         0 => {
-                            self.state.current_fg = TERM_FG;
-                            self.state.current_bg = Color32::TRANSPARENT;
+                            self.state.current_fg = TermColor::Default;
+                            self.state.current_bg = TermColor::Default;
                             self.state.current_bold = false;
+                            self.state.current_dim = false;
                             self.state.current_italic = false;
-                            self.state.current_underline = false;
+                            self.state.current_underline = Underline::None;
+                            self.state.current_underline_color = None;
                             self.state.current_inverse = false;
                         }
                         1 => self.state.current_bold = true,
+                        2 => self.state.current_dim = true,
                         3 => self.state.current_italic = true,
-                        4 => self.state.current_underline = true,
+                        // `4:n` selects the style (`4:3` is curly); plain `4` is a single line
+                        4 => self.state.current_underline = param.get(1).map_or(Underline::Single, |&n| Underline::from_subparam(n)),
                         7 => self.state.current_inverse = true,
-                        22 => self.state.current_bold = false,
+                        21 => self.state.current_underline = Underline::Double,
+                        22 => {
+                            self.state.current_bold = false;
+                            self.state.current_dim = false;
+                        }
                         23 => self.state.current_italic = false,
-                        24 => self.state.current_underline = false,
+                        24 => self.state.current_underline = Underline::None,
                         27 => self.state.current_inverse = false,
-                        30..=37 => self.state.current_fg = ansi_color(param[0] as u8 - 30),
-                        38 => {
-                            match it.next().map(|v| v[0]) {
-                                Some(5) => if let Some(v) = it.next() { self.state.current_fg = ansi_color(v[0] as u8); },
-                                Some(2) => {
-                                    let r = it.next().map(|v| v[0] as u8).unwrap_or(0);
-                                    let g = it.next().map(|v| v[0] as u8).unwrap_or(0);
-                                    let b = it.next().map(|v| v[0] as u8).unwrap_or(0);
-                                    self.state.current_fg = Color32::from_rgb(r, g, b);
-                                }
-                                _ => {} // Ignore unsupported SGR color modes
-                            }
-                        }
-                        39 => self.state.current_fg = TERM_FG,
-                        40..=47 => self.state.current_bg = ansi_color(param[0] as u8 - 40),
-                        48 => {
-                            match it.next().map(|v| v[0]) {
-                                Some(5) => if let Some(v) = it.next() { self.state.current_bg = ansi_color(v[0] as u8); },
-                                Some(2) => {
-                                    let r = it.next().map(|v| v[0] as u8).unwrap_or(0);
-                                    let g = it.next().map(|v| v[0] as u8).unwrap_or(0);
-                                    let b = it.next().map(|v| v[0] as u8).unwrap_or(0);
-                                    self.state.current_bg = Color32::from_rgb(r, g, b);
-                                }
-                                _ => {} // Ignore unsupported SGR color modes
-                            }
-                        }
-                        49 => self.state.current_bg = Color32::TRANSPARENT,
-                        90..=97 => self.state.current_fg = ansi_color(param[0] as u8 - 90 + 8),
-                        100..=107 => self.state.current_bg = ansi_color(param[0] as u8 - 100 + 8),
+                        30..=37 => self.state.current_fg = TermColor::Indexed(param[0] as u8 - 30),
+                        38 => if let Some(color) = sgr_extended_color(param, &mut it) { self.state.current_fg = color; },
+                        39 => self.state.current_fg = TermColor::Default,
+                        40..=47 => self.state.current_bg = TermColor::Indexed(param[0] as u8 - 40),
+                        48 => if let Some(color) = sgr_extended_color(param, &mut it) { self.state.current_bg = color; },
+                        49 => self.state.current_bg = TermColor::Default,
+                        58 => if let Some(color) = sgr_extended_color(param, &mut it) { self.state.current_underline_color = Some(color); },
+                        59 => self.state.current_underline_color = None,
+                        90..=97 => self.state.current_fg = TermColor::Indexed(param[0] as u8 - 90 + 8),
+                        100..=107 => self.state.current_bg = TermColor::Indexed(param[0] as u8 - 100 + 8),
                         _ => {} // Ignore unsupported SGR parameters
                     }
                 }
             }
             'H' | 'f' => {
-                let r = p(0).saturating_sub(1);
-                let col = p(1).saturating_sub(1);
+                let r = csi_param(params, 0, 1) - 1;
+                let col = csi_param(params, 1, 1) - 1;
                 self.state.cursor_row = r.min(self.state.rows - 1);
                 self.state.cursor_col = col.min(self.state.cols - 1);
             }
-            'A' => self.state.cursor_row = self.state.cursor_row.saturating_sub(p(0).max(1)),
-            'B' => self.state.cursor_row = (self.state.cursor_row + p(0).max(1)).min(self.state.rows - 1),
-            'C' => self.state.cursor_col = (self.state.cursor_col + p(0).max(1)).min(self.state.cols - 1),
-            'D' => self.state.cursor_col = self.state.cursor_col.saturating_sub(p(0).max(1)),
-            'G' => self.state.cursor_col = p(0).saturating_sub(1).min(self.state.cols - 1),
-            'd' => self.state.cursor_row = p(0).saturating_sub(1).min(self.state.rows - 1),
-            'J' => {
+            'A' => self.state.cursor_row = self.state.cursor_row.saturating_sub(csi_param(params, 0, 1)),
+            'B' => self.state.cursor_row = (self.state.cursor_row + csi_param(params, 0, 1)).min(self.state.rows - 1),
+            'C' => self.state.cursor_col = (self.state.cursor_col + csi_param(params, 0, 1)).min(self.state.cols - 1),
+            'D' => self.state.cursor_col = self.state.cursor_col.saturating_sub(csi_param(params, 0, 1)),
+            'G' => self.state.cursor_col = (csi_param(params, 0, 1) - 1).min(self.state.cols - 1),
+            'd' => self.state.cursor_row = (csi_param(params, 0, 1) - 1).min(self.state.rows - 1),
+            'J' => { // ED - Erase in Display
                 let (rows, cols, r, c) = (self.state.rows, self.state.cols, self.state.cursor_row, self.state.cursor_col);
+                let blank = self.state.blank_cell();
                 let grid = self.state.grid_mut();
                 match p(0) {
-                    0 => {
-                        for col in c..cols { grid[r][col] = Cell::default(); }
-                        for row in (r + 1)..rows { for col in 0..cols { grid[row][col] = Cell::default(); } }
+                    0 => { // Cursor to end of screen
+                        if r < rows { for col in c.min(cols)..cols { grid[r][col] = blank; } }
+                        for row in (r + 1)..rows { for col in 0..cols { grid[row][col] = blank; } }
                     }
-                    1 => {
-                        for row in 0..r { for col in 0..cols { grid[row][col] = Cell::default(); } }
-                        for col in 0..=c.min(cols - 1) { grid[r][col] = Cell::default(); }
+                    1 => { // Start of screen to cursor (inclusive)
+                        for row in 0..r.min(rows) { for col in 0..cols { grid[row][col] = blank; } }
+                        if r < rows { for col in 0..=c.min(cols - 1) { grid[r][col] = blank; } }
                     }
-                    2 | 3 => { for row in 0..rows { for col in 0..cols { grid[row][col] = Cell::default(); } } } // 3 clears entire screen and moves cursor to home
+                    2 => { for row in 0..rows { for col in 0..cols { grid[row][col] = blank; } } }
+                    3 => self.state.history.clear(), // Erase scrollback only; the screen is left alone
                     _ => {} // Ignore unsupported erase modes
                 }
             }
-            'K' => {
+            'K' => { // EL - Erase in Line
                 let (cols, r, c) = (self.state.cols, self.state.cursor_row, self.state.cursor_col);
+                let blank = self.state.blank_cell();
                 let grid = self.state.grid_mut();
                 if r < grid.len() {
                     match p(0) {
-                        0 => for col in c..cols { grid[r][col] = Cell::default(); },
-                        1 => for col in 0..=c.min(cols - 1) { grid[r][col] = Cell::default(); },
-                        2 => for col in 0..cols { grid[r][col] = Cell::default(); },
+                        0 => for col in c.min(cols)..cols { grid[r][col] = blank; },
+                        1 => for col in 0..=c.min(cols - 1) { grid[r][col] = blank; },
+                        2 => for col in 0..cols { grid[r][col] = blank; },
                         _ => {} // Ignore unsupported erase modes
                     }
                 }
             }
             'X' => { // ECH - Erase Character
-                let n = p(0).max(1);
                 let (cols, r, c) = (self.state.cols, self.state.cursor_row, self.state.cursor_col);
-                let grid = self.state.grid_mut();
-                if r < grid.len() {
-                    for i in 0..n {
-                        if c + i < cols {
-                            grid[r][c + i] = Cell::default();
-                        }
-                    }
+                let n = csi_param(params, 0, 1).min(cols.saturating_sub(c));
+                let blank = self.state.blank_cell();
+                if let Some(row) = self.state.grid_mut().get_mut(r) {
+                    row[c..c + n].fill(blank);
                 }
             }
             '@' => { // ICH - Insert Character
-                let n = p(0).max(1);
-                let (_cols, r, c) = (self.state.cols, self.state.cursor_row, self.state.cursor_col);
-                let grid = self.state.grid_mut();
-                if r < grid.len() {
-                    let row = &mut grid[r];
-                    for _ in 0..n {
-                        row.insert(c, Cell::default());
-                        row.pop(); // Remove from end to maintain width
-                    }
+                let (cols, r, c) = (self.state.cols, self.state.cursor_row, self.state.cursor_col);
+                let n = csi_param(params, 0, 1).min(cols.saturating_sub(c));
+                let blank = self.state.blank_cell();
+                if let Some(row) = self.state.grid_mut().get_mut(r).filter(|_| c < cols) {
+                    // Shift the rest of the line right; cells pushed past the margin are lost
+                    row[c..].rotate_right(n);
+                    row[c..c + n].fill(blank);
                 }
             }
             'P' => { // DCH - Delete Character
-                let n = p(0).max(1);
-                let (_cols, r, c) = (self.state.cols, self.state.cursor_row, self.state.cursor_col);
-                let grid = self.state.grid_mut();
-                if r < grid.len() {
-                    let row = &mut grid[r];
-                    for _ in 0..n {
-                        if c < row.len() {
-                            row.remove(c);
-                            row.push(Cell::default()); // Add to end
-                        }
-                    }
+                let (cols, r, c) = (self.state.cols, self.state.cursor_row, self.state.cursor_col);
+                let n = csi_param(params, 0, 1).min(cols.saturating_sub(c));
+                let blank = self.state.blank_cell();
+                if let Some(row) = self.state.grid_mut().get_mut(r).filter(|_| c < cols) {
+                    row[c..].rotate_left(n);
+                    row[cols - n..].fill(blank);
                 }
             }
             'L' => { // IL - Insert Line
-                let n = p(0).max(1);
                 let (top, bottom) = (self.state.scroll_top, self.state.scroll_bottom);
                 let r = self.state.cursor_row;
-                let cols = self.state.cols; // Capture cols before mut borrow
                 if r >= top && r <= bottom {
-                    let grid = self.state.grid_mut();
-                    for _ in 0..n {
-                        grid.remove(bottom);
-                        grid.insert(r, vec![Cell::default(); cols]);
-                    }
+                    let n = csi_param(params, 0, 1).min(bottom - r + 1);
+                    let blank = vec![self.state.blank_cell(); self.state.cols];
+                    let region = &mut self.state.grid_mut()[r..=bottom];
+                    region.rotate_right(n);
+                    region[..n].fill(blank);
+                    self.state.cursor_col = 0;
                 }
             }
             'M' => { // DL - Delete Line
-                let n = p(0).max(1);
                 let (top, bottom) = (self.state.scroll_top, self.state.scroll_bottom);
                 let r = self.state.cursor_row;
-                let cols = self.state.cols; // Capture cols before mut borrow
                 if r >= top && r <= bottom {
-                    let grid = self.state.grid_mut();
-                    for _ in 0..n {
-                        grid.remove(r);
-                        grid.insert(bottom, vec![Cell::default(); cols]);
+                    let n = csi_param(params, 0, 1).min(bottom - r + 1);
+                    let blank = vec![self.state.blank_cell(); self.state.cols];
+                    let region = &mut self.state.grid_mut()[r..=bottom];
+                    region.rotate_left(n);
+                    let len = region.len();
+                    region[len - n..].fill(blank);
+                    self.state.cursor_col = 0;
+                }
+            }
+            'r' if intermediates.is_empty() => { // DECSTBM - Set Top and Bottom Margins
+                let top = csi_param(params, 0, 1) - 1;
+                let bottom = csi_param(params, 1, self.state.rows).min(self.state.rows) - 1;
+                // A region needs at least two lines; invalid margins are ignored
+                if top < bottom {
+                    self.state.scroll_top = top;
+                    self.state.scroll_bottom = bottom;
+                    self.state.cursor_row = 0;
+                    self.state.cursor_col = 0;
+                }
+            }
+            'b' if intermediates.is_empty() => { // REP - Repeat Preceding Character
+                if let Some(last) = self.state.last_printed {
+                    // Never repeat past the end of the line; a pending wrap starts a new one
+                    let cols = self.state.cols;
+                    let remaining = match cols - self.state.cursor_col.min(cols) {
+                        0 => cols,
+                        n => n,
+                    };
+                    for _ in 0..csi_param(params, 0, 1).min(remaining) {
+                        self.print(last);
                     }
                 }
             }
-            'r' => {
-                let top = p(0).saturating_sub(1);
-                let bot = if p(1) == 0 { self.state.rows } else { p(1) }.saturating_sub(1);
-                self.state.scroll_top = top;
-                self.state.scroll_bottom = bot.min(self.state.rows - 1);
+            'c' if intermediates.is_empty() && p(0) == 0 => { // DA - Primary Device Attributes
+                // VT100 with Advanced Video Option
+                self.replies.extend_from_slice(b"\x1b[?1;2c");
+            }
+            'n' if intermediates.is_empty() => match p(0) { // DSR - Device Status Report
+                5 => self.replies.extend_from_slice(b"\x1b[0n"),
+                6 => {
+                    // After printing in the last column the cursor waits past the edge for the wrap
+                    let (row, col) = (self.state.cursor_row + 1, self.state.cursor_col.min(self.state.cols - 1) + 1);
+                    self.replies.extend_from_slice(format!("\x1b[{};{}R", row, col).as_bytes());
+                }
+                _ => {}
+            },
+            // SCOSC / SCORC, the CSI spelling of DECSC / DECRC
+            's' if intermediates.is_empty() => self.state.save_cursor(),
+            'u' if intermediates.is_empty() => self.state.restore_cursor(),
+            'q' if intermediates == b" " => { // DECSCUSR - Set Cursor Style
+                // Odd values blink, even values are steady; 0 restores our default
+                let (shape, blink) = match p(0) {
+                    0 | 2 => (CursorShape::Block, false),
+                    1 => (CursorShape::Block, true),
+                    3 => (CursorShape::Underline, true),
+                    4 => (CursorShape::Underline, false),
+                    5 => (CursorShape::Bar, true),
+                    6 => (CursorShape::Bar, false),
+                    _ => return, // Ignore unknown styles
+                };
+                self.state.cursor_shape = shape;
+                self.state.cursor_blink = blink;
             }
             'h' if intermediates == b"?" => {
                 for param in params.iter() {
                     match param[0] {
                         1 => self.state.application_cursor = true,
                         25 => self.state.cursor_visible = true,
+                        2004 => self.state.bracketed_paste = true,
+                        1000 => self.state.mouse_mode = MouseMode::Press,
+                        1002 => self.state.mouse_mode = MouseMode::Drag,
+                        1003 => self.state.mouse_mode = MouseMode::Any,
+                        1006 => self.state.mouse_sgr = true,
+                        1004 => self.state.focus_reporting = true,
                         1049 => {
-                            self.state.saved_cursor = (self.state.cursor_row, self.state.cursor_col);
+                            self.state.save_cursor();
                             self.state.is_alt_screen = true;
                             let (rows, cols) = (self.state.rows, self.state.cols);
                             self.state.alt_grid = vec![vec![Cell::default(); cols]; rows];
@@ -443,10 +987,13 @@ impl<'a> Perform for LogHandler<'a> {
                     match param[0] {
                         1 => self.state.application_cursor = false,
                         25 => self.state.cursor_visible = false,
+                        2004 => self.state.bracketed_paste = false,
+                        1000 | 1002 | 1003 => self.state.mouse_mode = MouseMode::Off,
+                        1006 => self.state.mouse_sgr = false,
+                        1004 => self.state.focus_reporting = false,
                         1049 => {
                             self.state.is_alt_screen = false;
-                            self.state.cursor_row = self.state.saved_cursor.0.min(self.state.rows - 1);
-                            self.state.cursor_col = self.state.saved_cursor.1.min(self.state.cols - 1);
+                            self.state.restore_cursor();
                         }
                         _ => {} // Ignore unsupported DECRST modes
                     }
@@ -454,15 +1001,17 @@ impl<'a> Perform for LogHandler<'a> {
             }
             _ => {} // Ignore unsupported CSI sequences
         }
-        self.state.dirty = true;
+        self.state.mark_dirty();
     }
 
     fn esc_dispatch(&mut self, _intermediates: &[u8], _ignore: bool, byte: u8) {
         match byte {
-            b'7' => self.state.saved_cursor = (self.state.cursor_row, self.state.cursor_col),
-            b'8' => {
-                self.state.cursor_row = self.state.saved_cursor.0.min(self.state.rows - 1);
-                self.state.cursor_col = self.state.saved_cursor.1.min(self.state.cols - 1);
+            b'7' => self.state.save_cursor(), // DECSC
+            b'8' => self.state.restore_cursor(), // DECRC
+            b'D' => self.state.line_feed(), // Index
+            b'E' => { // Next Line
+                self.state.cursor_col = 0;
+                self.state.line_feed();
             }
             b'M' => { // Reverse Index
                 if self.state.cursor_row == self.state.scroll_top {
@@ -478,102 +1027,1076 @@ impl<'a> Perform for LogHandler<'a> {
             }
             _ => {} // Ignore unsupported ESC sequences
         }
-        self.state.dirty = true;
+        self.state.mark_dirty();
     }
 
     fn hook(&mut self, _params: &vte::Params, _intermediates: &[u8], _ignore: bool, _c: char) {}
     fn put(&mut self, _byte: u8) {}
     fn unhook(&mut self) {}
-    fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {}
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        match params {
+            // OSC 0 sets icon name and title, OSC 2 only the title; we have no icon.
+            [b"0" | b"2", title @ ..] => {
+                // vte splits on ';', which is allowed inside the title itself.
+                let title = String::from_utf8_lossy(&title.join(&b';')).trim().to_string();
+                self.state.title = (!title.is_empty()).then_some(title);
+            }
+            // OSC 8 ; params ; URI — an empty URI ends the link
+            [b"8", _params, uri @ ..] => {
+                let uri = String::from_utf8_lossy(&uri.join(&b';')).into_owned();
+                self.state.current_link = (!uri.is_empty()).then(|| self.state.link_id(&uri));
+            }
+            _ => {}
+        }
+    }
 }
 
 // ----------------------------------------------------------------------------
-// Tab Implementation
+// Session (process & reader thread lifetime)
 // ----------------------------------------------------------------------------
 
-pub struct TerminalTab {
-    state: Arc<Mutex<TerminalState>>,
-    writer: Arc<Mutex<Box<dyn Write + Send>>>,
-    master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
-    last_size: (usize, usize),
-    ctx: egui::Context,
-    input_buffer: String,
-    is_composing: bool,
-    selection_start: Option<(usize, usize)>,
-    selection_end: Option<(usize, usize)>,
-    drag_start: Option<(usize, usize)>,
+/// Owns the shell process and the PTY reader thread of one terminal. It is shared
+/// by every clone of a `TerminalTab`, so it shuts down only when the last clone
+/// (i.e. the tab in the dock) is dropped.
+struct TerminalSession {
+    alive: Arc<AtomicBool>,
+    child: Mutex<Option<Box<dyn Child + Send + Sync>>>,
+    reader_thread: Mutex<Option<std::thread::JoinHandle<()>>>,
+    /// Started by `shutdown`: kills and reaps the shell, then joins the reader thread.
+    reaper: Mutex<Option<std::thread::JoinHandle<()>>>,
+    recorder: Arc<Mutex<Recorder>>,
 }
 
-impl std::fmt::Debug for TerminalTab {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("TerminalTab").finish()
+impl TerminalSession {
+    fn shutdown(&self) {
+        if !self.alive.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        self.recorder.lock().stop();
+        // Killing the shell closes the slave side, which unblocks the pending
+        // `read` in the reader thread; it then sees `alive == false` and exits.
+        // Kill, reap and join off the UI thread so a slow exit never stalls a frame.
+        let child = self.child.lock().take();
+        let reader = self.reader_thread.lock().take();
+        *self.reaper.lock() = Some(std::thread::spawn(move || {
+            if let Some(mut child) = child {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            if let Some(reader) = reader {
+                let _ = reader.join();
+            }
+        }));
     }
 }
 
-impl Clone for TerminalTab {
-    fn clone(&self) -> Self {
-        Self {
-            state: self.state.clone(),
-            writer: self.writer.clone(),
-            master: self.master.clone(),
-            last_size: self.last_size,
-            ctx: self.ctx.clone(),
-            input_buffer: String::new(),
-            is_composing: false,
-            selection_start: None,
-            selection_end: None,
-            drag_start: None,
-        }
+impl TerminalSession {
+    /// Whether the shell process is still running.
+    fn child_running(&self) -> bool {
+        self.child.lock().as_mut().is_some_and(|child| matches!(child.try_wait(), Ok(None)))
     }
 }
 
-impl TabInstance for TerminalTab {
-    fn title(&self) -> WidgetText { "Terminal".into() }
+/// Signals offered by the "Send Signal" menu.
+#[derive(Clone, Copy)]
+enum Signal {
+    Interrupt,
+    Terminate,
+    Kill,
+}
 
-    fn ui(&mut self, ui: &mut Ui, _control: &mut Vec<AppCommand>) {
-        let font_id = FontId::monospace(14.0);
-        let char_size = ui.fonts_mut(|f| {
-            let width = f.glyph_width(&font_id, 'M');
-            let height = f.row_height(&font_id);
-            Vec2::new(width, height)
-        });
+impl Signal {
+    const ALL: [Signal; 3] = [Signal::Interrupt, Signal::Terminate, Signal::Kill];
 
-        // 1. Calculate PTY size based on available area
-        let available_size = ui.available_size();
-        let cols = (available_size.x / char_size.x).floor() as usize;
-        let rows = (available_size.y / char_size.y).floor() as usize;
-        
-        if cols > 0 && rows > 0 && (cols != self.last_size.0 || rows != self.last_size.1) {
-            self.state.lock().resize(rows, cols);
-            let _ = self.master.lock().resize(PtySize {
-                rows: rows as u16,
-                cols: cols as u16,
-                pixel_width: 0,
-                pixel_height: 0,
-            });
-            self.last_size = (cols, rows);
+    fn label(self) -> &'static str {
+        match self {
+            Signal::Interrupt => "SIGINT (Interrupt)",
+            Signal::Terminate => "SIGTERM (Terminate)",
+            Signal::Kill => "SIGKILL (Kill)",
         }
+    }
 
-        let state_lock = self.state.lock();
-        let history_len = state_lock.history.len();
-        let grid_len = state_lock.rows;
-        let total_rows = history_len + grid_len;
-        drop(state_lock);
+    #[cfg(unix)]
+    fn number(self) -> libc::c_int {
+        match self {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+        }
+    }
+}
 
-        let mut output_to_write = String::new();
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
 
-        // 2. Use ScrollArea for native scrolling and scrollbar
-        egui::ScrollArea::vertical()
-            .id_salt("terminal_scroll")
-            .auto_shrink([false; 2])
-            .stick_to_bottom(true)
-            .show_viewport(ui, |ui, viewport| {
-                let content_size = Vec2::new(available_size.x, total_rows as f32 * char_size.y);
-                let (rect, response) = ui.allocate_at_least(content_size, Sense::click_and_drag());
-                
-                // 1. Invisible Input Overlay (Bottom Layer)
-                // We place this BEFORE the background is drawn so that the terminal's 
-                // background covers the TextEdit's default blinking cursor.
+// ----------------------------------------------------------------------------
+// Rendering
+// ----------------------------------------------------------------------------
+
+/// Finds the hyperlink under column `col` of a row: an OSC 8 link, or, when
+/// `detect_urls` is set, a plain `http(s)://` run. Returns its columns and URL.
+fn link_at(state: &TerminalState, cells: &[Cell], col: usize, detect_urls: bool) -> Option<(std::ops::Range<usize>, String)> {
+    let cell = cells.get(col)?;
+    if let Some(id) = cell.link {
+        let start = cells[..col].iter().rposition(|c| c.link != Some(id)).map_or(0, |i| i + 1);
+        let end = cells[col..].iter().position(|c| c.link != Some(id)).map_or(cells.len(), |i| col + i);
+        return state.links.get(id as usize).map(|url| (start..end, url.clone()));
+    }
+    if !detect_urls {
+        return None;
+    }
+
+    let is_url_char = |c: char| !c.is_whitespace() && !matches!(c, '"' | '\'' | '<' | '>' | '`' | '\0');
+    if !is_url_char(cell.c) {
+        return None;
+    }
+    let start = cells[..col].iter().rposition(|c| !is_url_char(c.c)).map_or(0, |i| i + 1);
+    let mut end = cells[col..].iter().position(|c| !is_url_char(c.c)).map_or(cells.len(), |i| col + i);
+    let word: String = cells[start..end].iter().filter(|c| !c.is_wide_continuation).map(|c| c.c).collect();
+    let offset = word.find("https://").or_else(|| word.find("http://"))?;
+    // Trailing punctuation usually belongs to the sentence, not the URL
+    let url = word[offset..].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}']);
+    end -= word[offset..].chars().count() - url.chars().count();
+    let url_start = start + word[..offset].chars().count();
+    (col >= url_start && col < end && url.len() > "https://".len()).then(|| (url_start..end, url.to_string()))
+}
+
+fn text_format(font_id: &FontId, color: Color32, italics: bool) -> TextFormat {
+    TextFormat { font_id: font_id.clone(), color, italics, ..Default::default() }
+}
+
+/// Layout job for a run of ASCII cells, with one section per change of color or italics.
+/// With `bold_only` the non-bold cells are blanked out, for the bold pass of `paint_cells`.
+fn text_run_job(cells: &[Cell], theme: &TerminalTheme, font_id: &FontId, bold_only: bool) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    job.text.reserve(cells.len());
+    let mut run: Option<((Color32, bool), usize)> = None;
+    for cell in cells {
+        let style = (cell.foreground(theme), cell.italic);
+        match run {
+            Some((current, _)) if current == style => {}
+            _ => {
+                if let Some(((color, italics), run_start)) = run {
+                    job.sections.push(LayoutSection { leading_space: 0.0, byte_range: run_start..job.text.len(), format: text_format(font_id, color, italics) });
+                }
+                run = Some((style, job.text.len()));
+            }
+        }
+        job.text.push(if bold_only && !cell.bold { ' ' } else { cell.c });
+    }
+    if let Some(((color, italics), run_start)) = run {
+        job.sections.push(LayoutSection { leading_space: 0.0, byte_range: run_start..job.text.len(), format: text_format(font_id, color, italics) });
+    }
+    job
+}
+
+/// Paints one row of cells (backgrounds, then glyphs) starting at `row_pos`.
+fn paint_cells(painter: &egui::Painter, theme: &TerminalTheme, cells: &[Cell], row_pos: egui::Pos2, cols: usize, char_size: Vec2, font_id: &FontId) {
+    let limit = cells.len().min(cols);
+    let cell_pos = |c_idx: usize| row_pos + Vec2::new(c_idx as f32 * char_size.x, 0.0);
+
+    // Backgrounds: one rect per run of equal color
+    let mut c_idx = 0;
+    while c_idx < limit {
+        let bg = cells[c_idx].background(theme);
+        let start_x = c_idx;
+        c_idx += 1;
+        while c_idx < limit && cells[c_idx].background(theme) == bg {
+            c_idx += 1;
+        }
+        if let Some(bg) = bg.filter(|bg| *bg != theme.background) {
+            let bg_rect = Rect::from_min_size(cell_pos(start_x), Vec2::new((c_idx - start_x) as f32 * char_size.x, char_size.y));
+            painter.rect_filled(bg_rect, 0.0, bg);
+        }
+    }
+
+    // Text: runs of ASCII cells share the monospace advance, so each run becomes a
+    // single galley with one section per color change. Other characters may come
+    // from fallback fonts with different advances and are placed cell by cell.
+    // There is no bold monospace font, so bold text is drawn a second time 1px to
+    // the right; that pass blanks out the non-bold cells to keep the columns.
+    let format = |color: Color32, italics: bool| text_format(font_id, color, italics);
+    const BOLD_OFFSET: Vec2 = Vec2::new(1.0, 0.0);
+    let mut c_idx = 0;
+    while c_idx < limit {
+        let cell = &cells[c_idx];
+        if cell.is_wide_continuation || cell.c == ' ' {
+            c_idx += 1;
+            continue;
+        }
+        if !cell.c.is_ascii() || cell.combining.is_some() {
+            let job = LayoutJob::single_section(cell.text(), format(cell.foreground(theme), cell.italic));
+            let galley = painter.layout_job(job);
+            if cell.bold {
+                painter.galley(cell_pos(c_idx) + BOLD_OFFSET, galley.clone(), Color32::TRANSPARENT);
+            }
+            painter.galley(cell_pos(c_idx), galley, Color32::TRANSPARENT);
+            c_idx += 1;
+            continue;
+        }
+
+        let start = c_idx;
+        let mut end = c_idx;
+        while c_idx < limit && cells[c_idx].c.is_ascii() && cells[c_idx].combining.is_none() && !cells[c_idx].is_wide_continuation {
+            if cells[c_idx].c != ' ' {
+                end = c_idx + 1;
+            }
+            c_idx += 1;
+        }
+
+        let layout = |bold_only: bool| painter.layout_job(text_run_job(&cells[start..end], theme, font_id, bold_only));
+        if cells[start..end].iter().any(|cell| cell.bold) {
+            painter.galley(cell_pos(start) + BOLD_OFFSET, layout(true), Color32::TRANSPARENT);
+        }
+        painter.galley(cell_pos(start), layout(false), Color32::TRANSPARENT);
+    }
+
+    // Underlines: one shape per run of equal style and color
+    let mut c_idx = 0;
+    while c_idx < limit {
+        let (style, color) = (cells[c_idx].underline, cells[c_idx].underline_color(theme));
+        let start = c_idx;
+        c_idx += 1;
+        while c_idx < limit && cells[c_idx].underline == style && cells[c_idx].underline_color(theme) == color {
+            c_idx += 1;
+        }
+        if style != Underline::None {
+            let y = row_pos.y + char_size.y - 1.5;
+            paint_underline(painter, style, color, cell_pos(start).x..=cell_pos(c_idx).x, y, char_size.x);
+        }
+    }
+}
+
+/// Draws an underline from `x.start()` to `x.end()` at height `y`.
+fn paint_underline(painter: &egui::Painter, style: Underline, color: Color32, x: std::ops::RangeInclusive<f32>, y: f32, cell_width: f32) {
+    let stroke = egui::Stroke::new(1.0, color);
+    let (x0, x1) = (*x.start(), *x.end());
+    let line = |y: f32| [egui::pos2(x0, y), egui::pos2(x1, y)];
+    match style {
+        Underline::None => {}
+        Underline::Single => { painter.line_segment(line(y), stroke); }
+        Underline::Double => {
+            painter.line_segment(line(y), stroke);
+            painter.line_segment(line(y - 2.0), stroke);
+        }
+        Underline::Curly => {
+            // One wave per cell
+            let steps = ((x1 - x0) / 2.0).ceil().max(1.0) as usize;
+            let points = (0..=steps)
+                .map(|i| {
+                    let px = x0 + (x1 - x0) * i as f32 / steps as f32;
+                    let phase = (px - x0) / cell_width * std::f32::consts::TAU;
+                    egui::pos2(px, y - 1.0 - phase.sin() * 1.5)
+                })
+                .collect();
+            painter.add(egui::Shape::line(points, stroke));
+        }
+        Underline::Dotted => { painter.extend(egui::Shape::dotted_line(&line(y), color, 3.0, 0.6)); }
+        Underline::Dashed => { painter.extend(egui::Shape::dashed_line(&line(y), stroke, 4.0, 2.0)); }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Output Recording
+// ----------------------------------------------------------------------------
+
+/// Collects the printable text of a byte stream, dropping escape sequences.
+struct PlainText {
+    out: String,
+}
+
+impl Perform for PlainText {
+    fn print(&mut self, c: char) {
+        self.out.push(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        if byte == b'\n' || byte == b'\t' {
+            self.out.push(byte as char);
+        }
+    }
+}
+
+struct Recording {
+    path: PathBuf,
+    file: std::io::BufWriter<std::fs::File>,
+    /// Present when ANSI escape sequences are stripped from the log.
+    stripper: Option<Parser>,
+}
+
+/// Tees PTY output into a file. Shared between the tab and the reader thread.
+#[derive(Default)]
+struct Recorder {
+    active: Option<Recording>,
+    /// Set when a write failed and recording stopped; reported by the tab.
+    error: Option<String>,
+}
+
+impl Recorder {
+    fn start(&mut self, path: PathBuf, strip_ansi: bool) -> std::io::Result<()> {
+        let file = std::fs::File::create(&path)?;
+        self.active = Some(Recording {
+            path,
+            file: std::io::BufWriter::new(file),
+            stripper: strip_ansi.then(Parser::new),
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut recording) = self.active.take() {
+            let _ = recording.file.flush();
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let Some(recording) = &mut self.active else { return };
+        let result = match &mut recording.stripper {
+            Some(parser) => {
+                let mut plain = PlainText { out: String::new() };
+                for byte in bytes {
+                    parser.advance(&mut plain, *byte);
+                }
+                recording.file.write_all(plain.out.as_bytes())
+            }
+            None => recording.file.write_all(bytes),
+        };
+        // Flush per chunk so the log can be followed while the command runs
+        if let Err(e) = result.and_then(|_| recording.file.flush()) {
+            self.error = Some(format!("Recording to {} stopped: {}", recording.path.display(), e));
+            self.active = None;
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Scrollback Search
+// ----------------------------------------------------------------------------
+
+/// One search hit: row in history + grid, and the column range it covers.
+#[derive(Clone, Copy, PartialEq)]
+struct SearchMatch {
+    row: usize,
+    start_col: usize,
+    end_col: usize,
+}
+
+/// State of the Ctrl+F search overlay.
+#[derive(Default)]
+struct TerminalSearch {
+    query: String,
+    case_sensitive: bool,
+    /// Sorted by row, then column.
+    matches: Vec<SearchMatch>,
+    current: usize,
+    /// (state revision, query, case sensitivity) the matches were computed for.
+    computed_for: Option<(u64, String, bool)>,
+    /// Scroll the current match into view on the next frame.
+    reveal: bool,
+    /// Give the query field keyboard focus on the next frame.
+    focus: bool,
+}
+
+impl TerminalSearch {
+    /// Rescans history and the grid when the screen, query or case setting changed.
+    fn update(&mut self, state: &TerminalState) {
+        let key = (state.revision, self.query.clone(), self.case_sensitive);
+        if self.computed_for.as_ref() == Some(&key) {
+            return;
+        }
+        let query_changed = self.computed_for.as_ref().is_none_or(|(_, query, case)| *query != key.1 || *case != key.2);
+        self.computed_for = Some(key);
+
+        let case_sensitive = self.case_sensitive;
+        let fold = |c: char| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+        let needle: Vec<char> = self.query.chars().map(fold).collect();
+        self.matches.clear();
+        if !needle.is_empty() {
+            for (row, cells) in state.history.iter().chain(state.grid().iter()).enumerate() {
+                // Skip the second half of wide characters so "中文" matches as two characters
+                let hay: Vec<(usize, char)> = cells
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, cell)| !cell.is_wide_continuation)
+                    .map(|(col, cell)| (col, fold(cell.c)))
+                    .collect();
+                let mut i = 0;
+                while i + needle.len() <= hay.len() {
+                    if hay[i..i + needle.len()].iter().map(|(_, c)| *c).eq(needle.iter().copied()) {
+                        let end_col = hay.get(i + needle.len()).map_or(cells.len(), |(col, _)| *col);
+                        self.matches.push(SearchMatch { row, start_col: hay[i].0, end_col });
+                        i += needle.len();
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+        }
+
+        if query_changed {
+            // Start from the most recent output
+            self.current = self.matches.len().saturating_sub(1);
+            self.reveal = true;
+        } else {
+            self.current = self.current.min(self.matches.len().saturating_sub(1));
+        }
+    }
+
+    /// Moves to the next (`forward`) or previous match, wrapping around.
+    fn step(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        self.current = if forward { (self.current + 1) % len } else { (self.current + len - 1) % len };
+        self.reveal = true;
+    }
+
+    fn current_match(&self) -> Option<SearchMatch> {
+        self.matches.get(self.current).copied()
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Tab Implementation
+// ----------------------------------------------------------------------------
+
+pub struct TerminalTab {
+    title: String,
+    /// Command and working directory the tab was started with, for "Restart".
+    run: Option<String>,
+    cwd: Option<PathBuf>,
+    settings: Arc<Mutex<TerminalSettings>>,
+    session: Arc<TerminalSession>,
+    state: Arc<Mutex<TerminalState>>,
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+    last_size: (usize, usize),
+    ctx: egui::Context,
+    input_buffer: String,
+    is_composing: bool,
+    selection_start: Option<(usize, usize)>,
+    selection_end: Option<(usize, usize)>,
+    drag_start: Option<(usize, usize)>,
+    /// Multiline paste waiting for the user's confirmation.
+    pending_paste: Option<String>,
+    /// "Strip ANSI escapes" choice for the next recording.
+    record_plain_text: bool,
+    /// Showing the primary screen read-only while a full-screen program owns the alt screen.
+    peek_primary: bool,
+    /// Screen text reported to assistive technology: (state revision, text, cursor offset).
+    accessible_text: Option<(u64, String, usize)>,
+    /// Give the input overlay keyboard focus on the next frame (set when the tab is focused).
+    focus_input: bool,
+    /// Scrollback search overlay, open while `Some`.
+    search: Option<TerminalSearch>,
+    /// Whether this tab has focus, so bells in background tabs can be reported.
+    focused: bool,
+    /// When the bell last rang, for the visual flash.
+    last_bell: Option<Instant>,
+    /// Zoomed with Ctrl+Plus / Ctrl+Minus, reset with Ctrl+0.
+    font_size: f32,
+    /// Button held down while the program tracks the mouse (report code 0-2).
+    mouse_button: Option<u8>,
+    /// Cell of the last reported motion, so motion is only sent when it changes.
+    mouse_cell: Option<(usize, usize)>,
+    /// Whether the input had focus last frame, to report focus changes (mode 1004).
+    input_focused: bool,
+    /// Rows the view is scrolled up from the bottom, for the status bar.
+    scrolled_up: usize,
+}
+
+impl std::fmt::Debug for TerminalTab {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TerminalTab").finish()
+    }
+}
+
+impl Clone for TerminalTab {
+    fn clone(&self) -> Self {
+        Self {
+            title: self.title.clone(),
+            run: self.run.clone(),
+            cwd: self.cwd.clone(),
+            settings: self.settings.clone(),
+            session: self.session.clone(),
+            state: self.state.clone(),
+            writer: self.writer.clone(),
+            master: self.master.clone(),
+            last_size: self.last_size,
+            ctx: self.ctx.clone(),
+            input_buffer: String::new(),
+            is_composing: false,
+            selection_start: None,
+            selection_end: None,
+            drag_start: None,
+            pending_paste: None,
+            record_plain_text: self.record_plain_text,
+            peek_primary: false,
+            accessible_text: None,
+            focus_input: false,
+            search: None,
+            focused: false,
+            last_bell: None,
+            font_size: self.font_size,
+            mouse_button: None,
+            mouse_cell: None,
+            input_focused: false,
+            scrolled_up: 0,
+        }
+    }
+}
+
+impl TerminalTab {
+    /// Read-only view of the primary screen and scrollback. The alt screen keeps
+    /// updating underneath; Escape returns to it.
+    fn primary_peek_ui(&mut self, ui: &mut Ui, theme: &TerminalTheme, char_size: Vec2, font_id: &FontId) {
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, Key::Escape)) {
+            self.peek_primary = false;
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Primary screen (read-only). Press Esc to return to the running program.").weak());
+            if ui.small_button("Return").clicked() {
+                self.peek_primary = false;
+            }
+        });
+
+        let state = self.state.lock();
+        let history_len = state.history.len();
+        let total_rows = history_len + state.primary_grid.len();
+        egui::ScrollArea::vertical()
+            .id_salt("terminal_peek_scroll")
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .show_viewport(ui, |ui, viewport| {
+                let content_size = Vec2::new(ui.available_width(), total_rows as f32 * char_size.y);
+                let (rect, _) = ui.allocate_at_least(content_size, Sense::hover());
+                let painter = ui.painter_at(rect);
+                painter.rect_filled(viewport.translate(rect.min.to_vec2()), 0.0, theme.background);
+
+                let start_row = (viewport.min.y / char_size.y).floor() as usize;
+                let end_row = (viewport.max.y / char_size.y).ceil() as usize;
+                for row_idx in start_row..end_row.min(total_rows) {
+                    let cells = if row_idx < history_len { &state.history[row_idx] } else { &state.primary_grid[row_idx - history_len] };
+                    let row_pos = rect.min + Vec2::new(0.0, row_idx as f32 * char_size.y);
+                    paint_cells(&painter, theme, cells, row_pos, state.cols, char_size, font_id);
+                }
+            });
+    }
+
+    /// Forwards pointer input inside `screen` to the program while it tracks the
+    /// mouse. Holding Shift bypasses reporting so text can still be selected.
+    /// Returns whether the pointer is being reported.
+    fn report_mouse(&mut self, ui: &Ui, screen: Rect, char_size: Vec2) -> bool {
+        let (mode, sgr) = {
+            let state = self.state.lock();
+            (state.mouse_mode, state.mouse_sgr)
+        };
+        if mode == MouseMode::Off || ui.input(|i| i.modifiers.shift) {
+            self.mouse_button = None;
+            return false;
+        }
+
+        let cell_at = |pos: egui::Pos2| {
+            let pos = pos - screen.min;
+            ((pos.y / char_size.y) as usize, (pos.x / char_size.x) as usize)
+        };
+        let mut out = Vec::new();
+        ui.input(|i| {
+            let modifiers = mouse_modifiers(i.modifiers);
+            let mut report = |button: u8, cell, release| {
+                if let Some(bytes) = mouse_report(button | modifiers, cell, release, sgr) {
+                    out.extend_from_slice(&bytes);
+                }
+            };
+            for event in &i.events {
+                match event {
+                    egui::Event::PointerButton { pos, button, pressed, .. } => {
+                        let code = match button {
+                            egui::PointerButton::Primary => 0,
+                            egui::PointerButton::Middle => 1,
+                            egui::PointerButton::Secondary => 2,
+                            _ => continue,
+                        };
+                        if *pressed && screen.contains(*pos) {
+                            self.mouse_button = Some(code);
+                            report(code, cell_at(*pos), false);
+                        } else if !*pressed && self.mouse_button == Some(code) {
+                            // Releases outside the screen still end the press, at the nearest cell
+                            self.mouse_button = None;
+                            report(code, cell_at(screen.clamp(*pos)), true);
+                        }
+                    }
+                    egui::Event::PointerMoved(pos) if screen.contains(*pos) => {
+                        let cell = cell_at(*pos);
+                        if self.mouse_cell == Some(cell) {
+                            continue;
+                        }
+                        self.mouse_cell = Some(cell);
+                        match (mode, self.mouse_button) {
+                            (MouseMode::Drag | MouseMode::Any, Some(button)) => report(32 + button, cell, false),
+                            (MouseMode::Any, None) => report(32 + 3, cell, false),
+                            _ => {}
+                        }
+                    }
+                    egui::Event::MouseWheel { delta, .. } if delta.y != 0.0 => {
+                        if let Some(pos) = i.pointer.hover_pos().filter(|pos| screen.contains(*pos)) {
+                            report(if delta.y > 0.0 { 64 } else { 65 }, cell_at(pos), false);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+        if !out.is_empty() {
+            let _ = self.writer.lock().write_all(&out);
+        }
+        true
+    }
+
+    /// Sends a focus event if focus changed and the program enabled mode 1004.
+    fn report_focus(&mut self, focused: bool) {
+        if std::mem::replace(&mut self.input_focused, focused) == focused {
+            return;
+        }
+        if self.state.lock().focus_reporting {
+            let _ = self.writer.lock().write_all(if focused { b"\x1b[I" } else { b"\x1b[O" });
+        }
+    }
+
+    /// Reports once that the shell has exited, with its exit code when available.
+    fn report_exit(&mut self, control: &mut Vec<AppCommand>) {
+        let mut state = self.state.lock();
+        let Some(exited) = state.exited.filter(|_| state.exit_reported.is_none()) else { return };
+        let code = match self.session.child.lock().as_mut().map(|child| child.try_wait()) {
+            Some(Ok(Some(status))) => Some(status.exit_code()),
+            // The PTY can close just before the process is reaped; give it a moment
+            Some(Ok(None)) if exited.elapsed() < Duration::from_secs(2) => return,
+            _ => None,
+        };
+        state.exit_reported = Some(code);
+        state.mark_dirty();
+        let name = state.title.clone().unwrap_or_else(|| self.title.clone());
+        drop(state);
+        control.push(AppCommand::notify(
+            match code {
+                Some(code) => format!("{} exited with status {}", name, code),
+                None => format!("{} exited", name),
+            },
+            if code == Some(0) { NotificationLevel::Info } else { NotificationLevel::Warning },
+        ));
+    }
+
+    /// Replaces the session with a freshly spawned shell, keeping the tab in place.
+    fn restart(&mut self) -> anyhow::Result<()> {
+        let mut fresh = create_terminal_tab(self.ctx.clone(), self.settings.clone(), self.run.as_deref(), self.cwd.as_deref())?;
+        self.session.shutdown();
+        fresh.focused = self.focused;
+        fresh.focus_input = true;
+        *self = fresh;
+        Ok(())
+    }
+
+    /// Reacts to a bell rung since the last frame, as configured.
+    fn bell(&mut self, control: &mut Vec<AppCommand>) {
+        let (rang, title) = {
+            let mut state = self.state.lock();
+            (state.bell_pending.take(), state.title.clone())
+        };
+        let mode = self.settings.lock().bell;
+        let Some(rang) = rang.filter(|_| mode != BellMode::None) else { return };
+
+        if mode == BellMode::Audible {
+            let mut stderr = std::io::stderr();
+            let _ = stderr.write_all(b"\x07").and_then(|_| stderr.flush());
+        }
+        // Programs often ring several times in a row; report a burst only once
+        let repeated = self.last_bell.is_some_and(|last| rang.duration_since(last) < Duration::from_secs(1));
+        if !self.focused && !repeated {
+            control.push(AppCommand::notify(
+                format!("Bell in {}", title.as_deref().unwrap_or(&self.title)),
+                NotificationLevel::Info,
+            ));
+        }
+        self.last_bell = Some(rang);
+    }
+
+    /// Search bar drawn over the top-right corner of the terminal.
+    fn search_ui(&mut self, ui: &mut Ui, area: Rect) {
+        let Some(search) = &mut self.search else { return };
+        let field_id = egui::Id::new("terminal_search").with(Arc::as_ptr(&self.state));
+        let mut close = false;
+
+        if ui.memory(|m| m.has_focus(field_id)) {
+            // Take these before the text field sees them: Enter would otherwise drop its focus
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, Key::Enter)) {
+                search.step(false);
+            }
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, Key::Enter)) {
+                search.step(true);
+            }
+            if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, Key::Escape))
+                || crate::keymap::consume(ui.ctx(), "terminal.search")
+            {
+                close = true;
+            }
+        }
+
+        let bar_rect = Rect::from_min_size(area.right_top() + Vec2::new(-360.0, 4.0), Vec2::new(352.0, 28.0));
+        let mut bar = ui.new_child(egui::UiBuilder::new().max_rect(bar_rect));
+        egui::Frame::popup(bar.style()).inner_margin(4.0).show(&mut bar, |ui| {
+            ui.horizontal(|ui| {
+                let field = ui.add(
+                    egui::TextEdit::singleline(&mut search.query)
+                        .id(field_id)
+                        .hint_text("Search scrollback")
+                        .desired_width(150.0),
+                );
+                if std::mem::take(&mut search.focus) {
+                    field.request_focus();
+                }
+                let status = match (search.query.is_empty(), search.matches.len()) {
+                    (true, _) => String::new(),
+                    (false, 0) => "No results".to_string(),
+                    (false, n) => format!("{}/{}", search.current + 1, n),
+                };
+                ui.label(egui::RichText::new(status).weak());
+                ui.toggle_value(&mut search.case_sensitive, "Aa").on_hover_text("Match case");
+                if ui.small_button("⬆").on_hover_text("Previous match (Shift+Enter)").clicked() {
+                    search.step(false);
+                }
+                if ui.small_button("⬇").on_hover_text("Next match (Enter)").clicked() {
+                    search.step(true);
+                }
+                if ui.small_button("✖").on_hover_text("Close (Esc)").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+        if close {
+            self.search = None;
+            self.focus_input = true;
+        }
+    }
+
+    /// Describes the screen to assistive technology through the focused input
+    /// widget. The text is only rebuilt when the terminal state has changed.
+    fn accessibility_ui(&mut self, ui: &Ui, input_response: &egui::Response) {
+        let enabled = ui.ctx().options(|o| o.screen_reader) || self.settings.lock().accessibility_text;
+        if !enabled {
+            self.accessible_text = None;
+            return;
+        }
+
+        let state = self.state.lock();
+        if self.accessible_text.as_ref().map(|(revision, ..)| *revision) != Some(state.revision) {
+            let (text, cursor) = state.screen_text();
+            self.accessible_text = Some((state.revision, text, cursor));
+        }
+        let (row, col) = (state.cursor_row, state.cursor_col);
+        drop(state);
+
+        let Some((_, text, cursor)) = &self.accessible_text else { return };
+        let cursor_char = text.chars().nth(*cursor).filter(|c| !c.is_whitespace());
+        input_response.widget_info(|| egui::WidgetInfo {
+            label: Some(match cursor_char {
+                Some(c) => format!("{}, cursor at row {} column {} on '{}'", self.title, row + 1, col + 1, c),
+                None => format!("{}, cursor at row {} column {}", self.title, row + 1, col + 1),
+            }),
+            current_text_value: Some(text.clone()),
+            text_selection: Some(*cursor..=*cursor),
+            ..egui::WidgetInfo::new(egui::WidgetType::TextEdit)
+        });
+    }
+
+    /// Signals the foreground process group of the PTY: the running program, or
+    /// the shell itself when it is sitting at the prompt.
+    #[cfg(unix)]
+    fn send_signal(&self, signal: Signal) -> std::io::Result<()> {
+        let shell_pid = self.session.child.lock().as_ref().and_then(|child| child.process_id());
+        let group = self.master.lock().process_group_leader()
+            .or(shell_pid.map(|pid| pid as libc::pid_t))
+            .ok_or_else(|| std::io::Error::other("no process is running"))?;
+        // SAFETY: killpg only reads its integer arguments.
+        if unsafe { libc::killpg(group, signal.number()) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    /// Windows has no signals: SIGINT becomes a Ctrl+C on the console, while
+    /// SIGTERM and SIGKILL both terminate the shell process.
+    #[cfg(not(unix))]
+    fn send_signal(&self, signal: Signal) -> std::io::Result<()> {
+        match signal {
+            Signal::Interrupt => {
+                let mut writer = self.writer.lock();
+                writer.write_all(b"\x03")?;
+                writer.flush()
+            }
+            Signal::Terminate | Signal::Kill => match self.session.child.lock().as_mut() {
+                Some(child) => child.kill(),
+                None => Err(std::io::Error::other("no process is running")),
+            },
+        }
+    }
+
+    /// Sends pasted text to the PTY.
+    fn send_paste(&self, text: &str) {
+        let bracketed = self.state.lock().bracketed_paste;
+        let _ = self.writer.lock().write_all(paste_payload(text, bracketed).as_bytes());
+    }
+
+    /// Pastes the system clipboard, asking first when it spans multiple lines.
+    fn paste_clipboard(&mut self) -> Result<(), arboard::Error> {
+        let text = arboard::Clipboard::new()?.get_text()?;
+        let confirm = {
+            let settings = self.settings.lock();
+            settings.confirm_multiline_paste && !settings.skip_paste_confirmation
+        };
+        if confirm && text.contains('\n') {
+            self.pending_paste = Some(text);
+        } else {
+            self.send_paste(&text);
+        }
+        Ok(())
+    }
+
+    fn paste_confirmation_ui(&mut self, ctx: &egui::Context) {
+        let Some(text) = self.pending_paste.clone() else { return };
+        let mut decision = None;
+        egui::Window::new("Confirm Multiline Paste")
+            .id(egui::Id::new("terminal_paste_confirm").with(Arc::as_ptr(&self.state)))
+            .collapsible(false)
+            .resizable(true)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                let lines: Vec<&str> = text.lines().collect();
+                ui.label(format!("You are about to paste {} lines into the terminal:", lines.len()));
+                egui::ScrollArea::vertical()
+                    .id_salt("terminal_paste_preview")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for line in &lines {
+                            ui.monospace(*line);
+                        }
+                    });
+                ui.separator();
+                let mut skip = self.settings.lock().skip_paste_confirmation;
+                if ui.checkbox(&mut skip, "Don't ask again this session").changed() {
+                    self.settings.lock().skip_paste_confirmation = skip;
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Paste").clicked() { decision = Some(true); }
+                    if ui.button("Cancel").clicked() { decision = Some(false); }
+                });
+            });
+        match decision {
+            Some(true) => {
+                self.send_paste(&text);
+                self.pending_paste = None;
+            }
+            Some(false) => self.pending_paste = None,
+            None => {}
+        }
+    }
+}
+
+impl TabInstance for TerminalTab {
+    fn title(&self) -> WidgetText {
+        let title = self.state.lock().title.clone().unwrap_or_else(|| self.title.clone());
+        if self.session.recorder.lock().active.is_some() {
+            format!("⏺ {}", title).into()
+        } else {
+            title.into()
+        }
+    }
+
+    fn status(&self) -> Option<TabStatus> {
+        let (cols, rows) = self.last_size;
+        let (history, alt_screen) = {
+            let state = self.state.lock();
+            (state.history.len(), state.is_alt_screen)
+        };
+        let scrollback = if alt_screen {
+            "Alternate screen".to_string()
+        } else if self.scrolled_up > 0 {
+            format!("Scrollback: {} of {} lines up", self.scrolled_up.min(history), history)
+        } else {
+            format!("Scrollback: {} lines", history)
+        };
+        Some(TabStatus { left: vec![format!("{}×{}", cols, rows), scrollback], right: Vec::new() })
+    }
+
+    fn on_context_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        let is_alt_screen = self.state.lock().is_alt_screen;
+        if ui.add_enabled(is_alt_screen, egui::Button::new("👁 Peek Primary Screen"))
+            .on_disabled_hover_text("Available while a full-screen program is running")
+            .clicked()
+        {
+            self.peek_primary = true;
+            ui.close();
+        }
+        if ui.button("📋 Paste").clicked() {
+            if let Err(e) = self.paste_clipboard() {
+                control.push(AppCommand::notify(
+                    format!("Cannot read the clipboard: {}", e),
+                    NotificationLevel::Error,
+                ));
+            }
+            self.focus_input = true;
+            ui.close();
+        }
+        let running = self.session.child_running();
+        ui.add_enabled_ui(running, |ui| {
+            ui.menu_button("⚡ Send Signal", |ui| {
+                for signal in Signal::ALL {
+                    if ui.button(signal.label()).clicked() {
+                        if let Err(e) = self.send_signal(signal) {
+                            control.push(AppCommand::notify(
+                                format!("Cannot send {}: {}", signal.label(), e),
+                                NotificationLevel::Error,
+                            ));
+                        }
+                        ui.close();
+                    }
+                }
+            });
+        }).response.on_disabled_hover_text("The shell has exited");
+        if ui.button("🔄 Restart").clicked() {
+            if let Err(e) = self.restart() {
+                control.push(AppCommand::notify(
+                    format!("Cannot restart the terminal: {}", e),
+                    NotificationLevel::Error,
+                ));
+            }
+            ui.close();
+        }
+        ui.separator();
+
+        let mut recorder = self.session.recorder.lock();
+        if let Some(recording) = &recorder.active {
+            let path = recording.path.clone();
+            if ui.button("⏹ Stop Recording").clicked() {
+                recorder.stop();
+                control.push(AppCommand::notify(
+                    format!("Terminal output saved to {}", path.display()),
+                    NotificationLevel::Success,
+                ));
+                ui.close();
+            }
+        } else {
+            // Don't block the reader thread while the file dialog is open
+            drop(recorder);
+            ui.checkbox(&mut self.record_plain_text, "Strip ANSI escapes");
+            if ui.button("⏺ Start Recording…").clicked() {
+                ui.close();
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name("terminal.log")
+                    .save_file()
+                {
+                    if let Err(e) = self.session.recorder.lock().start(path.clone(), self.record_plain_text) {
+                        control.push(AppCommand::notify(
+                            format!("Cannot record to {}: {}", path.display(), e),
+                            NotificationLevel::Error,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    fn ui(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
+        if let Some(error) = self.session.recorder.lock().error.take() {
+            control.push(AppCommand::notify(error, NotificationLevel::Error));
+        }
+        self.bell(control);
+        self.report_exit(control);
+
+        let mut theme = self.settings.lock().theme.clone();
+        if theme == TerminalTheme::default() && !ui.visuals().dark_mode {
+            theme = TerminalTheme::light();
+        }
+        let font_id = FontId::monospace(self.font_size);
+        let char_size = ui.fonts_mut(|f| {
+            let width = f.glyph_width(&font_id, 'M');
+            let height = f.row_height(&font_id);
+            Vec2::new(width, height)
+        });
+
+        // 1. Calculate PTY size based on available area
+        let available_size = ui.available_size();
+        let cols = (available_size.x / char_size.x).floor() as usize;
+        let rows = (available_size.y / char_size.y).floor() as usize;
+        
+        if cols > 0 && rows > 0 && (cols != self.last_size.0 || rows != self.last_size.1) {
+            self.state.lock().resize(rows, cols);
+            let _ = self.master.lock().resize(PtySize {
+                rows: rows as u16,
+                cols: cols as u16,
+                pixel_width: 0,
+                pixel_height: 0,
+            });
+            self.last_size = (cols, rows);
+        }
+
+        if self.peek_primary {
+            // Leave the peek automatically once the program exits the alt screen
+            if self.state.lock().is_alt_screen {
+                self.primary_peek_ui(ui, &theme, char_size, &font_id);
+                ui.ctx().request_repaint();
+                return;
+            }
+            self.peek_primary = false;
+        }
+
+        let state_lock = self.state.lock();
+        let history_len = state_lock.history.len();
+        let grid_len = state_lock.rows;
+        let total_rows = history_len + grid_len;
+        let mut scroll_to = None;
+        if let Some(search) = &mut self.search {
+            search.update(&state_lock);
+            if std::mem::take(&mut search.reveal) {
+                if let Some(found) = search.current_match() {
+                    // Center the match vertically
+                    scroll_to = Some((found.row as f32 * char_size.y - available_size.y / 2.0).max(0.0));
+                }
+            }
+        }
+        drop(state_lock);
+
+        let mut output_to_write = String::new();
+        let area = ui.available_rect_before_wrap();
+        let detect_urls = self.settings.lock().detect_urls;
+
+        // While the program tracks the mouse the wheel goes to it instead of the scrollback
+        let mouse_tracking = self.state.lock().mouse_mode != MouseMode::Off && !ui.input(|i| i.modifiers.shift);
+
+        // 2. Use ScrollArea for native scrolling and scrollbar
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .id_salt("terminal_scroll")
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .enable_scrolling(!mouse_tracking);
+        if let Some(offset) = scroll_to {
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+        scroll_area
+            .show_viewport(ui, |ui, viewport| {
+                let content_size = Vec2::new(available_size.x, total_rows as f32 * char_size.y);
+                let (rect, response) = ui.allocate_at_least(content_size, Sense::click_and_drag());
+                self.scrolled_up = ((content_size.y - viewport.max.y) / char_size.y).round().max(0.0) as usize;
+                
+                // 1. Invisible Input Overlay (Bottom Layer)
+                // We place this BEFORE the background is drawn so that the terminal's 
+                // background covers the TextEdit's default blinking cursor.
                 let input_rect = viewport.translate(rect.min.to_vec2());
                 
                 // Use a scope to make the TextEdit truly invisible (cursor, selection, etc.)
@@ -600,15 +2123,26 @@ impl TabInstance for TerminalTab {
                 if response.clicked() {
                     ui.memory_mut(|m| m.request_focus(response.id));
                 }
+                if std::mem::take(&mut self.focus_input) {
+                    input_response.request_focus();
+                }
+
+                let screen = Rect::from_min_size(
+                    rect.min + Vec2::new(0.0, history_len as f32 * char_size.y),
+                    Vec2::new(cols as f32 * char_size.x, grid_len as f32 * char_size.y),
+                )
+                .intersect(input_rect);
+                let reporting_mouse = self.report_mouse(ui, screen, char_size);
+                self.report_focus(input_response.has_focus());
 
                 // 2. Render Background (Middle Layer)
                 // This will overwrite/cover anything drawn by the TextEdit above.
                 let painter = ui.painter_at(rect);
-                painter.rect_filled(viewport.translate(rect.min.to_vec2()), 0.0, TERM_BG);
+                painter.rect_filled(viewport.translate(rect.min.to_vec2()), 0.0, theme.background);
 
                 // 3. Handle Input Events
                 if input_response.has_focus() || input_response.lost_focus() {
-                    if let Some(pos) = input_response.interact_pointer_pos() {
+                    if let Some(pos) = input_response.interact_pointer_pos().filter(|_| !reporting_mouse) {
                         let rel_pos = pos - rect.min;
                         let col = (rel_pos.x / char_size.x).floor() as usize;
                         let row_idx = (rel_pos.y / char_size.y).floor() as usize;
@@ -629,11 +2163,46 @@ impl TabInstance for TerminalTab {
                     }
                 }
 
+                if input_response.has_focus() {
+                    let ctx = ui.ctx();
+                    // egui zooms the whole UI on Ctrl+Plus/Minus/0 at the end of the frame; taking
+                    // the keys here leaves them to the terminal while its input has focus.
+                    // Ctrl+= is Ctrl+Plus without Shift on most layouts
+                    let zoom_key = |key| ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, key));
+                    if crate::keymap::consume(ctx, "terminal.zoom_in") | zoom_key(Key::Plus) | zoom_key(Key::Equals) {
+                        self.font_size = (self.font_size + 1.0).clamp(*FONT_SIZE_RANGE.start(), *FONT_SIZE_RANGE.end());
+                    }
+                    if crate::keymap::consume(ctx, "terminal.zoom_out") | zoom_key(Key::Minus) {
+                        self.font_size = (self.font_size - 1.0).clamp(*FONT_SIZE_RANGE.start(), *FONT_SIZE_RANGE.end());
+                    }
+                    if crate::keymap::consume(ctx, "terminal.zoom_reset") | zoom_key(Key::Num0) {
+                        self.font_size = DEFAULT_FONT_SIZE;
+                    }
+                }
+                if input_response.has_focus() && crate::keymap::consume(ui.ctx(), "terminal.search") {
+                    self.search.get_or_insert_with(TerminalSearch::default).focus = true;
+                }
+                if input_response.has_focus() && crate::keymap::consume(ui.ctx(), "terminal.paste") {
+                    // The platform may also report this keystroke as a Paste event; drop it so the text isn't sent twice
+                    ui.ctx().input_mut(|i| i.events.retain(|e| !matches!(e, egui::Event::Paste(_))));
+                    if let Err(e) = self.paste_clipboard() {
+                        control.push(AppCommand::notify(
+                            format!("Cannot read the clipboard: {}", e),
+                            NotificationLevel::Error,
+                        ));
+                    }
+                }
+
                 if input_response.has_focus() || input_response.lost_focus() {
                     let mut writer = self.writer.lock();
                     let state = self.state.lock();
                     let is_app_mode = state.application_cursor;
+                    let bracketed_paste = state.bracketed_paste;
                     drop(state);
+                    let confirm_paste = {
+                        let settings = self.settings.lock();
+                        settings.confirm_multiline_paste && !settings.skip_paste_confirmation
+                    };
         
                     let mut text_to_copy = None;
                     ui.input(|i| {
@@ -655,7 +2224,13 @@ impl TabInstance for TerminalTab {
                                     if !is_handled_control { output_to_write.push_str(&text.replace("\n", "\r")); }
                                 }
                                 egui::Event::Paste(text) => {
-                                    if !self.is_composing { output_to_write.push_str(&text.replace("\n", "\r")); }
+                                    if !self.is_composing {
+                                        if confirm_paste && text.contains('\n') {
+                                            self.pending_paste = Some(text.clone());
+                                        } else {
+                                            output_to_write.push_str(&paste_payload(text, bracketed_paste));
+                                        }
+                                    }
                                 }
                                 egui::Event::Copy => {
                                     if let (Some(start), Some(end)) = (self.selection_start, self.selection_end) {
@@ -672,7 +2247,7 @@ impl TabInstance for TerminalTab {
                                             for c in c_start..c_end {
                                                 if c < cells.len() {
                                                     let cell = &cells[c];
-                                                    if !cell.is_wide_continuation { text.push(cell.c); }
+                                                    if !cell.is_wide_continuation { text.push_str(&cell.text()); }
                                                 } else { text.push(' '); }
                                             }
                                             if r != e.0 { text.push('\n'); }
@@ -758,69 +2333,338 @@ impl TabInstance for TerminalTab {
                         }
                     }
 
-                    // Background and Text rendering
-                    let mut c_idx = 0;
-                    while c_idx < cells.len().min(cols) {
-                        let cell = &cells[c_idx];
-                        let mut bg = cell.bg;
-                        if cell.inverse { bg = if cell.fg == Color32::TRANSPARENT { TERM_FG } else { cell.fg }; }
-                        let start_x = c_idx;
-                        c_idx += 1;
-                        while c_idx < cells.len().min(cols) {
-                            let next = &cells[c_idx];
-                            let mut next_bg = next.bg;
-                            if next.inverse { next_bg = if next.fg == Color32::TRANSPARENT { TERM_FG } else { next.fg }; }
-                            if next_bg != bg { break; }
-                            c_idx += 1;
-                        }
-                        if bg != Color32::TRANSPARENT && bg != TERM_BG {
-                            let bg_rect = Rect::from_min_size(row_pos + Vec2::new(start_x as f32 * char_size.x, 0.0), Vec2::new((c_idx - start_x) as f32 * char_size.x, char_size.y));
-                            painter.rect_filled(bg_rect, 0.0, bg);
+                    if let Some(search) = &self.search {
+                        let current = search.current_match();
+                        let first = search.matches.partition_point(|m| m.row < row_idx);
+                        for found in search.matches[first..].iter().take_while(|m| m.row == row_idx) {
+                            let color = if Some(*found) == current {
+                                Color32::from_rgba_unmultiplied(255, 150, 0, 140)
+                            } else {
+                                Color32::from_rgba_unmultiplied(230, 200, 60, 70)
+                            };
+                            let hit_rect = Rect::from_min_size(
+                                row_pos + Vec2::new(found.start_col as f32 * char_size.x, 0.0),
+                                Vec2::new((found.end_col - found.start_col) as f32 * char_size.x, char_size.y),
+                            );
+                            painter.rect_filled(hit_rect, 0.0, color);
                         }
                     }
 
-                    for (c_idx, cell) in cells.iter().enumerate().take(cols) {
-                        if cell.is_wide_continuation || cell.c == ' ' { continue; }
-                        let mut fg = cell.fg;
-                        if cell.inverse { fg = if cell.bg == Color32::TRANSPARENT { TERM_BG } else { cell.bg }; }
-                        if fg == Color32::TRANSPARENT { fg = TERM_FG; }
-                        let cell_pos = row_pos + Vec2::new(c_idx as f32 * char_size.x, 0.0);
-                        let mut job = LayoutJob::default();
-                        job.append(&cell.c.to_string(), 0.0, TextFormat { font_id: font_id.clone(), color: fg, ..Default::default() });
-                        painter.galley(cell_pos, ui.fonts_mut(|f| f.layout_job(job)), Color32::TRANSPARENT);
-                    }
+                    paint_cells(&painter, &theme, cells, row_pos, cols, char_size, &font_id);
 
-                    if state.cursor_visible && (row_idx == (history.len() + state.cursor_row)) {
+                    // Blinking cursors are shown for the first half of every second
+                    let blink_on = !state.cursor_blink || ui.input(|i| i.time).fract() < 0.5;
+                    let is_cursor_row = row_idx == history.len() + state.cursor_row;
+                    if let Some(code) = state.exit_reported.filter(|_| is_cursor_row) {
+                        let banner = match code {
+                            Some(code) => format!("[Process exited with status {}]", code),
+                            None => "[Process exited]".to_string(),
+                        };
+                        let banner_pos = row_pos + Vec2::new(state.cursor_col.min(cols) as f32 * char_size.x, 0.0);
+                        painter.text(banner_pos, egui::Align2::LEFT_TOP, banner, font_id.clone(), theme.foreground.gamma_multiply(0.5));
+                    } else if state.cursor_visible && blink_on && is_cursor_row {
                         let cursor_pos = row_pos + Vec2::new(state.cursor_col as f32 * char_size.x, 0.0);
-                        painter.rect_filled(Rect::from_min_size(cursor_pos, char_size), 0.0, Color32::from_gray(200).linear_multiply(0.5));
+                        let cursor_rect = match state.cursor_shape {
+                            CursorShape::Block => Rect::from_min_size(cursor_pos, char_size),
+                            CursorShape::Underline => Rect::from_min_size(cursor_pos + Vec2::new(0.0, char_size.y - 2.0), Vec2::new(char_size.x, 2.0)),
+                            CursorShape::Bar => Rect::from_min_size(cursor_pos, Vec2::new(2.0, char_size.y)),
+                        };
+                        let color = if state.cursor_shape == CursorShape::Block { theme.cursor.linear_multiply(0.5) } else { theme.cursor };
+                        painter.rect_filled(cursor_rect, 0.0, color);
                     }
                 }
+
+                // Visual bell: fade a light overlay out over a short moment
+                const BELL_FLASH: Duration = Duration::from_millis(150);
+                if let Some(elapsed) = self.last_bell.map(|t| t.elapsed()).filter(|e| *e < BELL_FLASH) {
+                    if self.settings.lock().bell == BellMode::Visual {
+                        let alpha = 60.0 * (1.0 - elapsed.as_secs_f32() / BELL_FLASH.as_secs_f32());
+                        painter.rect_filled(viewport.translate(rect.min.to_vec2()), 0.0, Color32::from_white_alpha(alpha as u8));
+                    }
+                }
+
+                // Hyperlink under the pointer: underline it and open it on click
+                if let Some(pos) = input_response.hover_pos().or(response.hover_pos()) {
+                    let rel_pos = pos - rect.min;
+                    let row_idx = (rel_pos.y / char_size.y).floor() as usize;
+                    let col = (rel_pos.x / char_size.x).floor() as usize;
+                    let cells = if row_idx < history.len() { history.get(row_idx) } else { grid.get(row_idx - history.len()) };
+                    if let Some((range, url)) = cells.and_then(|cells| link_at(&state, cells, col, detect_urls)) {
+                        let y = rect.min.y + (row_idx + 1) as f32 * char_size.y - 1.0;
+                        let x = |col: usize| rect.min.x + col as f32 * char_size.x;
+                        painter.hline(x(range.start)..=x(range.end), y, egui::Stroke::new(1.0, theme.foreground));
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                        if input_response.clicked() || response.clicked() {
+                            control.push(AppCommand::OpenUrl(url));
+                        }
+                    }
+                }
+                drop(state);
+
+                self.accessibility_ui(ui, &input_response);
             });
 
+        self.search_ui(ui, area);
+        self.paste_confirmation_ui(ui.ctx());
+
         ui.ctx().request_repaint();
     }
 
+    fn on_focus(&mut self) {
+        self.focus_input = true;
+        self.focused = true;
+    }
+
+    fn on_blur(&mut self) {
+        // A drag can't continue once another tab has focus
+        self.drag_start = None;
+        self.focused = false;
+        // The input won't be drawn again to notice it lost focus
+        self.report_focus(false);
+    }
+
+    fn on_close(&mut self) {
+        // Don't wait for the last clone to be dropped: stop the shell right away
+        self.session.shutdown();
+    }
+
+    /// Only interactive shells come back with the session; re-running a
+    /// command on startup could have side effects the user didn't ask for.
+    fn descriptor(&self) -> Option<TabDescriptor> {
+        if self.run.is_some() {
+            return None;
+        }
+        let mut state = toml::Table::new();
+        if let Some(cwd) = &self.cwd {
+            state.insert("cwd".to_string(), cwd.to_string_lossy().into_owned().into());
+        }
+        Some(TabDescriptor::new(crate::plugins::PLUGIN_NAME_TERMINAL, state))
+    }
+
     fn box_clone(&self) -> Box<dyn TabInstance> {
         Box::new(self.clone())
     }
 }
 
-pub struct TerminalPlugin;
+#[derive(Default)]
+pub struct TerminalPlugin {
+    settings: Arc<Mutex<TerminalSettings>>,
+    config: PluginConfig,
+}
 
 impl Plugin for TerminalPlugin {
     fn name(&self) -> &str { crate::plugins::PLUGIN_NAME_TERMINAL }
 
+    fn load_config(&mut self, config: &PluginConfig) {
+        let mut settings = self.settings.lock();
+        let skip_paste_confirmation = settings.skip_paste_confirmation;
+        *settings = TerminalSettings { skip_paste_confirmation, ..Default::default() };
+        if let Some(confirm) = config.get_bool("confirm_multiline_paste") {
+            settings.confirm_multiline_paste = confirm;
+        }
+        if let Some(shell) = config.get_str("shell") {
+            settings.shell = shell.to_string();
+        }
+        // Either a string split on whitespace or an array, for arguments containing spaces
+        match config.values.get("shell_args") {
+            Some(toml::Value::String(args)) => settings.shell_args = args.clone(),
+            Some(toml::Value::Array(args)) => {
+                settings.shell_args = args.iter().filter_map(toml::Value::as_str).collect::<Vec<_>>().join(" ");
+            }
+            _ => {}
+        }
+        if let Some(dir) = config.get_str("working_dir") {
+            settings.working_dir = dir.to_string();
+        }
+        if let Some(env) = config.get_table("env") {
+            settings.env = env
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                .collect();
+        }
+        if let Some(enabled) = config.get_bool("accessibility_text") {
+            settings.accessibility_text = enabled;
+        }
+        if let Some(enabled) = config.get_bool("detect_urls") {
+            settings.detect_urls = enabled;
+        }
+        if let Some(bell) = config.get_str("bell").and_then(BellMode::from_config) {
+            settings.bell = bell;
+        }
+        // `theme` picks a preset; `[terminal.colors]` overrides single colors as "#rrggbb"
+        if let Some(theme) = config.get_str("theme").and_then(TerminalTheme::preset) {
+            settings.theme = theme;
+        }
+        if let Some(colors) = config.get_table("colors") {
+            let color = |key: &str| colors.get(key).and_then(toml::Value::as_str).and_then(theme::parse_hex);
+            let theme = &mut settings.theme;
+            for (key, slot) in [("foreground", &mut theme.foreground), ("background", &mut theme.background), ("cursor", &mut theme.cursor)] {
+                if let Some(value) = color(key) {
+                    *slot = value;
+                }
+            }
+            if let Some(palette) = colors.get("palette").and_then(toml::Value::as_array) {
+                for (slot, value) in theme.palette.iter_mut().zip(palette) {
+                    if let Some(value) = value.as_str().and_then(theme::parse_hex) {
+                        *slot = value;
+                    }
+                }
+            }
+        }
+        self.config = config.clone();
+    }
+
+    /// Colors are exported as `[terminal.colors]`, which overrides any `theme` preset on load
+    fn save_config(&self) -> Option<toml::Table> {
+        let settings = self.settings.lock();
+        let mut table = toml::Table::new();
+        table.insert("confirm_multiline_paste".to_string(), settings.confirm_multiline_paste.into());
+        table.insert("shell".to_string(), settings.shell.clone().into());
+        table.insert("shell_args".to_string(), settings.shell_args.clone().into());
+        table.insert("working_dir".to_string(), settings.working_dir.clone().into());
+        let env: toml::Table = settings.env.iter()
+            .filter(|(key, _)| !key.is_empty())
+            .map(|(key, value)| (key.clone(), value.clone().into()))
+            .collect();
+        table.insert("env".to_string(), env.into());
+        table.insert("accessibility_text".to_string(), settings.accessibility_text.into());
+        table.insert("detect_urls".to_string(), settings.detect_urls.into());
+        table.insert("bell".to_string(), settings.bell.label().to_lowercase().into());
+        let current = &settings.theme;
+        let mut colors = toml::Table::new();
+        for (key, color) in [("foreground", current.foreground), ("background", current.background), ("cursor", current.cursor)] {
+            colors.insert(key.to_string(), theme::to_hex(color).into());
+        }
+        let palette = current.palette.iter().map(|color| toml::Value::from(theme::to_hex(*color))).collect::<Vec<_>>();
+        colors.insert("palette".to_string(), palette.into());
+        table.insert("colors".to_string(), colors.into());
+        Some(table)
+    }
+
+    fn keybindings(&self) -> Vec<crate::keymap::Binding> {
+        use crate::keymap::Binding;
+        vec![
+            Binding::new("terminal.paste", "Terminal", "Paste from Clipboard", egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::V),
+            Binding::new("terminal.search", "Terminal", "Search Scrollback", egui::Modifiers::COMMAND, egui::Key::F),
+            Binding::new("terminal.zoom_in", "Terminal", "Zoom In", egui::Modifiers::COMMAND, egui::Key::Plus),
+            Binding::new("terminal.zoom_out", "Terminal", "Zoom Out", egui::Modifiers::COMMAND, egui::Key::Minus),
+            Binding::new("terminal.zoom_reset", "Terminal", "Reset Zoom", egui::Modifiers::COMMAND, egui::Key::Num0),
+        ]
+    }
+
+    fn on_settings_ui(&mut self, ui: &mut Ui) {
+        let mut settings = self.settings.lock();
+        ui.checkbox(&mut settings.confirm_multiline_paste, self.config.label("Confirm before pasting multiple lines", "confirm_multiline_paste"));
+        ui.horizontal(|ui| {
+            ui.label(self.config.label("Shell", "shell"));
+            ui.add(egui::TextEdit::singleline(&mut settings.shell).hint_text("default").desired_width(200.0));
+        });
+        ui.horizontal(|ui| {
+            ui.label(self.config.label("Shell arguments", "shell_args"));
+            ui.add(egui::TextEdit::singleline(&mut settings.shell_args).hint_text("e.g. -l").desired_width(200.0));
+        });
+        ui.horizontal(|ui| {
+            ui.label(self.config.label("Working directory", "working_dir"));
+            ui.add(egui::TextEdit::singleline(&mut settings.working_dir).hint_text("current directory").desired_width(200.0));
+            if ui.button("📂").on_hover_text("Browse").clicked() {
+                if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                    settings.working_dir = dir.display().to_string();
+                }
+            }
+        });
+        ui.collapsing(self.config.label("Environment variables", "env"), |ui| {
+            let mut remove = None;
+            for (i, (key, value)) in settings.env.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(key).hint_text("NAME").desired_width(120.0));
+                    ui.label("=");
+                    ui.add(egui::TextEdit::singleline(value).desired_width(200.0));
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                settings.env.remove(i);
+            }
+            if ui.button("➕ Add Variable").clicked() {
+                settings.env.push((String::new(), String::new()));
+            }
+        });
+        ui.checkbox(&mut settings.accessibility_text, self.config.label("Always expose screen text to screen readers", "accessibility_text"))
+            .on_hover_text("Enabled automatically when a screen reader is detected");
+        ui.checkbox(&mut settings.detect_urls, self.config.label("Make plain URLs in the output clickable", "detect_urls"));
+        ui.horizontal(|ui| {
+            ui.label(self.config.label("Bell", "bell"));
+            for mode in BellMode::ALL {
+                let radio = ui.radio_value(&mut settings.bell, mode, mode.label());
+                if mode == BellMode::Audible {
+                    radio.on_hover_text("Rings the console Verbium was started from");
+                }
+            }
+        });
+        ui.collapsing(self.config.label("Colors", "colors"), |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(self.config.label("Preset", "theme"));
+                for name in TerminalTheme::preset_names() {
+                    let preset = TerminalTheme::preset(name).unwrap_or_default();
+                    if ui.selectable_label(settings.theme == preset, name).clicked() {
+                        settings.theme = preset;
+                    }
+                }
+            });
+            let theme = &mut settings.theme;
+            ui.horizontal(|ui| {
+                for (label, color) in [("Foreground", &mut theme.foreground), ("Background", &mut theme.background), ("Cursor", &mut theme.cursor)] {
+                    ui.label(label);
+                    ui.color_edit_button_srgba(color).on_hover_text(theme::to_hex(*color));
+                }
+            });
+            for (start, label) in [(0, "Normal"), (8, "Bright")] {
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    for color in &mut theme.palette[start..start + 8] {
+                        ui.color_edit_button_srgba(color).on_hover_text(theme::to_hex(*color));
+                    }
+                });
+            }
+        });
+        if settings.skip_paste_confirmation && ui.button("Re-enable paste confirmation for this session").clicked() {
+            settings.skip_paste_confirmation = false;
+        }
+    }
+
     fn on_tab_menu(&mut self, ui: &mut Ui, control: &mut Vec<AppCommand>) {
         if ui.button("New Terminal").clicked() {
-            if let Ok(tab) = create_terminal_tab(ui.ctx().clone()) {
+            if let Ok(tab) = create_terminal_tab(ui.ctx().clone(), self.settings.clone(), None, None) {
                 control.push(AppCommand::OpenTab(Tab::new(Box::new(tab))));
             }
             ui.close();
         }
     }
+
+    fn try_run_command(&mut self, ctx: &egui::Context, command: &str, cwd: Option<&Path>) -> Option<Box<dyn TabInstance>> {
+        create_terminal_tab(ctx.clone(), self.settings.clone(), Some(command), cwd)
+            .ok()
+            .map(|tab| Box::new(tab) as Box<dyn TabInstance>)
+    }
+
+    fn restore_tab(&mut self, ctx: &egui::Context, state: &toml::Table) -> Option<Box<dyn TabInstance>> {
+        let cwd = state.get("cwd").and_then(toml::Value::as_str).map(Path::new).filter(|cwd| cwd.is_dir());
+        create_terminal_tab(ctx.clone(), self.settings.clone(), None, cwd)
+            .ok()
+            .map(|tab| Box::new(tab) as Box<dyn TabInstance>)
+    }
 }
 
-fn create_terminal_tab(ctx: egui::Context) -> anyhow::Result<TerminalTab> {
+/// Spawns the shell in a new PTY. When `run` is given, the shell executes that
+/// command and exits, leaving its output on screen.
+fn create_terminal_tab(
+    ctx: egui::Context,
+    settings: Arc<Mutex<TerminalSettings>>,
+    run: Option<&str>,
+    cwd: Option<&Path>,
+) -> anyhow::Result<TerminalTab> {
     let pty_system = native_pty_system();
     let pair = pty_system.openpty(PtySize {
         rows: 24,
@@ -830,38 +2674,101 @@ fn create_terminal_tab(ctx: egui::Context) -> anyhow::Result<TerminalTab> {
     })?;
 
     #[cfg(windows)]
-    let cmd = CommandBuilder::new("powershell.exe");
+    let default_shell = "powershell.exe";
     #[cfg(not(windows))]
-    let cmd = CommandBuilder::new("bash");
+    let default_shell = "bash";
+    let launch = settings.lock().clone();
+    let custom_shell = launch.shell.trim();
+    let mut cmd = if run.is_none() && !custom_shell.is_empty() {
+        CommandBuilder::new(custom_shell)
+    } else {
+        CommandBuilder::new(default_shell)
+    };
+    if run.is_none() {
+        cmd.args(launch.shell_args.split_whitespace());
+    }
+    for (key, value) in launch.env.iter().filter(|(key, _)| !key.trim().is_empty()) {
+        cmd.env(key.trim(), value);
+    }
 
-    let mut _child = pair.slave.spawn_command(cmd)?;
-    
-    let writer = pair.master.take_writer()?;
+    if let Some(run) = run {
+        #[cfg(windows)]
+        cmd.args(["-NoLogo", "-Command", run]);
+        #[cfg(not(windows))]
+        cmd.args(["-c", run]);
+    }
+    if let Some(cwd) = cwd {
+        cmd.cwd(cwd);
+    } else if !launch.working_dir.trim().is_empty() {
+        cmd.cwd(launch.working_dir.trim());
+    }
+
+    let child = pair.slave.spawn_command(cmd)?;
+
+    let writer = Arc::new(Mutex::new(pair.master.take_writer()?));
+    let writer_thread = writer.clone();
     let mut reader = pair.master.try_clone_reader()?;
     
     let state = Arc::new(Mutex::new(TerminalState::new(24, 80)));
     let s_thread = state.clone();
     let ctx_thread = ctx.clone();
+    let alive = Arc::new(AtomicBool::new(true));
+    let alive_thread = alive.clone();
+    let recorder = Arc::new(Mutex::new(Recorder::default()));
+    let recorder_thread = recorder.clone();
 
-    std::thread::spawn(move || {
+    let reader_thread = std::thread::spawn(move || {
         let mut buffer = [0u8; 8192];
         let mut parser = Parser::new();
-        while let Ok(n) = reader.read(&mut buffer) {
-            if n == 0 { break; }
-            {
+        while alive_thread.load(Ordering::SeqCst) {
+            let n = match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => {
+                    // Unless the tab is being closed, the shell went away on its own
+                    if alive_thread.load(Ordering::SeqCst) {
+                        s_thread.lock().exited = Some(Instant::now());
+                        ctx_thread.request_repaint();
+                    }
+                    break;
+                }
+                Ok(n) => n,
+            };
+            recorder_thread.lock().write(&buffer[..n]);
+            let replies = {
                 let mut s = s_thread.lock();
-                let mut handler = LogHandler { state: &mut *s };
+                let mut handler = LogHandler { state: &mut *s, replies: Vec::new() };
                 for byte in &buffer[..n] {
                     parser.advance(&mut handler, *byte);
                 }
+                handler.replies
+            };
+            if !replies.is_empty() {
+                let _ = writer_thread.lock().write_all(&replies);
             }
             ctx_thread.request_repaint();
         }
     });
 
+    let title = match run {
+        Some(run) => format!("▶ {}", run),
+        None => "Terminal".to_string(),
+    };
+
+    let session = Arc::new(TerminalSession {
+        alive,
+        child: Mutex::new(Some(child)),
+        reader_thread: Mutex::new(Some(reader_thread)),
+        reaper: Mutex::new(None),
+        recorder,
+    });
+
     Ok(TerminalTab {
+        title,
+        run: run.map(str::to_string),
+        cwd: cwd.map(Path::to_path_buf),
+        settings,
+        session,
         state,
-        writer: Arc::new(Mutex::new(writer)),
+        writer,
         master: Arc::new(Mutex::new(pair.master)),
         last_size: (80, 24),
         ctx,
@@ -870,10 +2777,317 @@ fn create_terminal_tab(ctx: egui::Context) -> anyhow::Result<TerminalTab> {
         selection_start: None,
         selection_end: None,
         drag_start: None,
+        pending_paste: None,
+        record_plain_text: true,
+        peek_primary: false,
+        accessible_text: None,
+        focus_input: false,
+        search: None,
+        focused: false,
+        last_bell: None,
+        font_size: DEFAULT_FONT_SIZE,
+        mouse_button: None,
+        mouse_cell: None,
+        input_focused: false,
+        scrolled_up: 0,
     })
 }
 
 
 pub fn create() -> TerminalPlugin {
-    TerminalPlugin
+    TerminalPlugin::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `input` through a parser into `state` and returns the replies sent back to the PTY.
+    fn feed(state: &mut TerminalState, input: &str) -> Vec<u8> {
+        let mut parser = vte::Parser::new();
+        let mut handler = LogHandler { state, replies: Vec::new() };
+        for byte in input.bytes() {
+            parser.advance(&mut handler, byte);
+        }
+        handler.replies
+    }
+
+    /// Feeds `input` into a 24x80 screen, starting with the cursor at (row, col),
+    /// and returns the resulting 0-based cursor position.
+    fn cursor_after(start: (usize, usize), input: &str) -> (usize, usize) {
+        let mut state = TerminalState::new(24, 80);
+        (state.cursor_row, state.cursor_col) = start;
+        feed(&mut state, input);
+        (state.cursor_row, state.cursor_col)
+    }
+
+    /// Text of each screen row without trailing blanks.
+    fn screen_text(state: &TerminalState) -> Vec<String> {
+        state.grid().iter()
+            .map(|row| row.iter().filter(|c| !c.is_wide_continuation).map(|c| c.c).collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn cursor_position_defaults() {
+        assert_eq!(cursor_after((10, 10), "\x1b[H"), (0, 0));
+        assert_eq!(cursor_after((10, 10), "\x1b[5;7H"), (4, 6));
+        assert_eq!(cursor_after((10, 10), "\x1b[;5H"), (0, 4));
+        assert_eq!(cursor_after((10, 10), "\x1b[5;H"), (4, 0));
+        assert_eq!(cursor_after((10, 10), "\x1b[0;0H"), (0, 0));
+        assert_eq!(cursor_after((10, 10), "\x1b[3;4f"), (2, 3));
+        assert_eq!(cursor_after((0, 0), "\x1b[99;999H"), (23, 79));
+    }
+
+    #[test]
+    fn cursor_movement_defaults() {
+        for (input, expected) in [
+            ("\x1b[A", (9, 10)),
+            ("\x1b[0A", (9, 10)),
+            ("\x1b[3A", (7, 10)),
+            ("\x1b[B", (11, 10)),
+            ("\x1b[0B", (11, 10)),
+            ("\x1b[C", (10, 11)),
+            ("\x1b[0C", (10, 11)),
+            ("\x1b[D", (10, 9)),
+            ("\x1b[0D", (10, 9)),
+            ("\x1b[4D", (10, 6)),
+        ] {
+            assert_eq!(cursor_after((10, 10), input), expected, "{:?}", input);
+        }
+        // Movement stops at the screen edges
+        assert_eq!(cursor_after((1, 1), "\x1b[5A\x1b[5D"), (0, 0));
+        assert_eq!(cursor_after((22, 78), "\x1b[5B\x1b[5C"), (23, 79));
+    }
+
+    #[test]
+    fn cursor_absolute_column_and_row() {
+        assert_eq!(cursor_after((10, 10), "\x1b[G"), (10, 0));
+        assert_eq!(cursor_after((10, 10), "\x1b[0G"), (10, 0));
+        assert_eq!(cursor_after((10, 10), "\x1b[20G"), (10, 19));
+        assert_eq!(cursor_after((10, 10), "\x1b[d"), (0, 10));
+        assert_eq!(cursor_after((10, 10), "\x1b[0d"), (0, 10));
+        assert_eq!(cursor_after((10, 10), "\x1b[5d"), (4, 10));
+        assert_eq!(cursor_after((10, 10), "\x1b[100d"), (23, 10));
+    }
+
+    #[test]
+    fn resize_reflows_soft_wrapped_lines() {
+        let mut state = TerminalState::new(4, 10);
+        feed(&mut state, "abcdefghijKLM");
+        assert_eq!(screen_text(&state), ["abcdefghij", "KLM", "", ""]);
+        assert!(state.grid()[0][9].wraps);
+        assert_eq!((state.cursor_row, state.cursor_col), (1, 3));
+
+        state.resize(4, 6);
+        assert_eq!(screen_text(&state), ["abcdef", "ghijKL", "M", ""]);
+        assert!(state.grid()[0][5].wraps && state.grid()[1][5].wraps && !state.grid()[2][5].wraps);
+        assert_eq!((state.cursor_row, state.cursor_col), (2, 1));
+
+        state.resize(4, 20);
+        assert_eq!(screen_text(&state), ["abcdefghijKLM", "", "", ""]);
+        assert!(!state.grid()[0][19].wraps);
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 13));
+
+        state.resize(4, 10);
+        assert_eq!(screen_text(&state), ["abcdefghij", "KLM", "", ""]);
+        assert_eq!((state.cursor_row, state.cursor_col), (1, 3));
+    }
+
+    #[test]
+    fn resize_keeps_wide_characters_whole() {
+        let mut state = TerminalState::new(3, 10);
+        feed(&mut state, "abc漢de");
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 7));
+
+        // 漢 would straddle the new edge, so it moves down and a spacer pads the row
+        state.resize(3, 4);
+        assert_eq!(screen_text(&state), ["abc", "漢de", ""]);
+        assert!(state.grid()[0][3].is_wide_continuation && state.grid()[0][3].wraps);
+        assert_eq!(state.grid()[1][0].c, '漢');
+        assert!(state.grid()[1][1].is_wide_continuation);
+        assert_eq!((state.cursor_row, state.cursor_col), (2, 0));
+
+        // Growing drops the spacer instead of turning it into a space
+        state.resize(3, 10);
+        assert_eq!(screen_text(&state), ["abc漢de", "", ""]);
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 7));
+    }
+
+    #[test]
+    fn wide_character_printed_at_the_edge_reflows_without_a_gap() {
+        let mut state = TerminalState::new(2, 5);
+        feed(&mut state, "abcd漢");
+        assert_eq!(screen_text(&state), ["abcd", "漢"]);
+        assert!(state.grid()[0][4].is_wide_continuation && state.grid()[0][4].wraps);
+        assert_eq!((state.cursor_row, state.cursor_col), (1, 2));
+
+        state.resize(2, 10);
+        assert_eq!(screen_text(&state), ["abcd漢", ""]);
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 6));
+    }
+
+    const ROWS: [&str; 5] = ["abcdef", "ghijkl", "mnopqr", "stuvwx", "yz0123"];
+
+    /// A 5x6 screen filled with `ROWS`, cursor on the `p` in the middle row.
+    fn filled_screen() -> TerminalState {
+        let mut state = TerminalState::new(5, 6);
+        for (i, text) in ROWS.iter().enumerate() {
+            feed(&mut state, &format!("\x1b[{};1H{}", i + 1, text));
+        }
+        feed(&mut state, "\x1b[3;4H");
+        state
+    }
+
+    fn erased(input: &str) -> Vec<String> {
+        let mut state = filled_screen();
+        feed(&mut state, input);
+        assert_eq!((state.cursor_row, state.cursor_col), (2, 3), "{:?} moved the cursor", input);
+        screen_text(&state)
+    }
+
+    #[test]
+    fn erase_in_display() {
+        for input in ["\x1b[J", "\x1b[0J"] {
+            assert_eq!(erased(input), ["abcdef", "ghijkl", "mno", "", ""], "{:?}", input);
+        }
+        assert_eq!(erased("\x1b[1J"), ["", "", "    qr", "stuvwx", "yz0123"]);
+        assert_eq!(erased("\x1b[2J"), ["", "", "", "", ""]);
+
+        // Mode 3 clears only the scrollback
+        let mut state = filled_screen();
+        state.history.push(vec![Cell::default(); 6]);
+        feed(&mut state, "\x1b[3J");
+        assert!(state.history.is_empty());
+        assert_eq!(screen_text(&state), ROWS);
+    }
+
+    #[test]
+    fn erase_in_line() {
+        for input in ["\x1b[K", "\x1b[0K"] {
+            assert_eq!(erased(input), ["abcdef", "ghijkl", "mno", "stuvwx", "yz0123"], "{:?}", input);
+        }
+        assert_eq!(erased("\x1b[1K"), ["abcdef", "ghijkl", "    qr", "stuvwx", "yz0123"]);
+        assert_eq!(erased("\x1b[2K"), ["abcdef", "ghijkl", "", "stuvwx", "yz0123"]);
+
+        // Erased cells take the current background color
+        let mut state = filled_screen();
+        feed(&mut state, "\x1b[41m\x1b[K");
+        assert!(state.grid()[2][3..].iter().all(|c| c.c == ' ' && c.bg == TermColor::Indexed(1)));
+        assert_eq!(state.grid()[2][2].bg, TermColor::Default);
+    }
+
+    fn sgr(input: &str) -> TerminalState {
+        let mut state = TerminalState::new(2, 10);
+        feed(&mut state, input);
+        state
+    }
+
+    #[test]
+    fn sgr_underline_color_in_both_encodings() {
+        let rgb = Some(TermColor::Rgb(Color32::from_rgb(10, 20, 30)));
+        for (input, expected) in [
+            ("\x1b[58;5;196m", Some(TermColor::Indexed(196))),
+            ("\x1b[58:5:196m", Some(TermColor::Indexed(196))),
+            ("\x1b[58;2;10;20;30m", rgb),
+            ("\x1b[58:2::10:20:30m", rgb),
+            ("\x1b[58:2:10:20:30m", rgb),
+            ("\x1b[58;5;196m\x1b[59m", None),
+        ] {
+            assert_eq!(sgr(input).current_underline_color, expected, "{:?}", input);
+        }
+        // Parameters after a semicolon-encoded color still apply
+        let state = sgr("\x1b[58;2;10;20;30;1m");
+        assert_eq!(state.current_underline_color, rgb);
+        assert!(state.current_bold);
+    }
+
+    #[test]
+    fn sgr_underline_styles() {
+        for (input, expected) in [
+            ("\x1b[4m", Underline::Single),
+            ("\x1b[4:3m", Underline::Curly),
+            ("\x1b[4:2m", Underline::Double),
+            ("\x1b[4m\x1b[4:0m", Underline::None),
+            ("\x1b[21m", Underline::Double),
+            ("\x1b[4:3m\x1b[24m", Underline::None),
+        ] {
+            assert_eq!(sgr(input).current_underline, expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn cursor_position_report() {
+        let mut state = TerminalState::new(24, 80);
+        assert_eq!(feed(&mut state, "\x1b[5;7H\x1b[6n"), b"\x1b[5;7R");
+        assert_eq!(feed(&mut state, "\x1b[5n"), b"\x1b[0n");
+        // A pending wrap after the last column still reports the last column
+        let mut state = TerminalState::new(2, 5);
+        assert_eq!(feed(&mut state, "abcde\x1b[6n"), b"\x1b[1;5R");
+    }
+
+    /// Opening and closing many terminals must not leave reader threads behind.
+    #[cfg(unix)]
+    #[test]
+    fn closed_sessions_join_their_reader_threads() {
+        let settings = Arc::new(Mutex::new(TerminalSettings { shell: "/bin/sh".to_string(), ..TerminalSettings::default() }));
+        for _ in 0..20 {
+            let mut tab = create_terminal_tab(egui::Context::default(), settings.clone(), None, None).unwrap();
+            tab.on_close();
+            assert!(!tab.session.alive.load(Ordering::SeqCst));
+            assert!(tab.session.child.lock().is_none());
+            assert!(tab.session.reader_thread.lock().is_none());
+
+            let reaper = tab.session.reaper.lock().take().expect("shutdown starts the reaper");
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !reaper.is_finished() {
+                assert!(Instant::now() < deadline, "reader thread did not exit after shutdown");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            reaper.join().unwrap();
+        }
+    }
+
+    /// Worst case for per-run layout: every cell of a full screen has its own truecolor.
+    #[test]
+    fn truecolor_gradient_layout_fits_a_frame() {
+        const HEIGHT: usize = 60;
+        const WIDTH: usize = 200;
+        let mut state = TerminalState::new(HEIGHT, WIDTH);
+        let mut input = String::new();
+        for r in 0..HEIGHT {
+            input.push_str(&format!("\x1b[{};1H", r + 1));
+            for c in 0..WIDTH {
+                input.push_str(&format!("\x1b[38;2;{};{};128mx", c * 255 / WIDTH, r * 255 / HEIGHT));
+            }
+        }
+        feed(&mut state, &input);
+        assert_eq!(state.grid()[HEIGHT - 1][WIDTH - 1].c, 'x');
+
+        let theme = TerminalTheme::default();
+        let font_id = FontId::monospace(DEFAULT_FONT_SIZE);
+        let mut best = Duration::MAX;
+        for _ in 0..5 {
+            let start = Instant::now();
+            let jobs: Vec<LayoutJob> = state.grid().iter().map(|row| text_run_job(row, &theme, &font_id, false)).collect();
+            best = best.min(start.elapsed());
+            assert!(jobs.iter().all(|job| job.text.len() == WIDTH && job.sections.len() == WIDTH));
+        }
+        let budget = if cfg!(debug_assertions) { Duration::from_millis(100) } else { Duration::from_millis(8) };
+        assert!(best < budget, "building the layout jobs took {:?} (budget {:?})", best, budget);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_is_disabled_while_a_terminal_is_focused() {
+        let settings = Arc::new(Mutex::new(TerminalSettings { shell: "/bin/sh".to_string(), ..TerminalSettings::default() }));
+        let mut tab = create_terminal_tab(egui::Context::default(), settings, None, None).unwrap();
+        assert!(!tab.capabilities().contains(crate::TabCapabilities::SAVEABLE));
+
+        let actions = crate::plugins::code_editor::create().tab_actions();
+        assert!(actions.iter().any(|a| a.action == "code_editor.save"));
+        let enabled = crate::app::enabled_tab_actions(Some(&tab as &dyn TabInstance), &actions);
+        assert!(enabled.iter().all(|a| a.action != "code_editor.save"));
+        tab.on_close();
+    }
 }
@@ -0,0 +1,138 @@
+//! Terminal color themes. Cells keep colors the way the program named them
+//! (`TermColor`) and are resolved against the active theme when painted, so a
+//! theme change also recolors text that is already on screen.
+
+use egui::Color32;
+
+/// A color as set by SGR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TermColor {
+    /// The theme's default foreground or background.
+    Default,
+    /// 256-color palette index; 0-15 come from the theme.
+    Indexed(u8),
+    Rgb(Color32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerminalTheme {
+    pub foreground: Color32,
+    pub background: Color32,
+    pub cursor: Color32,
+    /// The 8 standard colors followed by their bright variants.
+    pub palette: [Color32; 16],
+}
+
+impl Default for TerminalTheme {
+    fn default() -> Self {
+        Self {
+            foreground: Color32::from_rgb(210, 210, 210),
+            background: Color32::from_rgb(15, 15, 15),
+            cursor: Color32::from_gray(200),
+            palette: [
+                Color32::from_rgb(0, 0, 0),       // Black
+                Color32::from_rgb(205, 0, 0),     // Red
+                Color32::from_rgb(0, 205, 0),     // Green
+                Color32::from_rgb(205, 205, 0),   // Yellow
+                Color32::from_rgb(0, 0, 238),     // Blue
+                Color32::from_rgb(205, 0, 205),   // Magenta
+                Color32::from_rgb(0, 205, 205),   // Cyan
+                Color32::from_rgb(229, 229, 229), // White
+                Color32::from_rgb(127, 127, 127), // Bright Black
+                Color32::from_rgb(255, 0, 0),     // Bright Red
+                Color32::from_rgb(0, 255, 0),     // Bright Green
+                Color32::from_rgb(255, 255, 0),   // Bright Yellow
+                Color32::from_rgb(92, 92, 255),   // Bright Blue
+                Color32::from_rgb(255, 0, 255),   // Bright Magenta
+                Color32::from_rgb(0, 255, 255),   // Bright Cyan
+                Color32::from_rgb(255, 255, 255), // Bright White
+            ],
+        }
+    }
+}
+
+/// Built-in themes as (name, foreground, background, cursor, palette) in `0xRRGGBB`.
+const PRESETS: &[(&str, u32, u32, u32, [u32; 16])] = &[
+    ("Solarized Dark", 0x839496, 0x002b36, 0x93a1a1, [
+        0x073642, 0xdc322f, 0x859900, 0xb58900, 0x268bd2, 0xd33682, 0x2aa198, 0xeee8d5,
+        0x002b36, 0xcb4b16, 0x586e75, 0x657b83, 0x839496, 0x6c71c4, 0x93a1a1, 0xfdf6e3,
+    ]),
+    ("Dracula", 0xf8f8f2, 0x282a36, 0xf8f8f2, [
+        0x21222c, 0xff5555, 0x50fa7b, 0xf1fa8c, 0xbd93f9, 0xff79c6, 0x8be9fd, 0xf8f8f2,
+        0x6272a4, 0xff6e6e, 0x69ff94, 0xffffa5, 0xd6acff, 0xff92df, 0xa4ffff, 0xffffff,
+    ]),
+    ("Gruvbox Dark", 0xebdbb2, 0x282828, 0xebdbb2, [
+        0x282828, 0xcc241d, 0x98971a, 0xd79921, 0x458588, 0xb16286, 0x689d6a, 0xa89984,
+        0x928374, 0xfb4934, 0xb8bb26, 0xfabd2f, 0x83a598, 0xd3869b, 0x8ec07c, 0xebdbb2,
+    ]),
+];
+
+fn hex(rgb: u32) -> Color32 {
+    Color32::from_rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+/// Parses `#rrggbb` (the `#` is optional).
+pub fn parse_hex(text: &str) -> Option<Color32> {
+    let digits = text.trim().trim_start_matches('#');
+    if digits.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok().map(hex)
+}
+
+pub fn to_hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+impl TerminalTheme {
+    /// Stands in for the default theme while the app uses light visuals, so an
+    /// unconfigured terminal matches the rest of the UI.
+    pub fn light() -> Self {
+        let mut theme = Self::default();
+        theme.foreground = Color32::from_rgb(40, 40, 40);
+        theme.background = Color32::from_rgb(250, 250, 250);
+        theme.cursor = Color32::from_gray(60);
+        // Darken the colors that are unreadable on white
+        theme.palette[3] = Color32::from_rgb(160, 130, 0);
+        theme.palette[7] = Color32::from_rgb(120, 120, 120);
+        theme.palette[11] = Color32::from_rgb(190, 160, 0);
+        theme.palette[15] = Color32::from_rgb(60, 60, 60);
+        theme
+    }
+
+    /// Names of the themes `preset` accepts, starting with the built-in default.
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        std::iter::once("Default").chain(PRESETS.iter().map(|preset| preset.0))
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("Default") {
+            return Some(Self::default());
+        }
+        let (_, fg, bg, cursor, palette) = PRESETS.iter().find(|preset| preset.0.eq_ignore_ascii_case(name))?;
+        Some(Self {
+            foreground: hex(*fg),
+            background: hex(*bg),
+            cursor: hex(*cursor),
+            palette: palette.map(hex),
+        })
+    }
+
+    /// Resolves `color`; `TermColor::Default` becomes `default` (the theme's
+    /// foreground or background, depending on where the color is used).
+    pub fn resolve(&self, color: TermColor, default: Color32) -> Color32 {
+        match color {
+            TermColor::Default => default,
+            TermColor::Indexed(n @ 0..=15) => self.palette[n as usize],
+            // 6x6x6 color cube
+            TermColor::Indexed(n @ 16..=231) => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let n = n - 16;
+                Color32::from_rgb(level(n / 36), level(n / 6 % 6), level(n % 6))
+            }
+            // Grayscale ramp
+            TermColor::Indexed(n) => Color32::from_gray(8 + (n - 232) * 10),
+            TermColor::Rgb(color) => color,
+        }
+    }
+}